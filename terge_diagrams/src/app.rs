@@ -3,12 +3,18 @@ use std::u16;
 
 use crossterm::event::KeyEvent;
 use crossterm::event::{Event, KeyCode, MouseButton, MouseEvent, MouseEventKind};
-use log::{debug, error};
-use terge::common::{Arithmetics, U16Point, i32point_to_u16point, u16point_to_i32point};
+use log::{debug, error, warn};
+use serde::{Deserialize, Serialize};
+use terge::common::{I32Point, U16Point};
 use terge::event_group::EventGroup;
-use terge::gfx::Gfx;
+use terge::gfx::{CursorShape, Gfx};
+use terge::layout::Constraint::{Fill, Length};
+use terge::layout::Layout;
 use terge::line::{Line, LinePointsIterator};
 use terge::rect::Rect;
+use terge::save::SaveSlots;
+use terge::spatial::SpatialIndex;
+use terge::widget::{Label, Span, Style, Ui, WidgetId};
 
 use crate::common::*;
 use crate::freehand::Freehand;
@@ -17,6 +23,41 @@ use crate::rect::*;
 use crate::text::*;
 use crate::text_editor::*;
 
+/// What is kept of a diagram between runs.
+#[derive(Default, Serialize, Deserialize)]
+struct SavedDiagram {
+    rectangles: Vec<RectObject>,
+    lines: Vec<LineObject>,
+    texts: Vec<TextObject>,
+    freehands: Vec<Freehand>,
+}
+
+impl SavedDiagram {
+    /// Colors are indices into `COLORS`, the ones out of range (eg. in a hand edited or newer
+    /// save) fall back to the first color.
+    fn fix_colors(&mut self) {
+        let fix = |color: &mut usize, object: String| {
+            if *color >= COLORS.len() {
+                error!("Invalid color {} of saved {}", color, object);
+                *color = 0;
+            }
+        };
+
+        for rect_obj in &mut self.rectangles {
+            fix(&mut rect_obj.color, format!("rectangle {}", rect_obj.id));
+        }
+        for line_obj in &mut self.lines {
+            fix(&mut line_obj.color, format!("line {}", line_obj.id));
+        }
+        for text_obj in &mut self.texts {
+            fix(&mut text_obj.color, format!("text {}", text_obj.id));
+        }
+        for (i, freehand) in self.freehands.iter_mut().enumerate() {
+            fix(&mut freehand.color, format!("freehand {}", i));
+        }
+    }
+}
+
 pub struct App {
     id_provider: u64,
     action: Option<Action>,
@@ -26,26 +67,95 @@ pub struct App {
     rectangles: HashMap<IdType, RectObject>,
     lines: HashMap<IdType, LineObject>,
     texts: HashMap<IdType, TextObject>,
+    rectangle_index: SpatialIndex<IdType>,
+    line_index: SpatialIndex<IdType>,
+    text_index: SpatialIndex<IdType>,
     pointer_trace: VecDeque<PointerPoint>,
-    click_trace: VecDeque<(U16Point, u128)>,
+    click_trace: VecDeque<ClickTrace>,
     freehands: Vec<Freehand>,
+    ui: Ui,
+    status_bar: WidgetId,
+    /// `None` when there is nowhere to save.
+    saves: Option<SaveSlots>,
 }
 
 impl App {
     pub fn new() -> Self {
+        let mut ui = Ui::new();
+        let status_bar = ui.add(
+            Label::from_spans(Rect::default(), vec![])
+                .with_style(Style::new(STATUS_BAR_COLOR_CODE))
+                .with_fill(),
+        );
+
         Self {
             id_provider: 0,
             action: None,
             intent: Intent::Rect,
-            current_mouse_pos: (0, 0),
+            current_mouse_pos: U16Point::new(0, 0),
             current_color: 0,
             rectangles: HashMap::new(),
             lines: HashMap::new(),
             texts: HashMap::new(),
+            rectangle_index: SpatialIndex::default(),
+            line_index: SpatialIndex::default(),
+            text_index: SpatialIndex::default(),
             pointer_trace: VecDeque::new(),
             click_trace: VecDeque::new(),
             freehands: vec![],
+            ui,
+            status_bar,
+            saves: SaveSlots::new("terge_diagrams", DIAGRAM_SAVE_VERSION)
+                .inspect_err(|err| warn!("Saving is disabled: {}", err))
+                .ok(),
+        }
+    }
+
+    fn save(&self) {
+        let Some(saves) = &self.saves else {
+            return;
+        };
+
+        let diagram = SavedDiagram {
+            rectangles: self.rectangles.values().cloned().collect(),
+            lines: self.lines.values().cloned().collect(),
+            texts: self.texts.values().cloned().collect(),
+            freehands: self.freehands.clone(),
+        };
+        if let Err(err) = saves.save(DIAGRAM_SAVE_SLOT, &diagram) {
+            error!("Failed saving diagram: {}", err);
+        }
+    }
+
+    fn load(&mut self) {
+        let Some(saves) = &self.saves else {
+            return;
+        };
+
+        let mut diagram = match saves.load::<SavedDiagram>(DIAGRAM_SAVE_SLOT) {
+            Ok(diagram) => diagram.unwrap_or_default(),
+            Err(err) => {
+                error!("Failed loading diagram: {}", err);
+                return;
+            }
+        };
+        diagram.fix_colors();
+
+        let ids = diagram.rectangles.iter().map(|rect_obj| rect_obj.id);
+        let ids = ids.chain(diagram.lines.iter().map(|line_obj| line_obj.id));
+        let ids = ids.chain(diagram.texts.iter().map(|text_obj| text_obj.id));
+        self.id_provider = self.id_provider.max(ids.max().unwrap_or(0));
+
+        for rect_obj in diagram.rectangles {
+            self.insert_rectangle(rect_obj);
         }
+        for line_obj in diagram.lines {
+            self.insert_line(line_obj);
+        }
+        for text_obj in diagram.texts {
+            self.insert_text(text_obj);
+        }
+        self.freehands.extend(diagram.freehands);
     }
 
     fn get_id(&mut self) -> u64 {
@@ -71,7 +181,7 @@ impl App {
             Intent::Pointer => {
                 self.action = Some(Action::Pointer);
                 self.click_trace
-                    .push_front((self.current_mouse_pos, get_current_time_ms() + 2000));
+                    .push_front(ClickTrace::new(self.current_mouse_pos));
             }
             Intent::Freehand => {
                 self.action = Some(Action::Freehand(FreehandAction {
@@ -87,17 +197,14 @@ impl App {
                 let line_action = self.action.take().unwrap().unwrap_as_line();
                 let new_id = self.get_id();
 
-                self.lines.insert(
+                self.insert_line(LineObject::new(
                     new_id,
-                    LineObject::new(
-                        new_id,
-                        Line {
-                            start: line_action.start,
-                            end: self.current_mouse_pos,
-                        },
-                        self.current_color,
-                    ),
-                );
+                    Line {
+                        start: line_action.start,
+                        end: self.current_mouse_pos,
+                    },
+                    self.current_color,
+                ));
 
                 self.update_line_start_anchor(new_id);
                 self.update_line_end_anchor(new_id);
@@ -105,14 +212,11 @@ impl App {
             Some(Action::Rect(_)) => {
                 let rect_action = self.action.take().unwrap().unwrap_as_rect();
                 let new_id = self.get_id();
-                self.rectangles.insert(
+                self.insert_rectangle(RectObject::new(
                     new_id,
-                    RectObject::new(
-                        new_id,
-                        self.current_color,
-                        Rect::new_from_unordered_points(rect_action.start, self.current_mouse_pos),
-                    ),
-                );
+                    self.current_color,
+                    Rect::new_from_unordered_points(rect_action.start, self.current_mouse_pos),
+                ));
             }
             Some(Action::DragLineStart(_)) => {
                 let action = self.action.take().unwrap().unwrap_as_drag_line_start();
@@ -127,9 +231,11 @@ impl App {
                 let rect_id = self
                     .rectangle_under_point(self.current_mouse_pos)
                     .map(|rect_obj| rect_obj.id);
-                self.texts
-                    .get_mut(&action.text_id)
-                    .map(|text_obj| text_obj.anchor_rect_id = rect_id);
+                if let Some(text_obj) = self.texts.get_mut(&action.text_id) {
+                    // Anchoring changes the alignment, and so the bounds.
+                    text_obj.anchor_rect_id = rect_id;
+                    self.text_index.update(text_obj.id, text_obj.bounds());
+                }
                 self.action = None;
             }
             Some(Action::Freehand(_)) => {
@@ -155,16 +261,13 @@ impl App {
                 .map(|rect_obj| rect_obj.id);
 
             let id = self.get_id();
-            self.texts.insert(
+            self.insert_text(TextObject::new(
                 id,
-                TextObject::new(
-                    id,
-                    text_action.start,
-                    text_action.editor.lines,
-                    anchor_rect_id,
-                    self.current_color,
-                ),
-            );
+                text_action.start,
+                text_action.editor.lines,
+                anchor_rect_id,
+                self.current_color,
+            ));
             self.action = None;
         } else {
             unreachable!("Must be text action mode")
@@ -193,84 +296,101 @@ impl App {
             .map(|line_obj| line_obj.end_anchor_rect_id = anchor);
     }
 
-    fn rectangle_header_under_point(&self, p: U16Point) -> Option<&RectObject> {
-        for (_id, rect_obj) in &self.rectangles {
-            if rect_obj.rect.is_point_on_header(p) {
-                return Some(rect_obj);
-            }
-        }
+    fn insert_rectangle(&mut self, rect_obj: RectObject) {
+        self.rectangle_index.insert(rect_obj.id, rect_obj.bounds());
+        self.rectangles.insert(rect_obj.id, rect_obj);
+    }
 
-        None
+    fn insert_line(&mut self, line_obj: LineObject) {
+        self.line_index.insert(line_obj.id, line_obj.bounds());
+        self.lines.insert(line_obj.id, line_obj);
     }
 
-    fn rectangle_resize_point_under_point(&self, p: U16Point) -> Option<&RectObject> {
-        for (_id, rect_obj) in &self.rectangles {
-            if rect_obj.rect.end() == p {
-                return Some(rect_obj);
-            }
-        }
+    fn insert_text(&mut self, text_obj: TextObject) {
+        self.text_index.insert(text_obj.id, text_obj.bounds());
+        self.texts.insert(text_obj.id, text_obj);
+    }
 
-        None
+    fn remove_rectangle(&mut self, id: IdType) {
+        self.rectangle_index.remove(id);
+        self.rectangles.remove(&id);
     }
 
-    /// Find the most likely (smallest) rectangle.
-    fn rectangle_under_point(&self, p: U16Point) -> Option<&RectObject> {
-        let mut out = None;
-        let mut smallest_area = u16::MAX;
+    fn remove_line(&mut self, id: IdType) {
+        self.line_index.remove(id);
+        self.lines.remove(&id);
+    }
 
-        for (_id, rect_obj) in &self.rectangles {
-            if rect_obj.rect.is_point_on(p) && rect_obj.rect.area() < smallest_area {
-                smallest_area = rect_obj.rect.area();
-                out = Some(rect_obj);
-            }
-        }
+    fn remove_text(&mut self, id: IdType) {
+        self.text_index.remove(id);
+        self.texts.remove(&id);
+    }
+
+    fn rectangles_at(&self, p: U16Point) -> impl Iterator<Item = &RectObject> {
+        self.rectangle_index
+            .query_point(p)
+            .into_iter()
+            .filter_map(|id| self.rectangles.get(&id))
+    }
 
-        out
+    fn lines_at(&self, p: U16Point) -> impl Iterator<Item = &LineObject> {
+        self.line_index
+            .query_point(p)
+            .into_iter()
+            .filter_map(|id| self.lines.get(&id))
+    }
+
+    fn texts_at(&self, p: U16Point) -> impl Iterator<Item = &TextObject> {
+        self.text_index
+            .query_point(p)
+            .into_iter()
+            .filter_map(|id| self.texts.get(&id))
+    }
+
+    fn rectangle_header_under_point(&self, p: U16Point) -> Option<&RectObject> {
+        self.rectangles_at(p)
+            .find(|rect_obj| rect_obj.rect.is_point_on_header(p))
+    }
+
+    fn rectangle_resize_point_under_point(&self, p: U16Point) -> Option<&RectObject> {
+        self.rectangles_at(p)
+            .find(|rect_obj| rect_obj.rect.end() == p)
+    }
+
+    /// Find the most likely (smallest) rectangle.
+    fn rectangle_under_point(&self, p: U16Point) -> Option<&RectObject> {
+        self.rectangles_at(p)
+            .filter(|rect_obj| rect_obj.rect.is_point_on(p))
+            .min_by_key(|rect_obj| rect_obj.rect.area())
     }
 
     fn text_edit_under_point(&self, p: U16Point) -> Option<&TextObject> {
-        for (_id, text_obj) in &self.texts {
-            if text_obj.is_edit_point(p) {
-                return Some(text_obj);
-            }
-        }
-        None
+        self.texts_at(p).find(|text_obj| text_obj.is_edit_point(p))
     }
 
     fn text_drag_under_point(&self, p: U16Point) -> Option<&TextObject> {
-        for (_id, text_obj) in &self.texts {
-            if text_obj.is_drag_point(p) {
-                return Some(text_obj);
-            }
-        }
-        None
+        self.texts_at(p).find(|text_obj| text_obj.is_drag_point(p))
     }
 
     fn line_with_start_under_point(&mut self, p: U16Point) -> Option<&mut LineObject> {
-        for (_id, line_obj) in &mut self.lines {
-            if line_obj.line.start == p {
-                return Some(line_obj);
-            }
-        }
-        None
+        let id = self
+            .lines_at(p)
+            .find(|line_obj| line_obj.line.start == p)?
+            .id;
+        self.lines.get_mut(&id)
     }
 
     fn line_with_end_under_point(&mut self, p: U16Point) -> Option<&mut LineObject> {
-        for (_id, line_obj) in &mut self.lines {
-            if line_obj.line.end == p {
-                return Some(line_obj);
-            }
-        }
-        None
+        let id = self.lines_at(p).find(|line_obj| line_obj.line.end == p)?.id;
+        self.lines.get_mut(&id)
     }
 
     fn line_under_point(&mut self, p: U16Point) -> Option<&mut LineObject> {
-        for (_id, line_obj) in &mut self.lines {
-            if line_obj.is_point_on(p) {
-                return Some(line_obj);
-            }
-        }
-        None
+        let id = self
+            .lines_at(p)
+            .find(|line_obj| line_obj.is_point_on(p))?
+            .id;
+        self.lines.get_mut(&id)
     }
 
     fn is_active_action_text(&self) -> bool {
@@ -308,7 +428,7 @@ impl App {
                 editor: TextEditor::new_with_lines(text_obj.lines.clone()),
             }));
 
-            self.texts.remove(&id);
+            self.remove_text(id);
         } else if let Some(text_obj) = self.text_drag_under_point(self.current_mouse_pos) {
             self.action = Some(Action::DragText(DragTextAction {
                 text_id: text_obj.id,
@@ -316,11 +436,10 @@ impl App {
         } else if let Some(rect_obj) = self.rectangle_header_under_point(self.current_mouse_pos) {
             self.action = Some(Action::DragRectangle(DragRectangleAction {
                 rectangle_id: rect_obj.id,
-                offset: u16point_to_i32point(self.current_mouse_pos)
-                    .sub(u16point_to_i32point(rect_obj.rect.start)),
+                offset: self.current_mouse_pos.cast::<i32>() - rect_obj.rect.start.cast(),
             }));
         } else {
-            self.start_action((mouse_event.column, mouse_event.row));
+            self.start_action(U16Point::new(mouse_event.column, mouse_event.row));
         }
     }
 
@@ -329,15 +448,26 @@ impl App {
     }
 
     fn delete_under_point(&mut self, p: U16Point) {
-        if delete_one_from_list_with_cond(&mut self.texts, |o| o.is_point_on(p)) {
+        let text_id = self
+            .texts_at(p)
+            .find(|text_obj| text_obj.is_point_on(p))
+            .map(|text_obj| text_obj.id);
+        if let Some(id) = text_id {
+            self.remove_text(id);
             return;
         }
-        if delete_one_from_list_with_cond(&mut self.lines, |o| o.is_point_on(p)) {
+
+        let line_id = self
+            .lines_at(p)
+            .find(|line_obj| line_obj.is_point_on(p))
+            .map(|line_obj| line_obj.id);
+        if let Some(id) = line_id {
+            self.remove_line(id);
             return;
         }
 
         if let Some(id) = self.rectangle_under_point(p).map(|rect_obj| rect_obj.id) {
-            self.rectangles.remove(&id);
+            self.remove_rectangle(id);
         }
     }
 
@@ -349,7 +479,7 @@ impl App {
         }
     }
 
-    fn on_key_press_not_edit_mode(&mut self, key_code: &KeyCode) {
+    fn on_key_press_not_edit_mode(&mut self, key_code: &KeyCode, gfx: &mut Gfx) {
         match *key_code {
             KeyCode::Char(c) => match c {
                 'r' => self.intent = Intent::Rect,
@@ -357,6 +487,14 @@ impl App {
                 't' => self.intent = Intent::Text,
                 'p' => self.intent = Intent::Pointer,
                 'f' => self.intent = Intent::Freehand,
+                's' => self.save(),
+                'c' => self.copy_text_under_point(self.current_mouse_pos, gfx, false),
+                'x' => self.copy_text_under_point(self.current_mouse_pos, gfx, true),
+                'v' => {
+                    if let Some(text) = gfx.clipboard.text().map(str::to_string) {
+                        self.paste(&text);
+                    }
+                }
                 num_c @ '0'..='9' => self.current_color = (num_c as u8 - b'0') as usize,
                 _ => {}
             },
@@ -365,18 +503,44 @@ impl App {
         }
     }
 
+    fn copy_text_under_point(&mut self, p: U16Point, gfx: &mut Gfx, cut: bool) {
+        let Some(text_obj) = self.texts_at(p).find(|text_obj| text_obj.is_point_on(p)) else {
+            return;
+        };
+
+        let id = text_obj.id;
+        gfx.clipboard.copy(&text_obj.lines.join("\n"));
+        if cut {
+            self.remove_text(id);
+        }
+    }
+
+    /// Into the text being edited, or as a new text at the mouse.
+    fn paste(&mut self, text: &str) {
+        match self.action.as_mut() {
+            Some(Action::Text(text_action)) => text_action.editor.insert_str(text),
+            Some(_) => {}
+            None => {
+                let mut editor = TextEditor::new();
+                editor.insert_str(text);
+                self.action = Some(Action::Text(TextAction {
+                    start: self.current_mouse_pos,
+                    editor,
+                }));
+                self.end_text_mode();
+            }
+        }
+    }
+
     fn on_update_current_action(&mut self) {
         match &mut self.action {
             Some(Action::DragRectangle(drag_rect_action)) => {
                 self.rectangles
                     .get_mut(&drag_rect_action.rectangle_id)
                     .map(|rect_obj| {
-                        let mut new_pos = u16point_to_i32point(self.current_mouse_pos)
-                            .sub(drag_rect_action.offset);
-                        if new_pos.0 < 0 {
-                            new_pos.0 = 0;
-                        }
-                        rect_obj.rect.start = i32point_to_u16point(new_pos)
+                        let new_pos: I32Point =
+                            self.current_mouse_pos.cast() - drag_rect_action.offset;
+                        rect_obj.rect.start = new_pos.saturating_u16()
                     });
             }
             Some(Action::ResizeRectangle(resize_rect_action)) => {
@@ -429,6 +593,31 @@ impl App {
         }
     }
 
+    /// Keeps the index in sync with the object moved by the current action.
+    fn reindex_current_action_target(&mut self) {
+        match self.action {
+            Some(Action::DragRectangle(DragRectangleAction { rectangle_id, .. }))
+            | Some(Action::ResizeRectangle(ResizeRectangleAction { rectangle_id, .. })) => {
+                if let Some(rect_obj) = self.rectangles.get(&rectangle_id) {
+                    self.rectangle_index.update(rectangle_id, rect_obj.bounds());
+                }
+            }
+            Some(Action::DragLineStart(DragLineStartAction { line_id }))
+            | Some(Action::DragLineEnd(DragLineEndAction { line_id }))
+            | Some(Action::LineSegment(LineSegmentAction { line_id })) => {
+                if let Some(line_obj) = self.lines.get(&line_id) {
+                    self.line_index.update(line_id, line_obj.bounds());
+                }
+            }
+            Some(Action::DragText(DragTextAction { text_id })) => {
+                if let Some(text_obj) = self.texts.get(&text_id) {
+                    self.text_index.update(text_id, text_obj.bounds());
+                }
+            }
+            _ => {}
+        }
+    }
+
     fn on_update_lines_state(&mut self) {
         for (_id, line_obj) in self.lines.iter_mut() {
             if let Some(rect_obj) = line_obj
@@ -456,6 +645,8 @@ impl App {
                     line_obj.line.end = intersection;
                 }
             }
+
+            self.line_index.update(line_obj.id, line_obj.bounds());
         }
     }
 
@@ -473,16 +664,18 @@ impl App {
 
                 if !is_text_dragged {
                     text_obj.start = p;
+                    self.text_index.update(text_obj.id, text_obj.bounds());
                 }
             }
         }
     }
 
-    fn on_update(&mut self) {
+    fn on_update(&mut self, delta_ms: u128) {
         self.on_update_current_action();
+        self.reindex_current_action_target();
         self.on_update_lines_state();
         self.on_update_text_state();
-        self.update_pointer_trace();
+        self.update_pointer_trace(delta_ms);
     }
 
     fn populate_pointer_trace(&mut self) {
@@ -501,7 +694,36 @@ impl App {
         }
     }
 
-    fn update_pointer_trace(&mut self) {
+    fn update_status_bar(&mut self, gfx: &Gfx) {
+        let Some(status_bar) = self.ui.get_mut::<Label>(self.status_bar) else {
+            return;
+        };
+
+        let (color_code, color_name) = COLORS[self.current_color];
+        status_bar.rect =
+            Layout::vertical([Fill(1), Length(1)]).split(&gfx.screen_rect())[1].unwrap_or_default();
+        status_bar.spans = vec![
+            Span::plain("  "),
+            Span::colored(format!(" Intent: {:?} ", self.intent), DEFAULT_COLOR_CODE),
+            Span::plain(" "),
+            Span::colored(
+                format!(
+                    " Active: {} ",
+                    self.action
+                        .as_ref()
+                        .map(|a| a.to_string_short())
+                        .unwrap_or("-")
+                ),
+                DEFAULT_COLOR_CODE,
+            ),
+            Span::plain(" "),
+            Span::colored(" Color: ", DEFAULT_COLOR_CODE),
+            Span::colored(color_name, color_code),
+            Span::colored(" ", DEFAULT_COLOR_CODE),
+        ];
+    }
+
+    fn update_pointer_trace(&mut self, delta_ms: u128) {
         let current_time_ms = get_current_time_ms();
 
         loop {
@@ -514,15 +736,11 @@ impl App {
             break;
         }
 
-        loop {
-            if let Some((_, deadline)) = self.click_trace.back() {
-                if *deadline < current_time_ms {
-                    self.click_trace.pop_back();
-                    continue;
-                }
-            }
-            break;
+        for click_trace in &mut self.click_trace {
+            click_trace.phase.advance(delta_ms);
         }
+        self.click_trace
+            .retain(|click_trace| !click_trace.phase.is_finished());
     }
 
     fn on_mouse_middle_down(&mut self) {
@@ -573,15 +791,15 @@ impl terge::App for App {
 
         for freehand in &self.freehands {
             for p in &freehand.points {
-                gfx.draw_text(POINTER_STR, p.0, p.1, COLORS[freehand.color].0);
+                gfx.draw_text(POINTER_STR, p.x, p.y, COLORS[freehand.color].0);
             }
         }
 
         for trace in &self.pointer_trace {
             gfx.draw_text(
                 POINTER_STR,
-                trace.pos.0,
-                trace.pos.1,
+                trace.pos.x,
+                trace.pos.y,
                 self.current_color_code(),
             );
         }
@@ -601,15 +819,19 @@ impl terge::App for App {
                 Action::Text(text_action) => {
                     gfx.draw_multiline_text(
                         &text_action.editor.lines,
-                        text_action.start.0,
-                        text_action.start.1,
+                        text_action.start.x,
+                        text_action.start.y,
                         self.current_color_code(),
                     );
-                    gfx.draw_text_to_current_pos("_");
+                    let cursor_pos = text_action.editor.cursor_pos();
+                    gfx.show_cursor(
+                        text_action.start + U16Point::new(cursor_pos.x as u16, cursor_pos.y as u16),
+                        CursorShape::BlinkingBar,
+                    );
                 }
                 Action::Freehand(action) => {
                     for p in &action.points {
-                        gfx.draw_text(POINTER_STR, p.0, p.1, self.current_color_code());
+                        gfx.draw_text(POINTER_STR, p.x, p.y, self.current_color_code());
                     }
                 }
                 Action::DragRectangle { .. }
@@ -621,50 +843,34 @@ impl terge::App for App {
                 | Action::Pointer => {
                     gfx.draw_text(
                         POINTER_STR,
-                        self.current_mouse_pos.0,
-                        self.current_mouse_pos.1,
+                        self.current_mouse_pos.x,
+                        self.current_mouse_pos.y,
                         self.current_color_code(),
                     );
                 }
             };
         }
 
-        gfx.draw_text_to_current_pos("\x1B[100m");
-        gfx.draw_text_uncoloured(&" ".repeat(gfx.width as usize), 0, gfx.height - 1);
-        gfx.draw_text_to_current_pos("\x1B[0m");
-
-        gfx.draw_text_uncoloured(
-            &format!(
-                " Intent: {:?} \x1B[100m \x1B[0m Active: {} \x1B[100m \x1B[0m Color: \x1B[{}m{}\x1B[0m ",
-                self.intent,
-                self.action
-                    .as_ref()
-                    .map(|a| a.to_string_short())
-                    .unwrap_or("-"),
-                COLORS[self.current_color as usize].0,
-                COLORS[self.current_color as usize].1
-            ),
-            2,
-            gfx.height - 1,
-        );
+        self.ui.draw(gfx);
 
-        let current_time_ms = get_current_time_ms();
-        let start_mod = (current_time_ms / 60) % CLICK_TRACE_STRS.len() as u128;
-        for (click_pos, _) in &self.click_trace {
+        for click_trace in &self.click_trace {
+            let start_mod = click_trace.phase.value() as usize;
             for (i, [xoffs, yoffs]) in CLICK_TRACE_MAP.iter().enumerate() {
                 gfx.draw_text(
-                    CLICK_TRACE_STRS[(start_mod as usize + i) % CLICK_TRACE_STRS.len()],
-                    (click_pos.0 as i32 + *xoffs as i32) as u16,
-                    (click_pos.1 as i32 + *yoffs as i32) as u16,
+                    CLICK_TRACE_STRS[(start_mod + i) % CLICK_TRACE_STRS.len()],
+                    (click_trace.pos.x as i32 + *xoffs as i32) as u16,
+                    (click_trace.pos.y as i32 + *yoffs as i32) as u16,
                     self.current_color_code(),
                 );
             }
         }
     }
 
-    fn reset(&mut self, _gfx: &mut Gfx) {}
+    fn reset(&mut self, _gfx: &mut Gfx) {
+        self.load();
+    }
 
-    fn update(&mut self, events: &EventGroup, _gfx: &mut Gfx) -> bool {
+    fn update(&mut self, events: &EventGroup, gfx: &mut Gfx) -> bool {
         if let Some(last_mouse_pos) = events.last_mouse_pos() {
             self.current_mouse_pos = last_mouse_pos;
         }
@@ -682,6 +888,7 @@ impl terge::App for App {
                         self.on_mouse_middle_down();
                     }
                 }
+                Event::Paste(text) => self.paste(text),
                 Event::Key(key_event) => {
                     if key_event.is_press() {
                         if self.is_active_action_text() {
@@ -691,7 +898,7 @@ impl terge::App for App {
                                 self.text_edit_mode_update(&key_event);
                             }
                         } else {
-                            self.on_key_press_not_edit_mode(&key_event.code);
+                            self.on_key_press_not_edit_mode(&key_event.code, gfx);
                         }
                     }
                 }
@@ -699,8 +906,53 @@ impl terge::App for App {
             }
         }
 
-        self.on_update();
+        // Double click with the pointer starts a text, anchored if it's on a rectangle.
+        if self.intent == Intent::Pointer
+            && self.action.is_none()
+            && let Some(click) = events.mouse.double_clicked(MouseButton::Left)
+        {
+            self.action = Some(Action::Text(TextAction {
+                start: click.pos,
+                editor: TextEditor::new(),
+            }));
+        }
+
+        self.on_update(events.delta_ms);
+        self.update_status_bar(gfx);
 
         true
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn out_of_range_saved_colors_fall_back() {
+        let dir = std::env::temp_dir().join(format!("terge-diagrams-{}", std::process::id()));
+        let saves = SaveSlots::in_dir(&dir, DIAGRAM_SAVE_VERSION);
+        let rect = Rect::new(U16Point::new(1, 1), U16Point::new(4, 2));
+        let line = Line {
+            start: U16Point::new(0, 0),
+            end: U16Point::new(5, 0),
+        };
+        let diagram = SavedDiagram {
+            rectangles: vec![RectObject::new(1, 3, rect), RectObject::new(2, 99, rect)],
+            lines: vec![LineObject::new(3, line, usize::MAX)],
+            texts: vec![],
+            freehands: vec![Freehand::new(vec![U16Point::new(2, 2)], COLORS.len())],
+        };
+        saves.save(DIAGRAM_SAVE_SLOT, &diagram).unwrap();
+
+        let mut app = App::new();
+        app.saves = Some(saves);
+        app.load();
+
+        assert_eq!(app.rectangles[&1].color, 3);
+        assert_eq!(app.rectangles[&2].color, 0);
+        assert_eq!(app.lines[&3].color, 0);
+        assert_eq!(app.freehands[0].color, 0);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,10 +1,9 @@
-use std::collections::HashMap;
-
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 use terge::{
     common::{I32Point, U16Point, intersection_of_rect_and_line},
     line::Line,
     rect::Rect,
+    tween::{Easing, Tween},
 };
 
 use crate::text_editor::TextEditor;
@@ -49,6 +48,10 @@ pub(crate) const COLORS: [(u8, &'static str); 10] = [
     (96, "Light cyan"),
 ];
 pub(crate) const DEFAULT_COLOR_CODE: u8 = COLORS[0].0;
+pub(crate) const STATUS_BAR_COLOR_CODE: u8 = 100;
+/// Slot the diagram is saved into with `s` and loaded from on start.
+pub(crate) const DIAGRAM_SAVE_SLOT: &str = "diagram";
+pub(crate) const DIAGRAM_SAVE_VERSION: u32 = 1;
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Intent {
@@ -143,16 +146,26 @@ pub struct PointerPoint {
     pub deadline: u128,
 }
 
-pub fn intersection_of_rect_and_anchored_line(rect: &Rect, line: &Line) -> Option<U16Point> {
-    let intersections = intersection_of_rect_and_line(rect, line);
+pub struct ClickTrace {
+    pub pos: U16Point,
+    /// Index into `CLICK_TRACE_STRS` the animation starts at, cycling while the trace lives.
+    pub phase: Tween<f32>,
+}
+
+impl ClickTrace {
+    pub fn new(pos: U16Point) -> Self {
+        let cycle_ms = CLICK_TRACE_STEP_MS * CLICK_TRACE_STRS.len() as u128;
 
-    for p in intersections {
-        if line.x_range().contains(&p.0) && line.y_range().contains(&p.1) {
-            return Some(p);
+        Self {
+            pos,
+            phase: Tween::new(0.0, CLICK_TRACE_STRS.len() as f32, cycle_ms, Easing::Linear)
+                .with_repeat((CLICK_TRACE_LIFETIME_MS / cycle_ms) as u32),
         }
     }
+}
 
-    None
+pub fn intersection_of_rect_and_anchored_line(rect: &Rect, line: &Line) -> Option<U16Point> {
+    intersection_of_rect_and_line(rect, line).into_iter().next()
 }
 
 pub(crate) trait KeyEventUtil {
@@ -165,24 +178,6 @@ impl KeyEventUtil for KeyEvent {
     }
 }
 
-pub fn delete_one_from_list_with_cond<K, V, C>(list: &mut HashMap<K, V>, cond: C) -> bool
-where
-    C: Fn(&mut V) -> bool,
-{
-    let mut done = false;
-
-    list.retain(|_, o| {
-        if !done && cond(o) {
-            done = true;
-            false
-        } else {
-            true
-        }
-    });
-
-    done
-}
-
 pub(crate) fn get_current_time_ms() -> u128 {
     std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
@@ -209,4 +204,6 @@ pub const CLICK_TRACE_MAP: [[i8; 2]; 16] = [
     [-2, -1],
 ];
 
+pub const CLICK_TRACE_STEP_MS: u128 = 60;
+pub const CLICK_TRACE_LIFETIME_MS: u128 = 2000;
 pub const CLICK_TRACE_STRS: [&'static str; 5] = [" ", "░", "▒", "▓", "▉"];
@@ -1,5 +1,7 @@
+use serde::{Deserialize, Serialize};
 use terge::common::U16Point;
 
+#[derive(Clone, Serialize, Deserialize)]
 pub(crate) struct Freehand {
     pub(crate) points: Vec<U16Point>,
     pub(crate) color: usize,
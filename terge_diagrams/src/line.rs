@@ -1,7 +1,9 @@
-use terge::{common::U16Point, line::Line};
+use serde::{Deserialize, Serialize};
+use terge::{common::U16Point, line::Line, rect::Rect};
 
 use crate::common::IdType;
 
+#[derive(Clone, Serialize, Deserialize)]
 pub struct LineObject {
     pub id: IdType,
     pub line: Line,
@@ -44,6 +46,15 @@ impl LineObject {
         }
     }
 
+    /// Covers both ends and the segment point.
+    pub(crate) fn bounds(&self) -> Rect {
+        let bounds = Rect::new_from_unordered_points(self.line.start, self.line.end);
+        match self.segment {
+            Some(segment) => bounds.union(&Rect::new(segment, U16Point::new(0, 0))),
+            None => bounds,
+        }
+    }
+
     pub(crate) fn start_line_segment(&self) -> Line {
         if let Some(segment) = self.segment {
             Line {
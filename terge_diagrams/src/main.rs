@@ -1,3 +1,4 @@
+use crossterm::event::KeyCode;
 use terge::Terge;
 
 use crate::app::App;
@@ -15,5 +16,7 @@ fn main() {
 
     let mut engine = Terge::new(Box::new(App::new()));
     engine.set_target_fps(60);
+    engine.set_record_hotkey(KeyCode::F(12), "diagrams.cast");
+    engine.set_capture_hotkey(KeyCode::F(11), "diagrams.svg");
     engine.run();
 }
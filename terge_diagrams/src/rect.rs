@@ -1,3 +1,4 @@
+use serde::{Deserialize, Serialize};
 use terge::{
     common::{U16Point, point_pair_minmax},
     rect::Rect,
@@ -5,6 +6,7 @@ use terge::{
 
 use crate::common::IdType;
 
+#[derive(Clone, Serialize, Deserialize)]
 pub struct RectObject {
     pub id: IdType,
     pub color: usize,
@@ -17,10 +19,10 @@ impl RectObject {
     }
 
     pub fn resize(&mut self, previous_start: U16Point, end: U16Point) {
-        let (min_x, min_y, max_x, max_y) = point_pair_minmax(previous_start, end);
+        let (min, max) = point_pair_minmax(previous_start, end);
 
-        self.rect.start = (min_x, min_y);
-        self.rect.size = (max_x - min_x, max_y - min_y);
+        self.rect.start = min;
+        self.rect.size = max - min;
     }
 
     pub(crate) fn is_resize_point(&self, p: U16Point) -> bool {
@@ -30,4 +32,8 @@ impl RectObject {
     pub(crate) fn is_drag_point(&self, p: U16Point) -> bool {
         self.rect.is_point_on_header(p)
     }
+
+    pub(crate) fn bounds(&self) -> Rect {
+        self.rect
+    }
 }
@@ -1,10 +1,13 @@
+use serde::{Deserialize, Serialize};
 use terge::{
     common::{TextHorizontalAlign, TextVercticalAlign, U16Point, multiline_text_line_start},
     gfx::Gfx,
+    rect::Rect,
 };
 
 use crate::common::{COLORS, IdType};
 
+#[derive(Clone, Serialize, Deserialize)]
 pub struct TextObject {
     pub id: IdType,
     pub start: U16Point,
@@ -43,7 +46,7 @@ impl TextObject {
                 }
             }
 
-            if pos.1 == p.1 && p.0 >= pos.0 && p.0 <= pos.0 + line.len() as u16 {
+            if pos.y == p.y && p.x >= pos.x && p.x <= pos.x + line.len() as u16 {
                 return true;
             }
         }
@@ -59,10 +62,21 @@ impl TextObject {
         self.is_edit_point(p) || self.is_drag_point(p)
     }
 
+    /// Covers every line including the cell after its end, which is still an edit point.
+    pub fn bounds(&self) -> Rect {
+        (0..self.lines.len())
+            .map(|i| {
+                let start = self.line_start(i);
+                Rect::new(start, U16Point::new(self.lines[i].len() as u16, 0))
+            })
+            .reduce(|lhs, rhs| lhs.union(&rhs))
+            .unwrap_or(Rect::new(self.start, U16Point::new(0, 0)))
+    }
+
     pub fn draw(&self, gfx: &Gfx) {
         for (i, line) in self.lines.iter().enumerate() {
             let pos = self.line_start(i);
-            gfx.draw_text(&line, pos.0, pos.1, COLORS[self.color].0);
+            gfx.draw_text(&line, pos.x, pos.y, COLORS[self.color].0);
         }
     }
 
@@ -9,35 +9,54 @@ pub struct TextEditor {
 impl TextEditor {
     pub fn new() -> Self {
         Self {
-            cursor: (0, 0), // X is ignored for now.
+            cursor: UsizePoint::new(0, 0), // X is ignored for now.
             lines: vec![String::new()],
         }
     }
 
     pub fn new_with_lines(lines: Vec<String>) -> Self {
         Self {
-            cursor: (0, lines.len() - 1),
+            cursor: UsizePoint::new(0, lines.len() - 1),
             lines,
         }
     }
 
+    /// Column and line where the next character goes.
+    pub fn cursor_pos(&self) -> UsizePoint {
+        UsizePoint::new(self.lines[self.cursor.y].chars().count(), self.cursor.y)
+    }
+
+    /// Pasted text, line breaks start new lines.
+    pub fn insert_str(&mut self, text: &str) {
+        for c in text.chars() {
+            match c {
+                '\n' => {
+                    self.cursor.y += 1;
+                    self.lines.insert(self.cursor.y, String::new());
+                }
+                '\r' => {}
+                c => self.lines[self.cursor.y].push(c),
+            }
+        }
+    }
+
     pub fn edit(&mut self, event: &KeyEvent) {
         match event.code {
             KeyCode::Char(c) => {
-                self.lines[self.cursor.1].push(c);
+                self.lines[self.cursor.y].push(c);
             }
             KeyCode::Backspace => {
-                if self.lines[self.cursor.1].pop().is_none() {
-                    if self.cursor.1 > 0 {
-                        self.lines.remove(self.cursor.1);
-                        self.cursor.1 -= 1;
+                if self.lines[self.cursor.y].pop().is_none() {
+                    if self.cursor.y > 0 {
+                        self.lines.remove(self.cursor.y);
+                        self.cursor.y -= 1;
                     }
                 }
             }
             KeyCode::Enter => {
                 if event.modifiers.contains(KeyModifiers::ALT) {
-                    self.cursor.1 += 1;
-                    self.lines.insert(self.cursor.1, String::new());
+                    self.cursor.y += 1;
+                    self.lines.insert(self.cursor.y, String::new());
                 }
             }
             _ => {}
@@ -1,5 +1,12 @@
 use crossterm::event::Event;
 use crossterm::event::KeyCode;
+use terge::banner::{Banner, BannerColors, FigFont};
+use terge::gfx::Gfx;
+use terge::layout::Constraint::{Fill, Length, Ratio};
+use terge::layout::{Alignment, Layout, align_rect};
+use terge::rect::Rect;
+use terge::sprite::Sprite;
+use terge::widget::{Align, Label, Style, Ui, WidgetId};
 
 use crate::common::*;
 use crate::player::*;
@@ -9,16 +16,49 @@ use crate::terrain::*;
 pub(crate) struct App {
     player: Player,
     terrain: Terrain,
+    ui: Ui,
+    dead_label: WidgetId,
+    dead_banner: Sprite,
 }
 
-impl App {}
+impl App {
+    /// Where the large death message goes, `None` if the screen is too narrow for it.
+    fn dead_banner_rect(&self, gfx: &Gfx) -> Option<Rect> {
+        if self.dead_banner.width() > gfx.width {
+            return None;
+        }
+
+        let area = Layout::vertical([Ratio(1, 3), Fill(1)]).split(&gfx.screen_rect())[1]?;
+        align_rect(
+            &area,
+            self.dead_banner.width(),
+            self.dead_banner.height(),
+            Alignment::Center,
+            Alignment::Start,
+        )
+    }
+}
+
+/// Row a third down the screen, the small death message is centered on it.
+fn dead_label_rect(gfx: &Gfx) -> Rect {
+    Layout::vertical([Ratio(1, 3), Length(1), Fill(1)]).split(&gfx.screen_rect())[1]
+        .unwrap_or_default()
+}
 
 impl terge::App for App {
     fn reset(&mut self, gfx: &mut terge::gfx::Gfx) {
-        self.player.pos = (PLAYER_X as f32, floor(gfx) as f32);
+        self.player.reset(gfx);
+        self.terrain.reset(gfx);
 
-        self.player.reset();
-        self.terrain.reset();
+        self.ui = Ui::new();
+        self.dead_label = self.ui.add(
+            Label::new(dead_label_rect(gfx), DEAD_TEXT)
+                .with_style(Style::new(DEAD_TEXT_COLOR))
+                .with_align(Align::Center),
+        );
+        self.dead_banner = Banner::new(&FigFont::block())
+            .with_colors(BannerColors::Solid(DEAD_TEXT_COLOR))
+            .render(DEAD_TEXT);
     }
 
     fn draw(&self, gfx: &mut terge::gfx::Gfx) {
@@ -27,9 +67,12 @@ impl terge::App for App {
         self.terrain.draw(gfx);
         self.player.draw(gfx);
 
-        if self.player.dead {
-            gfx.draw_text("-== DEAD ==-", gfx.width / 2 - 6, gfx.height / 3, 91);
+        if self.player.dead
+            && let Some(rect) = self.dead_banner_rect(gfx)
+        {
+            gfx.draw_sprite(&self.dead_banner, rect.start.x, rect.start.y);
         }
+        self.ui.draw(gfx);
     }
 
     fn update(
@@ -41,22 +84,22 @@ impl terge::App for App {
             match &event {
                 Event::Key(key_event) => match &key_event.code {
                     KeyCode::Char('w') => {
-                        if self.player.jump(JUMP_SETTING[1].0, &gfx) {
+                        if self.player.jump(JUMP_SETTING[1].0) {
                             self.terrain.set_speed(JUMP_SETTING[1].1);
                         }
                     }
                     KeyCode::Char('s') => {
-                        if self.player.jump(JUMP_SETTING[0].0, gfx) {
+                        if self.player.jump(JUMP_SETTING[0].0) {
                             self.terrain.set_speed(JUMP_SETTING[0].1);
                         }
                     }
                     KeyCode::Char('a') => {
-                        if self.player.jump(JUMP_SETTING[3].0, gfx) {
+                        if self.player.jump(JUMP_SETTING[3].0) {
                             self.terrain.set_speed(JUMP_SETTING[3].1);
                         }
                     }
                     KeyCode::Char('d') => {
-                        if self.player.jump(JUMP_SETTING[2].0, gfx) {
+                        if self.player.jump(JUMP_SETTING[2].0) {
                             self.terrain.set_speed(JUMP_SETTING[2].1);
                         }
                     }
@@ -69,12 +112,25 @@ impl terge::App for App {
             }
         }
 
+        let player_start = self.player.body.aabb;
         self.player.update(gfx);
-        self.terrain.update(gfx);
+        let player_movement = self.player.body.aabb.pos - player_start.pos;
+
+        self.terrain.update(events, gfx);
+
+        if self
+            .terrain
+            .did_collide(gfx, &player_start, player_movement)
+        {
+            self.player.die(&mut gfx.rng);
+            self.terrain.end_game();
+        }
 
-        if self.terrain.did_collide_with_frame(self.player.frame()) {
-            self.player.die();
-            self.terrain.game_over = true;
+        // The banner needs a wide screen, the label is the fallback.
+        let show_label = self.player.dead && self.dead_banner_rect(gfx).is_none();
+        self.ui.set_visible(self.dead_label, show_label);
+        if let Some(label) = self.ui.get_mut::<Label>(self.dead_label) {
+            label.rect = dead_label_rect(gfx);
         }
 
         true
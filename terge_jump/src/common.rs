@@ -1,25 +1,42 @@
-use terge::{common::F32Point, gfx::Gfx};
+use terge::{common::F32Point, gfx::Gfx, random::Random};
 
-pub(crate) const PLAYER_COLOR: u8 = 97;
 pub(crate) const PLAYER_X: u16 = 10;
+pub(crate) const PLAYER_SIZE: F32Point = F32Point::new(1.0, 2.0);
+pub(crate) const PLAYER_GRAVITY: f32 = 0.08;
+pub(crate) const PLAYER_MAX_FALL_SPEED: f32 = 2.0;
 
 //                                              Medium       Tall         Long         Short
-pub(crate) const JUMP_SETTING: [F32Point; 4] = [(-1.7, 1.1), (-2.2, 1.2), (-1.0, 2.5), (-1.0, 0.4)];
+pub(crate) const JUMP_SETTING: [(f32, f32); 4] =
+    [(-1.6, 1.1), (-1.9, 1.2), (-1.2, 2.5), (-1.2, 0.4)];
 
 pub(crate) const FLOOR_OFFS_FROM_BOTTOM: u16 = 6;
 
-pub(crate) const PLAYER_SPRITE: [[&'static str; 2]; 4] =
-    [["O", "^"], ["O", "<"], ["O", "v"], ["O", ">"]];
-pub(crate) const PLAYER_SPRITE_SPEED: u64 = 16;
+// Assets are embedded, the files are only read (and hot reloaded) when running from the checkout.
+pub(crate) const PLAYER_SPRITE_PATH: &str =
+    concat!(env!("CARGO_MANIFEST_DIR"), "/assets/player.txt");
+pub(crate) const PLAYER_SPRITE: &str = include_str!("../assets/player.txt");
+pub(crate) const DECORATIONS_COLOR_MAP_PATH: &str =
+    concat!(env!("CARGO_MANIFEST_DIR"), "/assets/decorations.txt");
+pub(crate) const DECORATIONS_COLOR_MAP: &str = include_str!("../assets/decorations.txt");
+
+pub(crate) const BLOOD_COUNT: usize = 32;
+pub(crate) const BLOOD_GRAVITY: f32 = 0.03;
+// Blood is removed once it leaves the screen, this is just an upper bound.
+pub(crate) const BLOOD_LIFETIME: u32 = 1_000;
 
 pub(crate) const TERRAIN_OBSTACLE_DEFAULT_SPEED: f32 = 1.0;
+pub(crate) const TERRAIN_SPEED_RECOVERY_MS: u128 = 1_500;
+pub(crate) const TERRAIN_GAME_OVER_STOP_MS: u128 = 1_000;
 pub(crate) const TERRAIN_OBSTACLE_COLORS: [u8; 2] = [91, 97];
 
+pub(crate) const DEAD_TEXT: &str = "-== DEAD ==-";
+pub(crate) const DEAD_TEXT_COLOR: u8 = 91;
+
 pub(crate) fn floor(gfx: &Gfx) -> u16 {
     gfx.height - FLOOR_OFFS_FROM_BOTTOM
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub(crate) enum DecorationType {
     Stone,
     GrassSmall,
@@ -29,8 +46,8 @@ pub(crate) enum DecorationType {
 }
 
 impl DecorationType {
-    pub(crate) fn random() -> Self {
-        match rand::random::<u8>() % 5 {
+    pub(crate) fn random(rng: &mut Random) -> Self {
+        match rng.range(0..5) {
             0 => Self::Stone,
             1 => Self::GrassSmall,
             2 => Self::GrassMedium,
@@ -39,20 +56,39 @@ impl DecorationType {
             _ => unreachable!(),
         }
     }
+
+    /// Key of the decoration in the decorations color map.
+    pub(crate) fn key(self) -> &'static str {
+        match self {
+            Self::Stone => "stone",
+            Self::GrassSmall => "grass_small",
+            Self::GrassMedium => "grass_medium",
+            Self::GrassLeanLeft => "grass_lean_left",
+            Self::GrassLeanRight => "grass_lean_right",
+        }
+    }
 }
 
+/// Terrain entity moving left with the terrain speed, removed once it leaves the screen.
 #[derive(Debug)]
-pub(crate) struct Decoration {
-    pub(crate) ty: DecorationType,
+pub(crate) struct Scrolling {
     pub(crate) x: f32,
 }
 
-impl Decoration {
-    pub(crate) fn new(ty: DecorationType, x: f32) -> Self {
-        Self { ty, x }
-    }
+/// Terrain entity drawn on the floor.
+#[derive(Debug)]
+pub(crate) struct Decoration(pub(crate) DecorationType);
+
+/// Terrain entity the player dies on, a column standing on the floor.
+#[derive(Debug)]
+pub(crate) struct Obstacle {
+    pub(crate) height: u16,
 }
 
+/// Resource of the terrain world, distance scrolled in the current update.
+#[derive(Debug)]
+pub(crate) struct TerrainSpeed(pub(crate) f32);
+
 pub(crate) enum ObstacleType {
     OneSmall,
     LongSmall,
@@ -64,8 +100,8 @@ pub(crate) enum ObstacleType {
 }
 
 impl ObstacleType {
-    pub(crate) fn random() -> Self {
-        match rand::random::<u8>() % 7 {
+    pub(crate) fn random(rng: &mut Random) -> Self {
+        match rng.range(0..7) {
             0 => Self::OneSmall,
             1 => Self::LongSmall,
             2 => Self::OneMedium,
@@ -1,4 +1,8 @@
+use crossterm::event::KeyCode;
 use terge::Terge;
+use terge::compositor::{Compositor, Pane};
+use terge::layout::Direction;
+use terge::server::Server;
 
 mod app;
 mod common;
@@ -8,7 +12,32 @@ mod terrain;
 use app::*;
 
 fn main() {
-    let mut app = Terge::new(Box::new(App::default()));
+    // `--serve <addr>` hosts a game for every telnet connection instead of playing locally.
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(addr) = args
+        .windows(2)
+        .find(|pair| pair[0] == "--serve")
+        .map(|pair| pair[1].clone())
+    {
+        let mut server =
+            Server::bind(addr, || Box::new(App::default())).expect("Failed binding server");
+        server.set_target_fps(60);
+        server.run().expect("Failed accepting connection");
+        return;
+    }
+
+    // `--split` plays two games side by side, the focused one gets the keys.
+    let mut app = if args.iter().any(|arg| arg == "--split") {
+        Terge::new(Box::new(
+            Compositor::new(Direction::Horizontal)
+                .with_pane(Pane::tiled("Player 1", Box::new(App::default())))
+                .with_pane(Pane::tiled("Player 2", Box::new(App::default()))),
+        ))
+    } else {
+        Terge::new(Box::new(App::default()))
+    };
     app.set_target_fps(60);
+    app.set_record_hotkey(KeyCode::F(12), "jumper.cast");
+    app.set_capture_hotkey(KeyCode::F(11), "jumper.svg");
     app.run();
 }
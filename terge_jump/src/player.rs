@@ -1,70 +1,87 @@
+use log::error;
 use terge::{
-    common::{F32Point, Gravity, U16Point},
+    common::F32Point,
     gfx::Gfx,
+    particle::{ParticleConfig, ParticleEmitter},
+    physics::{Aabb, Body, PhysicsWorld},
+    random::Random,
 };
 
 use crate::common::*;
 
 #[derive(Debug)]
 pub(crate) struct Player {
-    pub(crate) pos: F32Point,
-    pub(crate) v: F32Point,
+    pub(crate) body: Body,
+    on_ground: bool,
     pub(crate) sprite_counter: u64,
     pub(crate) dead: bool,
-    pub(crate) bloods: Vec<(F32Point, F32Point)>,
-    blood_g: Gravity,
-    jump_g: Gravity,
+    blood: ParticleEmitter,
+    world: PhysicsWorld,
 }
 
 impl Default for Player {
     fn default() -> Self {
         Self {
-            pos: Default::default(),
-            v: Default::default(),
+            body: Body::new(Aabb::new(F32Point::new(0.0, 0.0), PLAYER_SIZE)),
+            on_ground: false,
             sprite_counter: 0,
             dead: false,
-            bloods: vec![],
-            blood_g: Gravity::new(1.03, 1.0),
-            jump_g: Gravity::new(1.1, 2.0),
+            blood: ParticleEmitter::new(
+                F32Point::new(0.0, 0.0),
+                ParticleConfig {
+                    lifetime: BLOOD_LIFETIME..BLOOD_LIFETIME + 1,
+                    velocity_x: -0.3..0.7,
+                    velocity_y: -1.0..0.0,
+                    gravity: F32Point::new(0.0, BLOOD_GRAVITY),
+                    glyphs: vec!["*"],
+                    colors: vec![31],
+                    ..Default::default()
+                },
+            ),
+            world: PhysicsWorld::new(
+                F32Point::new(0.0, PLAYER_GRAVITY),
+                F32Point::new(0.0, PLAYER_MAX_FALL_SPEED),
+            ),
         }
     }
 }
 
 impl Player {
-    pub(crate) fn reset(&mut self) {
+    pub(crate) fn reset(&mut self, gfx: &mut Gfx) {
         self.dead = false;
+        self.body.aabb.pos =
+            F32Point::new(PLAYER_X as f32, floor(gfx) as f32 - PLAYER_SIZE.y + 1.0);
+        self.body.v = F32Point::new(0.0, 0.0);
+        self.on_ground = true;
+
+        gfx.assets.set_fallback(PLAYER_SPRITE_PATH, PLAYER_SPRITE);
+        if let Err(err) = gfx.assets.load_animation(PLAYER_SPRITE_PATH) {
+            error!("Failed loading player sprite: {}", err);
+        }
     }
 
     pub(crate) fn draw(&self, gfx: &Gfx) {
-        let player_sprite_idx =
-            self.sprite_counter / (PLAYER_SPRITE_SPEED / PLAYER_SPRITE.len() as u64);
-        let sprite = PLAYER_SPRITE[player_sprite_idx as usize];
-        for i in 0..sprite.len() {
-            gfx.draw_text(
-                sprite[i],
-                self.pos.0 as u16,
-                (self.pos.1 - sprite.len() as f32 + 1.0 + i as f32) as u16,
-                PLAYER_COLOR,
+        if let Some(animation) = gfx.assets.animation(PLAYER_SPRITE_PATH) {
+            let sprite = animation.frame(self.sprite_counter);
+            let bottom = self.body.aabb.max().y;
+            gfx.draw_sprite(
+                sprite,
+                self.body.aabb.pos.x as u16,
+                (bottom - sprite.height() as f32) as u16,
             );
         }
 
         if self.dead {
             gfx.draw_text("▁▁▁▁▁▁▂▂▂▃", 0, floor(gfx), 91);
-
-            for (blood_pos, _blood_v) in &self.bloods {
-                gfx.draw_text("*", blood_pos.0 as u16, blood_pos.1 as u16, 31);
-            }
         }
-    }
 
-    fn is_on_ground(&self, gfx: &Gfx) -> bool {
-        let floor: f32 = floor(gfx) as f32;
-        (floor - self.pos.1).abs() < 0.1
+        self.blood.draw(gfx);
     }
 
-    pub(crate) fn jump(&mut self, force: f32, gfx: &Gfx) -> bool {
-        if !self.dead && self.is_on_ground(gfx) {
-            self.v.1 = force;
+    pub(crate) fn jump(&mut self, force: f32) -> bool {
+        if !self.dead && self.on_ground {
+            self.body.v.y = force;
+            self.on_ground = false;
             true
         } else {
             false
@@ -74,56 +91,47 @@ impl Player {
     pub(crate) fn update(&mut self, gfx: &mut Gfx) {
         self.update_height(gfx);
         self.update_blood(gfx);
-        self.sprite_counter = (self.sprite_counter + 1) % PLAYER_SPRITE_SPEED;
+        self.sprite_counter = (self.sprite_counter + 1)
+            % gfx
+                .assets
+                .animation(PLAYER_SPRITE_PATH)
+                .map(|animation| animation.duration())
+                .unwrap_or(1);
     }
 
-    fn update_blood(&mut self, gfx: &Gfx) {
-        for (blood_pos, blood_v) in &mut self.bloods {
-            self.blood_g.apply(blood_pos, blood_v);
-            blood_pos.0 += blood_v.0;
-        }
-
-        self.bloods.retain(|(blood_pos, _blood_v)| {
-            blood_pos.0 >= 0.0
-                && blood_pos.1 >= 0.0
-                && blood_pos.0 < gfx.width as f32
-                && blood_pos.1 < gfx.height as f32
-        });
+    fn update_blood(&mut self, gfx: &mut Gfx) {
+        self.blood.config.bounds = Some((
+            F32Point::new(0.0, 0.0),
+            F32Point::new(gfx.width as f32, gfx.height as f32),
+        ));
+        self.blood.update(&mut gfx.rng);
     }
 
     fn update_height(&mut self, gfx: &mut Gfx) {
-        self.jump_g.apply(&mut self.pos, &mut self.v);
-
-        // Floor check.
-        let floor: f32 = (gfx.height - FLOOR_OFFS_FROM_BOTTOM) as f32;
-        if self.pos.1 >= floor {
-            self.pos.1 = floor;
-            self.v.1 = 0.0;
-            return;
+        let ground = Aabb::new(
+            F32Point::new(0.0, floor(gfx) as f32 + 1.0),
+            F32Point::new(gfx.width as f32, FLOOR_OFFS_FROM_BOTTOM as f32),
+        );
+
+        let contacts = self.world.step(&mut self.body, &[ground]);
+        if contacts.iter().any(|contact| contact.normal.y < 0.0) {
+            self.on_ground = true;
         }
     }
 
-    pub(crate) fn frame(&self) -> (U16Point, U16Point) {
-        (
-            (self.pos.0 as u16, (self.pos.1) as u16 - 1),
-            ((self.pos.0) as u16, self.pos.1 as u16),
-        )
-    }
-
-    pub(crate) fn die(&mut self) {
+    pub(crate) fn die(&mut self, rng: &mut Random) {
         if self.dead {
             return;
         }
 
         self.dead = true;
 
-        for _ in 0..32 {
-            let blood_pos = self.pos;
-            let blood_v = (
-                rand::random::<f32>() % 1.5 - 0.3,
-                rand::random::<f32>() % 1.0 - 1.0,
-            );
-            self.bloods.push((blood_pos, blood_v));
-        }
+        self.blood.pos = self.pos();
+        self.blood.burst(BLOOD_COUNT, rng);
+    }
+
+    /// Bottom-left cell of the player.
+    pub(crate) fn pos(&self) -> F32Point {
+        F32Point::new(self.body.aabb.pos.x, self.body.aabb.max().y - 1.0)
     }
 }
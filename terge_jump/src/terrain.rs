@@ -1,123 +1,99 @@
-use std::collections::VecDeque;
-
+use log::error;
 use terge::{
-    common::{U16Point, u16_range_overlap, u16_value_included_in_range},
+    common::F32Point,
+    ecs::{EntityBuilder, Schedule, World},
+    event_group::EventGroup,
     gfx::Gfx,
+    physics::{Aabb, sweep_aabb},
+    tween::{Easing, Tween},
 };
 
 use crate::common::*;
 
+/// Obstacles and decorations are entities of `world`, moved and drawn by the `schedule` systems.
 #[derive(Debug, Default)]
 pub(crate) struct Terrain {
-    obstacles: VecDeque<(f32, U16Point)>,
+    world: World,
+    schedule: Schedule,
     speed: f32,
-    decorations: VecDeque<Decoration>,
+    /// Distance the terrain moved in the last update.
+    last_step: f32,
+    speed_tween: Option<Tween<f32>>,
     pub(crate) game_over: bool,
     obstacle_delay: u16,
 }
 
 impl Terrain {
-    pub(crate) fn reset(&mut self) {
-        self.obstacles.clear();
-        self.decorations.clear();
+    pub(crate) fn reset(&mut self, gfx: &mut Gfx) {
+        gfx.assets
+            .set_fallback(DECORATIONS_COLOR_MAP_PATH, DECORATIONS_COLOR_MAP);
+        if let Err(err) = gfx.assets.load_color_map(DECORATIONS_COLOR_MAP_PATH) {
+            error!("Failed loading decorations: {}", err);
+        }
+
+        self.world.clear();
+        self.schedule = Schedule::new()
+            .with_update(scroll_system)
+            .with_draw(draw_decorations_system)
+            .with_draw(draw_obstacles_system);
         self.speed = TERRAIN_OBSTACLE_DEFAULT_SPEED;
+        self.speed_tween = None;
         self.game_over = false;
     }
 
-    pub(crate) fn update(&mut self, gfx: &mut Gfx) {
-        // Move obstacles.
-        for obstacle in &mut self.obstacles {
-            obstacle.0 -= self.speed;
-        }
+    pub(crate) fn update(&mut self, events: &EventGroup, gfx: &mut Gfx) {
+        self.last_step = self.speed;
 
-        // Move decorations.
-        for decor in &mut self.decorations {
-            decor.x -= self.speed;
-        }
-
-        // Cleanup obstacles.
-        loop {
-            if let Some(obstacle) = self.obstacles.front() {
-                if obstacle.0 <= 0.0 {
-                    self.obstacles
-                        .pop_front()
-                        .expect("Failed removing front obstacle");
-                    continue;
-                }
-            }
-            break;
-        }
-
-        // Cleanup decorations.
-        loop {
-            if let Some(decor) = self.decorations.front() {
-                if decor.x <= 0.0 {
-                    self.decorations
-                        .pop_front()
-                        .expect("Failed removing front decoration");
-                    continue;
-                }
-            }
-            break;
-        }
+        // Move and cleanup obstacles and decorations.
+        self.world.insert_resource(TerrainSpeed(self.speed));
+        self.schedule.update(&mut self.world, events, gfx);
 
         // New obstacles.
         let last_obstacle_enough_far = self
-            .obstacles
-            .back()
-            .map(|obstacle| gfx.width as f32 - obstacle.0 > self.obstacle_delay as f32)
+            .last_x::<Obstacle>()
+            .map(|x| gfx.width as f32 - x > self.obstacle_delay as f32)
             .unwrap_or(true);
 
         if last_obstacle_enough_far {
-            let rand_u8: u8 = rand::random();
-            let floor = floor(gfx);
+            let rand_u8: u8 = gfx.rng.range(0..=u8::MAX);
+            let width = gfx.width as f32;
             if rand_u8 >= 220 || true {
-                match ObstacleType::random() {
+                match ObstacleType::random(&mut gfx.rng) {
                     ObstacleType::OneSmall => {
-                        self.obstacles
-                            .push_back((gfx.width as f32, (floor - 2, floor)));
+                        self.spawn_obstacle(width, 2);
                         self.obstacle_delay = 30;
                     }
                     ObstacleType::OneTall => {
-                        self.obstacles
-                            .push_back((gfx.width as f32, (floor - 16, floor)));
+                        self.spawn_obstacle(width, 16);
                         self.obstacle_delay = 70;
                     }
                     ObstacleType::TwoTall => {
-                        self.obstacles
-                            .push_back((gfx.width as f32, (floor - 16, floor)));
-                        self.obstacles
-                            .push_back((gfx.width as f32 + 8.0, (floor - 16, floor)));
+                        self.spawn_obstacle(width, 16);
+                        self.spawn_obstacle(width + 8.0, 16);
                         self.obstacle_delay = 70;
                     }
                     ObstacleType::LongSmall => {
                         for i in -4i32..=4i32 {
-                            self.obstacles.push_back((
-                                gfx.width as f32 + (i as f32 + 4.0) * 5.0,
-                                (floor - 4 + i.abs() as u16 / 2, floor),
-                            ));
+                            self.spawn_obstacle(
+                                width + (i as f32 + 4.0) * 5.0,
+                                4 - i.unsigned_abs() as u16 / 2,
+                            );
                         }
                         self.obstacle_delay = 70;
                     }
                     ObstacleType::OneMedium => {
-                        self.obstacles
-                            .push_back((gfx.width as f32, (floor - 10, floor)));
+                        self.spawn_obstacle(width, 10);
                         self.obstacle_delay = 50;
                     }
                     ObstacleType::TwoMedium => {
-                        self.obstacles
-                            .push_back((gfx.width as f32, (floor - 10, floor)));
-                        self.obstacles
-                            .push_back((gfx.width as f32 + 8.0, (floor - 10, floor)));
+                        self.spawn_obstacle(width, 10);
+                        self.spawn_obstacle(width + 8.0, 10);
                         self.obstacle_delay = 55;
                     }
                     ObstacleType::ThreeMedium => {
-                        self.obstacles
-                            .push_back((gfx.width as f32, (floor - 10, floor)));
-                        self.obstacles
-                            .push_back((gfx.width as f32 + 6.0, (floor - 10, floor)));
-                        self.obstacles
-                            .push_back((gfx.width as f32 + 12.0, (floor - 10, floor)));
+                        self.spawn_obstacle(width, 10);
+                        self.spawn_obstacle(width + 6.0, 10);
+                        self.spawn_obstacle(width + 12.0, 10);
                         self.obstacle_delay = 60;
                     }
                 }
@@ -126,83 +102,147 @@ impl Terrain {
 
         // New decoration.
         let last_decoration_enough_far = self
-            .decorations
-            .back()
-            .map(|decor| gfx.width as f32 - decor.x as f32 > 2.0)
+            .last_x::<Decoration>()
+            .map(|x| gfx.width as f32 - x > 2.0)
             .unwrap_or(true);
         if last_decoration_enough_far {
-            let rand_u8: u8 = rand::random();
+            let rand_u8: u8 = gfx.rng.range(0..=u8::MAX);
             if rand_u8 >= 200 {
-                self.decorations.push_back(Decoration::new(
-                    DecorationType::random(),
-                    (gfx.width - 1) as f32,
-                ));
+                self.world.spawn_with(
+                    EntityBuilder::new()
+                        .with(Scrolling {
+                            x: (gfx.width - 1) as f32,
+                        })
+                        .with(Decoration(DecorationType::random(&mut gfx.rng))),
+                );
             }
         }
 
         // Regulate speed.
-        if self.game_over {
-            self.speed *= 0.95;
-        } else {
-            let diff = self.speed - TERRAIN_OBSTACLE_DEFAULT_SPEED;
-            if diff != 0.0 {
-                static SPEED_ADJUST: f32 = 0.98;
-                let new_diff = diff * SPEED_ADJUST;
-                self.speed = TERRAIN_OBSTACLE_DEFAULT_SPEED + new_diff;
-
-                if (self.speed - TERRAIN_OBSTACLE_DEFAULT_SPEED).abs() < 0.1 {
-                    self.speed = TERRAIN_OBSTACLE_DEFAULT_SPEED;
-                }
-            }
+        if let Some(speed_tween) = &mut self.speed_tween {
+            speed_tween.advance(events.delta_ms);
+            self.speed = speed_tween.value();
         }
     }
 
     pub(crate) fn draw(&self, gfx: &Gfx) {
         let floor = floor(gfx);
 
-        for decor in &self.decorations {
-            let (decor_str, color) = match decor.ty {
-                DecorationType::Stone => (".", 90),
-                DecorationType::GrassSmall => (",", 32),
-                DecorationType::GrassMedium => ("v", 32),
-                DecorationType::GrassLeanLeft => ("╮", 92),
-                DecorationType::GrassLeanRight => ("╭", 92),
-            };
-            gfx.draw_text(decor_str, decor.x as u16, floor, color);
-        }
-
-        for (obstacle_x, obstacle_y) in &self.obstacles {
-            if (*obstacle_x as u16) < gfx.width {
-                let obstacle_height = obstacle_y.1 - obstacle_y.0;
-                for i in 0..obstacle_height as u16 {
-                    gfx.draw_text(
-                        "▓",
-                        *obstacle_x as u16,
-                        floor - i,
-                        TERRAIN_OBSTACLE_COLORS[i as usize % TERRAIN_OBSTACLE_COLORS.len()],
-                    );
-                }
-            }
-        }
+        self.schedule.draw(&self.world, gfx);
 
         gfx.draw_text(&"▒".repeat(gfx.width as usize), 0, floor + 1, 32);
         gfx.draw_text(&"▓".repeat(gfx.width as usize), 0, floor + 2, 33);
         gfx.draw_text(&"░".repeat(gfx.width as usize), 0, floor + 3, 33);
     }
 
+    /// Jumps speed up the terrain, which then eases back to the default speed.
     pub(crate) fn set_speed(&mut self, speed: f32) {
         self.speed = speed;
+        self.speed_tween = Some(Tween::new(
+            speed,
+            TERRAIN_OBSTACLE_DEFAULT_SPEED,
+            TERRAIN_SPEED_RECOVERY_MS,
+            Easing::CubicOut,
+        ));
     }
 
-    pub(crate) fn did_collide_with_frame(&self, frame: (U16Point, U16Point)) -> bool {
-        for (obstacle_x, obstacle_y) in &self.obstacles {
-            if u16_value_included_in_range(*obstacle_x as u16, (frame.0.0, frame.1.0)) {
-                if u16_range_overlap((frame.0.1, frame.1.1), *obstacle_y) {
-                    return true;
-                }
-            }
+    pub(crate) fn end_game(&mut self) {
+        if self.game_over {
+            return;
         }
 
-        false
+        self.game_over = true;
+        self.speed_tween = Some(Tween::new(
+            self.speed,
+            0.0,
+            TERRAIN_GAME_OVER_STOP_MS,
+            Easing::CubicOut,
+        ));
+    }
+
+    fn spawn_obstacle(&mut self, x: f32, height: u16) {
+        self.world.spawn_with(
+            EntityBuilder::new()
+                .with(Scrolling { x })
+                .with(Obstacle { height }),
+        );
     }
+
+    /// Position of the rightmost entity with a `T` component, the one spawned last.
+    fn last_x<T: 'static>(&self) -> Option<f32> {
+        let mut last_x: Option<f32> = None;
+        self.world.query::<(&Scrolling, &T)>(|_, (scrolling, _)| {
+            last_x = Some(last_x.map_or(scrolling.x, |x| x.max(scrolling.x)));
+        });
+        last_x
+    }
+
+    /// Whether the player, starting at `player` and moving by `player_movement` during the last
+    /// update, hit an obstacle. The whole path is checked, so fast movement can't skip through.
+    pub(crate) fn did_collide(&self, gfx: &Gfx, player: &Aabb, player_movement: F32Point) -> bool {
+        // Obstacles moved too, check against their position before the update.
+        let relative_movement = player_movement + F32Point::new(self.last_step, 0.0);
+        let floor = floor(gfx) as f32;
+
+        let mut collided = false;
+        self.world
+            .query::<(&Scrolling, &Obstacle)>(|_, (scrolling, obstacle)| {
+                let height = obstacle.height as f32;
+                let obstacle = Aabb::new(
+                    F32Point::new(scrolling.x + self.last_step, floor - height + 1.0),
+                    F32Point::new(1.0, height),
+                );
+
+                collided |= sweep_aabb(player, relative_movement, &obstacle).is_some();
+            });
+        collided
+    }
+}
+
+fn scroll_system(world: &mut World, _events: &EventGroup, _gfx: &mut Gfx) {
+    let speed = world
+        .resource::<TerrainSpeed>()
+        .map_or(0.0, |speed| speed.0);
+
+    world.query::<(&mut Scrolling,)>(|entity, (scrolling,)| {
+        scrolling.x -= speed;
+        if scrolling.x <= 0.0 {
+            world.despawn_later(entity);
+        }
+    });
+}
+
+fn draw_decorations_system(world: &World, gfx: &Gfx) {
+    let Some(color_map) = gfx.assets.color_map(DECORATIONS_COLOR_MAP_PATH) else {
+        return;
+    };
+    let floor = floor(gfx);
+
+    world.query::<(&Scrolling, &Decoration)>(|_, (scrolling, decoration)| {
+        if let Some((decor_ch, color)) = color_map.get(decoration.0.key()) {
+            gfx.draw_text(
+                decor_ch.encode_utf8(&mut [0; 4]),
+                scrolling.x as u16,
+                floor,
+                color,
+            );
+        }
+    });
+}
+
+fn draw_obstacles_system(world: &World, gfx: &Gfx) {
+    let floor = floor(gfx);
+
+    world.query::<(&Scrolling, &Obstacle)>(|_, (scrolling, obstacle)| {
+        if (scrolling.x as u16) < gfx.width {
+            for i in 0..obstacle.height {
+                gfx.draw_text(
+                    "▓",
+                    scrolling.x as u16,
+                    floor - i,
+                    TERRAIN_OBSTACLE_COLORS[i as usize % TERRAIN_OBSTACLE_COLORS.len()],
+                );
+            }
+        }
+    });
 }
@@ -0,0 +1,98 @@
+use crossterm::event::{Event, KeyCode};
+use terge::{
+    Terge,
+    camera::Camera,
+    common::{F32Point, I32Point, U16Point},
+    event_group::EventGroup,
+    gfx::Gfx,
+    tilemap::TileMap,
+};
+
+const MAP_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/examples/assets/dungeon.txt");
+const FLOOR_TILE: char = '.';
+
+struct App {
+    player: I32Point,
+    camera: Camera,
+}
+
+impl App {
+    fn new() -> Self {
+        Self {
+            player: I32Point::new(0, 0),
+            camera: Camera::new(U16Point::new(0, 0)),
+        }
+    }
+
+    fn map<'a>(&self, gfx: &'a Gfx) -> &'a TileMap {
+        gfx.assets.tile_map(MAP_PATH).expect("Map is loaded")
+    }
+}
+
+impl terge::App for App {
+    fn draw(&self, gfx: &mut Gfx) {
+        gfx.clear_screen();
+
+        self.map(gfx).draw(gfx, &self.camera);
+
+        if let Some(p) = self.camera.world_to_screen(self.player.as_f32()) {
+            gfx.draw_text("@", p.x, p.y, 93);
+        }
+    }
+
+    fn update(&mut self, events: &EventGroup, gfx: &mut Gfx) -> bool {
+        for event in &events.events {
+            if let Event::Key(key_event) = event {
+                let step = match key_event.code {
+                    KeyCode::Char('w') | KeyCode::Up => I32Point::new(0, -1),
+                    KeyCode::Char('s') | KeyCode::Down => I32Point::new(0, 1),
+                    KeyCode::Char('a') | KeyCode::Left => I32Point::new(-1, 0),
+                    KeyCode::Char('d') | KeyCode::Right => I32Point::new(1, 0),
+                    _ => continue,
+                };
+
+                let next = self.player + step;
+                if !self.map(gfx).is_solid(next) {
+                    self.player = next;
+                }
+            }
+        }
+
+        self.camera
+            .set_viewport(U16Point::new(0, 0), U16Point::new(gfx.width, gfx.height));
+        self.camera.follow(self.player.as_f32());
+
+        true
+    }
+
+    fn reset(&mut self, gfx: &mut Gfx) {
+        let map = gfx
+            .assets
+            .load_tile_map(MAP_PATH)
+            .expect("Failed loading map");
+
+        'search: for y in 0..map.height {
+            for x in 0..map.width {
+                if map.get(U16Point::new(x, y)) == Some(FLOOR_TILE) {
+                    self.player = U16Point::new(x, y).cast();
+                    break 'search;
+                }
+            }
+        }
+
+        self.camera = Camera::new(U16Point::new(gfx.width, gfx.height));
+        self.camera.dead_zone = F32Point::new(gfx.width as f32 / 6.0, gfx.height as f32 / 6.0);
+        self.camera.smoothing = 0.2;
+        self.camera
+            .set_bounds(F32Point::new(0.0, 0.0), map.size().as_f32());
+        self.camera.center_on(self.player.as_f32());
+    }
+}
+
+fn main() {
+    pretty_env_logger::init();
+
+    let mut engine = Terge::new(Box::new(App::new()));
+    engine.set_target_fps(60);
+    engine.run();
+}
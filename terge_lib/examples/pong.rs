@@ -23,36 +23,36 @@ impl terge::App for App {
         gfx.clear_screen();
 
         gfx.draw_text_uncoloured("████████", self.pad_x, gfx.height - 1);
-        gfx.draw_text_uncoloured("O", self.ball_pos.0, self.ball_pos.1);
+        gfx.draw_text_uncoloured("O", self.ball_pos.x, self.ball_pos.y);
     }
 
     fn update(&mut self, events: &EventGroup, gfx: &mut Gfx) -> bool {
-        if let Some((x, _y)) = events.last_mouse_pos() {
-            self.pad_x = x;
+        if let Some(mouse_pos) = events.last_mouse_pos() {
+            self.pad_x = mouse_pos.x;
         }
 
-        let ball_next_x = self.ball_pos.0 as i32 + self.ball_v.0;
-        let ball_next_y = self.ball_pos.1 as i32 + self.ball_v.1;
+        let ball_next_x = self.ball_pos.x as i32 + self.ball_v.x;
+        let ball_next_y = self.ball_pos.y as i32 + self.ball_v.y;
 
         if ball_next_x <= 0 || ball_next_x >= gfx.width as i32 {
-            self.ball_v.0 *= -1;
+            self.ball_v.x *= -1;
         }
         if ball_next_y <= 0 || ball_next_y >= gfx.height as i32 - 1 {
-            self.ball_v.1 *= -1;
+            self.ball_v.y *= -1;
         }
-        self.ball_pos.0 = (self.ball_pos.0 as i32 + self.ball_v.0) as u16;
-        self.ball_pos.1 = (self.ball_pos.1 as i32 + self.ball_v.1) as u16;
+        self.ball_pos.x = (self.ball_pos.x as i32 + self.ball_v.x) as u16;
+        self.ball_pos.y = (self.ball_pos.y as i32 + self.ball_v.y) as u16;
 
         true
     }
 
     fn reset(&mut self, gfx: &mut Gfx) {
         self.pad_x = gfx.width / 2;
-        self.ball_pos.0 = gfx.width / 2;
-        self.ball_pos.1 = gfx.height / 2;
+        self.ball_pos.x = gfx.width / 2;
+        self.ball_pos.y = gfx.height / 2;
 
-        self.ball_v.0 = 1;
-        self.ball_v.1 = 1;
+        self.ball_v.x = 1;
+        self.ball_v.y = 1;
     }
 }
 
@@ -0,0 +1,459 @@
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use log::{debug, error};
+
+use crate::banner::FigFont;
+use crate::get_current_ms;
+use crate::sprite::*;
+use crate::tilemap::TileMap;
+
+/// Lines like this separate frames in an animation file.
+pub const ANIMATION_FRAME_SEPARATOR: &str = "---";
+/// Optional first line of an animation file: `@frame_duration <n>`.
+pub const ANIMATION_FRAME_DURATION_DIRECTIVE: &str = "@frame_duration";
+pub const ANIMATION_DEFAULT_FRAME_DURATION: u64 = 1;
+/// How often file modification times are checked when hot reload is active.
+pub const ASSET_RELOAD_POLL_MS: u128 = 250;
+
+#[derive(Debug)]
+pub enum Asset {
+    Sprite(Sprite),
+    Animation(Animation),
+    ColorMap(ColorMap),
+    TileMap(TileMap),
+    Font(FigFont),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum AssetKind {
+    Sprite,
+    Animation,
+    ColorMap,
+    TileMap,
+    Font,
+}
+
+#[derive(Debug)]
+struct AssetEntry {
+    kind: AssetKind,
+    asset: Asset,
+    modified: Option<SystemTime>,
+}
+
+/// Loads sprites, animations, color maps, tile maps and FIGlet fonts from disk and caches them
+/// by path.
+///
+/// Text files may contain ANSI color escapes. When hot reload is enabled (the default in debug
+/// builds) `Terge` calls `reload_changed` every frame, so edited files show up without restart.
+///
+/// Apps shipped without their asset files can embed them (`include_str!`) and register them with
+/// `set_fallback`, they are used while the file doesn't exist.
+#[derive(Debug)]
+pub struct Assets {
+    entries: HashMap<PathBuf, AssetEntry>,
+    fallbacks: HashMap<PathBuf, &'static str>,
+    hot_reload: bool,
+    last_poll_ms: u128,
+}
+
+impl Default for Assets {
+    fn default() -> Self {
+        Self {
+            entries: HashMap::new(),
+            fallbacks: HashMap::new(),
+            hot_reload: cfg!(debug_assertions),
+            last_poll_ms: 0,
+        }
+    }
+}
+
+impl Assets {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_hot_reload(&mut self, hot_reload: bool) {
+        self.hot_reload = hot_reload;
+    }
+
+    /// Content used for `path` while there is no file there. Once the file is created it is
+    /// picked up by hot reload.
+    pub fn set_fallback(&mut self, path: impl AsRef<Path>, content: &'static str) {
+        self.fallbacks.insert(path.as_ref().to_path_buf(), content);
+    }
+
+    pub fn load_sprite(&mut self, path: impl AsRef<Path>) -> io::Result<&Sprite> {
+        match self.load(path.as_ref(), AssetKind::Sprite)? {
+            Asset::Sprite(sprite) => Ok(sprite),
+            _ => Err(asset_kind_mismatch(path.as_ref())),
+        }
+    }
+
+    pub fn load_animation(&mut self, path: impl AsRef<Path>) -> io::Result<&Animation> {
+        match self.load(path.as_ref(), AssetKind::Animation)? {
+            Asset::Animation(animation) => Ok(animation),
+            _ => Err(asset_kind_mismatch(path.as_ref())),
+        }
+    }
+
+    pub fn load_color_map(&mut self, path: impl AsRef<Path>) -> io::Result<&ColorMap> {
+        match self.load(path.as_ref(), AssetKind::ColorMap)? {
+            Asset::ColorMap(color_map) => Ok(color_map),
+            _ => Err(asset_kind_mismatch(path.as_ref())),
+        }
+    }
+
+    pub fn load_tile_map(&mut self, path: impl AsRef<Path>) -> io::Result<&TileMap> {
+        match self.load(path.as_ref(), AssetKind::TileMap)? {
+            Asset::TileMap(tile_map) => Ok(tile_map),
+            _ => Err(asset_kind_mismatch(path.as_ref())),
+        }
+    }
+
+    pub fn load_font(&mut self, path: impl AsRef<Path>) -> io::Result<&FigFont> {
+        match self.load(path.as_ref(), AssetKind::Font)? {
+            Asset::Font(font) => Ok(font),
+            _ => Err(asset_kind_mismatch(path.as_ref())),
+        }
+    }
+
+    /// Cached sprite, `None` if it was never loaded.
+    pub fn sprite(&self, path: impl AsRef<Path>) -> Option<&Sprite> {
+        match self.entries.get(path.as_ref()).map(|entry| &entry.asset) {
+            Some(Asset::Sprite(sprite)) => Some(sprite),
+            _ => None,
+        }
+    }
+
+    /// Cached animation, `None` if it was never loaded.
+    pub fn animation(&self, path: impl AsRef<Path>) -> Option<&Animation> {
+        match self.entries.get(path.as_ref()).map(|entry| &entry.asset) {
+            Some(Asset::Animation(animation)) => Some(animation),
+            _ => None,
+        }
+    }
+
+    /// Cached color map, `None` if it was never loaded.
+    pub fn color_map(&self, path: impl AsRef<Path>) -> Option<&ColorMap> {
+        match self.entries.get(path.as_ref()).map(|entry| &entry.asset) {
+            Some(Asset::ColorMap(color_map)) => Some(color_map),
+            _ => None,
+        }
+    }
+
+    /// Cached tile map, `None` if it was never loaded.
+    pub fn tile_map(&self, path: impl AsRef<Path>) -> Option<&TileMap> {
+        match self.entries.get(path.as_ref()).map(|entry| &entry.asset) {
+            Some(Asset::TileMap(tile_map)) => Some(tile_map),
+            _ => None,
+        }
+    }
+
+    /// Cached font, `None` if it was never loaded.
+    pub fn font(&self, path: impl AsRef<Path>) -> Option<&FigFont> {
+        match self.entries.get(path.as_ref()).map(|entry| &entry.asset) {
+            Some(Asset::Font(font)) => Some(font),
+            _ => None,
+        }
+    }
+
+    pub fn unload(&mut self, path: impl AsRef<Path>) {
+        self.entries.remove(path.as_ref());
+    }
+
+    /// Reloads every cached asset whose file changed since it was loaded. Does nothing unless
+    /// hot reload is on, and checks the disk at most every `ASSET_RELOAD_POLL_MS`. Returns the
+    /// reloaded paths.
+    pub fn reload_changed(&mut self) -> Vec<PathBuf> {
+        let mut reloaded = vec![];
+
+        if !self.hot_reload {
+            return reloaded;
+        }
+
+        let current_ms = get_current_ms();
+        if current_ms.saturating_sub(self.last_poll_ms) < ASSET_RELOAD_POLL_MS {
+            return reloaded;
+        }
+        self.last_poll_ms = current_ms;
+
+        for (path, entry) in &mut self.entries {
+            let modified = file_modified(path);
+            if modified == entry.modified {
+                continue;
+            }
+
+            let fallback = self.fallbacks.get(path).copied();
+            match read_asset(path, entry.kind, fallback) {
+                Ok(asset) => {
+                    debug!("Reloaded asset: {:?}", path);
+                    entry.asset = asset;
+                    entry.modified = modified;
+                    reloaded.push(path.clone());
+                }
+                Err(err) => {
+                    // Keep the old version, the file is likely mid-save.
+                    error!("Failed reloading asset {:?}: {}", path, err);
+                }
+            }
+        }
+
+        reloaded
+    }
+
+    /// A path is cached as one kind only, loading it as another one is an error.
+    fn load(&mut self, path: &Path, kind: AssetKind) -> io::Result<&Asset> {
+        let cached_kind = self.entries.get(path).map(|entry| entry.kind);
+        if cached_kind.is_some_and(|cached_kind| cached_kind != kind) {
+            return Err(asset_kind_mismatch(path));
+        }
+
+        if cached_kind.is_none() {
+            let asset = read_asset(path, kind, self.fallbacks.get(path).copied())?;
+            self.entries.insert(
+                path.to_path_buf(),
+                AssetEntry {
+                    kind,
+                    asset,
+                    modified: file_modified(path),
+                },
+            );
+        }
+
+        Ok(&self.entries[path].asset)
+    }
+}
+
+fn file_modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+fn asset_kind_mismatch(path: &Path) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("Asset {:?} is cached as a different kind", path),
+    )
+}
+
+/// Reads the file at `path`, or takes `fallback` when there is no such file.
+fn read_asset(path: &Path, kind: AssetKind, fallback: Option<&str>) -> io::Result<Asset> {
+    let content = match (fs::read_to_string(path), fallback) {
+        (Err(err), Some(fallback)) if err.kind() == io::ErrorKind::NotFound => fallback.into(),
+        (content, _) => content?,
+    };
+
+    Ok(match kind {
+        AssetKind::Sprite => Asset::Sprite(Sprite::from_ansi(&content)),
+        AssetKind::Animation => Asset::Animation(parse_animation(&content)?),
+        AssetKind::ColorMap => Asset::ColorMap(parse_color_map(&content)?),
+        AssetKind::TileMap => Asset::TileMap(TileMap::from_text(&content)?),
+        AssetKind::Font => Asset::Font(FigFont::parse(&content)?),
+    })
+}
+
+/// Frames are separated by `---` lines. The first line may set the frame duration with
+/// `@frame_duration <n>`.
+pub fn parse_animation(content: &str) -> io::Result<Animation> {
+    let mut frame_duration = ANIMATION_DEFAULT_FRAME_DURATION;
+    let mut frames = vec![];
+    let mut frame_lines: Vec<&str> = vec![];
+
+    for (i, line) in content.lines().enumerate() {
+        if i == 0
+            && let Some(value) = line.strip_prefix(ANIMATION_FRAME_DURATION_DIRECTIVE)
+        {
+            frame_duration = value
+                .trim()
+                .parse()
+                .map_err(|_| invalid_data(format!("Invalid frame duration: {}", value.trim())))?;
+            continue;
+        }
+
+        if line.trim_end() == ANIMATION_FRAME_SEPARATOR {
+            frames.push(Sprite::from_ansi(&frame_lines.join("\n")));
+            frame_lines.clear();
+        } else {
+            frame_lines.push(line);
+        }
+    }
+
+    if !frame_lines.is_empty() {
+        frames.push(Sprite::from_ansi(&frame_lines.join("\n")));
+    }
+
+    if frames.is_empty() {
+        return Err(invalid_data("Animation has no frames".to_string()));
+    }
+
+    Ok(Animation {
+        frames,
+        frame_duration,
+    })
+}
+
+/// One entry per line, `<glyph> <color code>` or `<key> <glyph> <color code>`, keyed entries
+/// can be looked up with `ColorMap::get`. Empty lines are skipped, and so are lines starting
+/// with `#` unless they are an entry for the `#` glyph.
+pub fn parse_color_map(content: &str) -> io::Result<ColorMap> {
+    let mut color_map = ColorMap::default();
+
+    for line in content.lines() {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let entry = match fields[..] {
+            [glyph, color] => color_map_entry(None, glyph, color),
+            [key, glyph, color] => color_map_entry(Some(key), glyph, color),
+            _ => None,
+        };
+
+        match entry {
+            Some((key, ch, color)) => {
+                if let Some(key) = key {
+                    color_map
+                        .keys
+                        .insert(key.to_string(), color_map.entries.len());
+                }
+                color_map.entries.push((ch, color));
+            }
+            None if fields.is_empty() || line.starts_with('#') => {}
+            None => return Err(invalid_data(format!("Invalid color map line: {}", line))),
+        }
+    }
+
+    Ok(color_map)
+}
+
+fn color_map_entry<'a>(
+    key: Option<&'a str>,
+    glyph: &str,
+    color: &str,
+) -> Option<(Option<&'a str>, char, u8)> {
+    let mut chars = glyph.chars();
+    let ch = chars.next()?;
+    if chars.next().is_some() || key.is_some_and(|key| key.starts_with('#')) {
+        return None;
+    }
+
+    Some((key, ch, color.parse().ok()?))
+}
+
+fn invalid_data(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::U16Point;
+
+    fn test_file(name: &str, content: &str) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("terge-asset-{}-{}", std::process::id(), name));
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn a_path_is_cached_as_one_kind() {
+        let path = test_file("kind.txt", "ab");
+        let mut assets = Assets::new();
+
+        assets.load_sprite(&path).unwrap();
+        let err = assets.load_animation(&path).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert!(assets.sprite(&path).is_some());
+        assert!(assets.animation(&path).is_none());
+
+        assets.unload(&path);
+        assert!(assets.load_animation(&path).is_ok());
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn parses_color_maps() {
+        let color_map = parse_color_map(
+            "# Comment\n\n# 31\nwall # 90\n. 32\nfloor _ 33\n#comment without space\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            color_map.entries,
+            vec![('#', 31), ('#', 90), ('.', 32), ('_', 33)]
+        );
+        assert_eq!(color_map.get("wall"), Some(('#', 90)));
+        assert_eq!(color_map.get("floor"), Some(('_', 33)));
+        assert_eq!(color_map.get("door"), None);
+        assert_eq!(color_map.color_of('.'), Some(32));
+
+        assert!(parse_color_map("ab 12").is_err());
+        assert!(parse_color_map("a 256").is_err());
+    }
+
+    #[test]
+    fn fallback_is_used_without_a_file() {
+        let path = std::env::temp_dir().join(format!("terge-asset-{}-none", std::process::id()));
+        let mut assets = Assets::new();
+        assert!(assets.load_sprite(&path).is_err());
+
+        assets.set_fallback(&path, "ab");
+        assert_eq!(assets.load_sprite(&path).unwrap().width(), 2);
+    }
+
+    #[test]
+    fn parses_animations() {
+        let animation = parse_animation("@frame_duration 3\nab\n---\ncd\nef\n---\ngh").unwrap();
+        assert_eq!(animation.frame_duration, 3);
+        assert_eq!(animation.frames.len(), 3);
+        assert_eq!(animation.frames[1].size(), U16Point::new(2, 2));
+        assert_eq!(animation.frame(7).rows[0][0].ch, 'g');
+
+        let animation = parse_animation("ab").unwrap();
+        assert_eq!(animation.frame_duration, ANIMATION_DEFAULT_FRAME_DURATION);
+        assert!(parse_animation("").is_err());
+        assert!(parse_animation("@frame_duration x\nab").is_err());
+    }
+
+    #[test]
+    fn reloads_changed_files() {
+        let path = test_file("reload.txt", "ab");
+        let mut assets = Assets::new();
+        assets.set_hot_reload(true);
+        assets.load_sprite(&path).unwrap();
+        assert!(assets.reload_changed().is_empty());
+
+        fs::write(&path, "abc").unwrap();
+        let file = fs::File::options().write(true).open(&path).unwrap();
+        file.set_modified(SystemTime::UNIX_EPOCH).unwrap();
+
+        // Polling is throttled.
+        assert!(assets.reload_changed().is_empty());
+        assets.last_poll_ms = 0;
+        assert_eq!(assets.reload_changed(), vec![path.clone()]);
+        assert_eq!(assets.sprite(&path).unwrap().width(), 3);
+
+        // A broken file keeps the last good version.
+        let color_map_path = test_file("reload-map.txt", "a 1");
+        assets.load_color_map(&color_map_path).unwrap();
+        fs::write(&color_map_path, "a b c d").unwrap();
+        let file = fs::File::options()
+            .write(true)
+            .open(&color_map_path)
+            .unwrap();
+        file.set_modified(SystemTime::UNIX_EPOCH).unwrap();
+        assets.last_poll_ms = 0;
+        assert!(assets.reload_changed().is_empty());
+        assert_eq!(
+            assets.color_map(&color_map_path).unwrap().color_of('a'),
+            Some(1)
+        );
+
+        fs::remove_file(path).unwrap();
+        fs::remove_file(color_map_path).unwrap();
+    }
+}
@@ -0,0 +1,539 @@
+use std::collections::HashMap;
+use std::io;
+
+use crate::common::*;
+use crate::sprite::{Sprite, SpriteCell};
+
+/// 5 rows of full blocks, bundled with the engine.
+pub const BANNER_BLOCK_FONT: &str = include_str!("../fonts/block.flf");
+/// The block font squeezed into 3 rows of half blocks.
+pub const BANNER_SMALL_BLOCK_FONT: &str = include_str!("../fonts/small_block.flf");
+
+/// Horizontal smushing rules from the FIGlet spec, combined as bits in `FigLayout::Smushing`.
+pub const FIG_SMUSH_EQUAL: u8 = 1;
+pub const FIG_SMUSH_UNDERSCORE: u8 = 2;
+pub const FIG_SMUSH_HIERARCHY: u8 = 4;
+pub const FIG_SMUSH_OPPOSITE_PAIR: u8 = 8;
+pub const FIG_SMUSH_BIG_X: u8 = 16;
+pub const FIG_SMUSH_HARDBLANK: u8 = 32;
+
+const FIG_SIGNATURE: &str = "flf2a";
+const FIG_REQUIRED_EXTRA_CHARS: [u32; 7] = [196, 214, 220, 228, 246, 252, 223];
+const FIG_HIERARCHY_CLASSES: [&str; 6] = ["|", "/\\", "[]", "{}", "()", "<>"];
+
+/// How neighbouring letters are put together.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FigLayout {
+    /// Every letter keeps its full width.
+    FullWidth,
+    /// Letters are moved together until they touch.
+    Kerning,
+    /// Letters are moved one column further, merging the touching characters by the given
+    /// `FIG_SMUSH_*` rules. `0` merges anything, keeping the right hand character.
+    Smushing(u8),
+}
+
+/// FIGlet font, parsed from the `.flf` format.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FigFont {
+    pub height: usize,
+    /// Drawn as a space, but never removed by kerning or smushing.
+    pub hardblank: char,
+    /// The layout the font was designed for.
+    pub layout: FigLayout,
+    glyphs: HashMap<char, Vec<Vec<char>>>,
+}
+
+impl FigFont {
+    pub fn block() -> Self {
+        Self::parse(BANNER_BLOCK_FONT).expect("Bundled font is valid")
+    }
+
+    pub fn small_block() -> Self {
+        Self::parse(BANNER_SMALL_BLOCK_FONT).expect("Bundled font is valid")
+    }
+
+    /// Reads the header, the required ASCII and German characters and any code tagged ones.
+    /// Fonts ending early are accepted with the characters read so far.
+    pub fn parse(content: &str) -> io::Result<Self> {
+        let mut lines = content.lines();
+
+        let header = lines
+            .next()
+            .ok_or_else(|| invalid_data("Empty font file".to_string()))?;
+        let mut header_chars = header
+            .strip_prefix(FIG_SIGNATURE)
+            .ok_or_else(|| invalid_data("Not a FIGlet font".to_string()))?
+            .chars();
+        let hardblank = header_chars
+            .next()
+            .ok_or_else(|| invalid_data("Missing hardblank".to_string()))?;
+        let params = header_chars
+            .as_str()
+            .split_whitespace()
+            .map(|param| {
+                param
+                    .parse::<i64>()
+                    .map_err(|_| invalid_data(format!("Invalid font header: {}", header)))
+            })
+            .collect::<io::Result<Vec<i64>>>()?;
+        if params.len() < 5 || params[0] < 1 {
+            return Err(invalid_data(format!("Invalid font header: {}", header)));
+        }
+
+        let height = params[0] as usize;
+        let layout = match params.get(6) {
+            Some(full_layout) => layout_from_full(*full_layout),
+            None => layout_from_old(params[3]),
+        };
+
+        for _ in 0..params[4] {
+            lines.next();
+        }
+
+        let mut glyphs = HashMap::new();
+        let required = (32..=126).chain(FIG_REQUIRED_EXTRA_CHARS);
+        for code in required {
+            let Some(glyph) = read_glyph(&mut lines, height) else {
+                break;
+            };
+            if let Some(ch) = char::from_u32(code) {
+                glyphs.insert(ch, glyph);
+            }
+        }
+
+        while let Some(tag_line) = lines.next() {
+            if tag_line.trim().is_empty() {
+                continue;
+            }
+
+            let code = tag_line
+                .split_whitespace()
+                .next()
+                .and_then(parse_char_code)
+                .ok_or_else(|| invalid_data(format!("Invalid character code: {}", tag_line)))?;
+            let Some(glyph) = read_glyph(&mut lines, height) else {
+                break;
+            };
+            if let Some(ch) = code.and_then(char::from_u32) {
+                glyphs.insert(ch, glyph);
+            }
+        }
+
+        Ok(Self {
+            height,
+            hardblank,
+            layout,
+            glyphs,
+        })
+    }
+
+    pub fn has_glyph(&self, ch: char) -> bool {
+        self.glyphs.contains_key(&ch)
+    }
+
+    /// Rows of cells with the index of the letter they came from. Characters without a glyph
+    /// are skipped, `\n` starts a new line of letters below.
+    fn render_cells(&self, text: &str, layout: FigLayout) -> Vec<Vec<(char, usize)>> {
+        let mut out = vec![];
+        let mut letter = 0;
+
+        for line in text.split('\n') {
+            let mut rows: Vec<Vec<(char, usize)>> = vec![vec![]; self.height];
+            let mut previous_width = 0;
+
+            for ch in line.chars() {
+                let Some(glyph) = self.glyphs.get(&ch) else {
+                    continue;
+                };
+                let width = glyph.iter().map(|row| row.len()).max().unwrap_or(0);
+
+                let amount = self.smush_amount(&rows, glyph, layout, previous_width, width);
+                let start = rows[0].len() - amount;
+
+                for (row, glyph_row) in rows.iter_mut().zip(glyph) {
+                    for (i, ch) in glyph_row.iter().enumerate() {
+                        let Some(cell) = row.get_mut(start + i) else {
+                            row.push((*ch, letter));
+                            continue;
+                        };
+
+                        if cell.0 == ' ' {
+                            *cell = (*ch, letter);
+                        } else if *ch != ' ' {
+                            let merged = self
+                                .smush(cell.0, *ch, layout, previous_width, width)
+                                .unwrap_or(*ch);
+                            if merged != cell.0 {
+                                *cell = (merged, letter);
+                            }
+                        }
+                    }
+                }
+
+                previous_width = width;
+                if !ch.is_whitespace() {
+                    letter += 1;
+                }
+            }
+
+            out.extend(rows);
+        }
+
+        out
+    }
+
+    /// Columns the next glyph can be moved left into the rendered ones.
+    fn smush_amount(
+        &self,
+        rows: &[Vec<(char, usize)>],
+        glyph: &[Vec<char>],
+        layout: FigLayout,
+        previous_width: usize,
+        width: usize,
+    ) -> usize {
+        if layout == FigLayout::FullWidth {
+            return 0;
+        }
+
+        let mut amount = width;
+        for (row, glyph_row) in rows.iter().zip(glyph) {
+            let glyph_start = glyph_row
+                .iter()
+                .position(|ch| *ch != ' ')
+                .unwrap_or(glyph_row.len());
+
+            let row_amount = match row.iter().rposition(|cell| cell.0 != ' ') {
+                None => glyph_start + row.len(),
+                Some(end) => {
+                    let touching = glyph_start + row.len() - 1 - end;
+                    let can_smush = glyph_row.get(glyph_start).is_some_and(|ch| {
+                        self.smush(row[end].0, *ch, layout, previous_width, width)
+                            .is_some()
+                    });
+                    touching + can_smush as usize
+                }
+            };
+            amount = amount.min(row_amount);
+        }
+
+        amount.min(rows[0].len())
+    }
+
+    /// The character two overlapping ones merge into, `None` if they can't be merged.
+    fn smush(
+        &self,
+        lhs: char,
+        rhs: char,
+        layout: FigLayout,
+        previous_width: usize,
+        width: usize,
+    ) -> Option<char> {
+        if lhs == ' ' {
+            return Some(rhs);
+        }
+        if rhs == ' ' {
+            return Some(lhs);
+        }
+        // Merging into single column letters makes them unreadable.
+        if previous_width < 2 || width < 2 {
+            return None;
+        }
+
+        let FigLayout::Smushing(rules) = layout else {
+            return None;
+        };
+        let hardblank = self.hardblank;
+
+        if rules == 0 {
+            return Some(if rhs == hardblank { lhs } else { rhs });
+        }
+
+        if lhs == hardblank || rhs == hardblank {
+            return (rules & FIG_SMUSH_HARDBLANK != 0 && lhs == rhs).then_some(lhs);
+        }
+        if rules & FIG_SMUSH_EQUAL != 0 && lhs == rhs {
+            return Some(lhs);
+        }
+        if rules & FIG_SMUSH_UNDERSCORE != 0 {
+            let border = "|/\\[]{}()<>";
+            if lhs == '_' && border.contains(rhs) {
+                return Some(rhs);
+            }
+            if rhs == '_' && border.contains(lhs) {
+                return Some(lhs);
+            }
+        }
+        if rules & FIG_SMUSH_HIERARCHY != 0 {
+            let class = |ch: char| FIG_HIERARCHY_CLASSES.iter().position(|c| c.contains(ch));
+            if let (Some(lhs_class), Some(rhs_class)) = (class(lhs), class(rhs))
+                && lhs_class != rhs_class
+            {
+                return Some(if lhs_class > rhs_class { lhs } else { rhs });
+            }
+        }
+        if rules & FIG_SMUSH_OPPOSITE_PAIR != 0
+            && matches!(
+                (lhs, rhs),
+                ('[', ']') | (']', '[') | ('{', '}') | ('}', '{') | ('(', ')') | (')', '(')
+            )
+        {
+            return Some('|');
+        }
+        if rules & FIG_SMUSH_BIG_X != 0 {
+            match (lhs, rhs) {
+                ('/', '\\') => return Some('|'),
+                ('\\', '/') => return Some('Y'),
+                ('>', '<') => return Some('X'),
+                _ => {}
+            }
+        }
+
+        None
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BannerColors {
+    Solid(u8),
+    /// Cycled through from the top row down.
+    Rows(Vec<u8>),
+    /// Cycled through letter by letter, spaces are skipped.
+    Letters(Vec<u8>),
+}
+
+/// Renders text in large letters of a FIGlet font into a sprite.
+#[derive(Debug, Clone)]
+pub struct Banner<'a> {
+    pub font: &'a FigFont,
+    pub layout: FigLayout,
+    pub colors: BannerColors,
+}
+
+impl<'a> Banner<'a> {
+    /// Uses the layout the font was designed for.
+    pub fn new(font: &'a FigFont) -> Self {
+        Self {
+            font,
+            layout: font.layout,
+            colors: BannerColors::Solid(DEFAULT_COLOR_CODE),
+        }
+    }
+
+    pub fn with_layout(mut self, layout: FigLayout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    pub fn with_colors(mut self, colors: BannerColors) -> Self {
+        self.colors = colors;
+        self
+    }
+
+    /// Blank cells (including hardblanks) are spaces, so they are transparent when drawn.
+    pub fn render(&self, text: &str) -> Sprite {
+        let pick = |colors: &[u8], i: usize| {
+            colors
+                .get(i % colors.len().max(1))
+                .copied()
+                .unwrap_or(DEFAULT_COLOR_CODE)
+        };
+
+        let rows = self
+            .font
+            .render_cells(text, self.layout)
+            .into_iter()
+            .enumerate()
+            .map(|(row_i, row)| {
+                row.into_iter()
+                    .map(|(ch, letter)| SpriteCell {
+                        ch: if ch == self.font.hardblank { ' ' } else { ch },
+                        color: match &self.colors {
+                            BannerColors::Solid(color) => *color,
+                            BannerColors::Rows(colors) => pick(colors, row_i),
+                            BannerColors::Letters(colors) => pick(colors, letter),
+                        },
+                    })
+                    .collect()
+            })
+            .collect();
+
+        Sprite { rows }
+    }
+}
+
+fn layout_from_full(full_layout: i64) -> FigLayout {
+    if full_layout & 128 != 0 {
+        FigLayout::Smushing((full_layout & 63) as u8)
+    } else if full_layout & 64 != 0 {
+        FigLayout::Kerning
+    } else {
+        FigLayout::FullWidth
+    }
+}
+
+fn layout_from_old(old_layout: i64) -> FigLayout {
+    match old_layout {
+        ..0 => FigLayout::FullWidth,
+        0 => FigLayout::Kerning,
+        rules => FigLayout::Smushing((rules & 63) as u8),
+    }
+}
+
+/// Decimal, `0x` hex or `0` octal. `Some(None)` for negative codes, which are valid in the
+/// format but don't map to characters.
+fn parse_char_code(code: &str) -> Option<Option<u32>> {
+    let (negative, code) = match code.strip_prefix('-') {
+        Some(code) => (true, code),
+        None => (false, code),
+    };
+
+    let value = if let Some(hex) = code.strip_prefix("0x").or_else(|| code.strip_prefix("0X")) {
+        u32::from_str_radix(hex, 16).ok()?
+    } else if code.len() > 1 && code.starts_with('0') {
+        u32::from_str_radix(&code[1..], 8).ok()?
+    } else {
+        code.parse().ok()?
+    };
+
+    Some((!negative).then_some(value))
+}
+
+/// `height` lines, each ending with one or more end mark characters.
+fn read_glyph<'a>(
+    lines: &mut impl Iterator<Item = &'a str>,
+    height: usize,
+) -> Option<Vec<Vec<char>>> {
+    let mut rows = vec![];
+
+    for _ in 0..height {
+        let line = lines.next()?.trim_end();
+        let row = match line.chars().last() {
+            Some(end_mark) => line.trim_end_matches(end_mark),
+            None => line,
+        };
+        rows.push(row.chars().collect::<Vec<char>>());
+    }
+
+    let width = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+    for row in &mut rows {
+        row.resize(width, ' ');
+    }
+
+    Some(rows)
+}
+
+fn invalid_data(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Three glyphs ("/", "\\" and "i", 2 rows tall) plus a code tagged one.
+    fn test_font(layout: &str) -> FigFont {
+        let mut content = format!("flf2a$ 2 2 4 {} 1\ncomment\n", layout);
+        for code in (32..=126).chain(FIG_REQUIRED_EXTRA_CHARS) {
+            let glyph = match char::from_u32(code).unwrap() {
+                ' ' => "$@\n$@@\n",
+                '/' => " /@\n/ @@\n",
+                '\\' => "\\ @\n \\@@\n",
+                'i' => "i@\ni@@\n",
+                _ => "@\n@@\n",
+            };
+            content.push_str(glyph);
+        }
+        content.push_str("0x263A smiley\n:)@\n  @@\n");
+
+        FigFont::parse(&content).unwrap()
+    }
+
+    fn text(sprite: &Sprite) -> Vec<String> {
+        sprite
+            .rows
+            .iter()
+            .map(|row| row.iter().map(|cell| cell.ch).collect())
+            .collect()
+    }
+
+    #[test]
+    fn parses_bundled_fonts() {
+        for font in [FigFont::block(), FigFont::small_block()] {
+            assert!((32..=126).all(|code| font.has_glyph(char::from_u32(code).unwrap())));
+            assert!(font.has_glyph('ß'));
+            assert_eq!(font.layout, FigLayout::Kerning);
+        }
+        assert_eq!(FigFont::block().height, 5);
+        assert_eq!(FigFont::small_block().height, 3);
+    }
+
+    #[test]
+    fn parses_header_and_code_tags() {
+        let font = test_font("-1");
+        assert_eq!(font.height, 2);
+        assert_eq!(font.layout, FigLayout::FullWidth);
+        assert!(font.has_glyph('☺'));
+
+        assert_eq!(test_font("0").layout, FigLayout::Kerning);
+        assert_eq!(test_font("24").layout, FigLayout::Smushing(24));
+        assert!(FigFont::parse("flf2b$ 2 2 4 0 0").is_err());
+        assert_eq!(parse_char_code("0x41"), Some(Some(65)));
+        assert_eq!(parse_char_code("0101"), Some(Some(65)));
+        assert_eq!(parse_char_code("-5"), Some(None));
+    }
+
+    #[test]
+    fn full_width_kerning_and_smushing() {
+        let font = test_font("-1");
+        let render = |layout| text(&Banner::new(&font).with_layout(layout).render("/\\"));
+
+        assert_eq!(render(FigLayout::FullWidth), vec![" /\\ ", "/  \\"]);
+        assert_eq!(render(FigLayout::Kerning), vec![" /\\ ", "/  \\"]);
+        assert_eq!(
+            render(FigLayout::Smushing(FIG_SMUSH_BIG_X)),
+            vec![" | ", "/ \\"]
+        );
+        assert_eq!(
+            text(
+                &Banner::new(&font)
+                    .with_layout(FigLayout::Kerning)
+                    .render("\\/")
+            ),
+            vec!["\\  /", " \\/ "]
+        );
+    }
+
+    #[test]
+    fn single_column_letters_are_not_smushed() {
+        let font = test_font("-1");
+        let sprite = Banner::new(&font)
+            .with_layout(FigLayout::Smushing(0))
+            .render("ii");
+
+        assert_eq!(text(&sprite), vec!["ii", "ii"]);
+    }
+
+    #[test]
+    fn hardblanks_become_spaces() {
+        let font = test_font("0");
+        assert_eq!(text(&Banner::new(&font).render("i i")), vec!["i i", "i i"]);
+    }
+
+    #[test]
+    fn colors() {
+        let font = test_font("-1");
+
+        let sprite = Banner::new(&font)
+            .with_colors(BannerColors::Letters(vec![31, 32]))
+            .render("i ii");
+        let colors: Vec<u8> = sprite.rows[0].iter().map(|cell| cell.color).collect();
+        assert_eq!(colors, vec![31, 32, 32, 31]);
+
+        let sprite = Banner::new(&font)
+            .with_colors(BannerColors::Rows(vec![33, 34]))
+            .render("i\ni");
+        let colors: Vec<u8> = sprite.rows.iter().map(|row| row[0].color).collect();
+        assert_eq!(colors, vec![33, 34, 33, 34]);
+    }
+}
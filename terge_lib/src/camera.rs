@@ -0,0 +1,179 @@
+use crate::common::*;
+
+/// Maps world coordinates to screen coordinates.
+///
+/// `pos` is the world position shown at the upper-left corner of the viewport. The viewport is
+/// usually the full terminal, but can be any part of it starting at `screen_offset`.
+#[derive(Debug, Clone)]
+pub struct Camera {
+    pub pos: F32Point,
+    pub viewport_size: U16Point,
+    pub screen_offset: U16Point,
+    /// Half size of the area around the viewport center the target can move in freely.
+    pub dead_zone: F32Point,
+    /// Portion of the distance to the desired position covered each `follow` call. 1.0 snaps.
+    pub smoothing: f32,
+    /// World area (start, end) the viewport is kept inside, if set.
+    pub bounds: Option<(F32Point, F32Point)>,
+}
+
+impl Camera {
+    pub fn new(viewport_size: U16Point) -> Self {
+        Self {
+            pos: F32Point::new(0.0, 0.0),
+            viewport_size,
+            screen_offset: U16Point::new(0, 0),
+            dead_zone: F32Point::new(0.0, 0.0),
+            smoothing: 1.0,
+            bounds: None,
+        }
+    }
+
+    pub fn set_viewport(&mut self, screen_offset: U16Point, viewport_size: U16Point) {
+        self.screen_offset = screen_offset;
+        self.viewport_size = viewport_size;
+        self.clamp_to_bounds();
+    }
+
+    pub fn set_bounds(&mut self, start: F32Point, end: F32Point) {
+        self.bounds = Some((start, end));
+        self.clamp_to_bounds();
+    }
+
+    /// `None` if the world point is not in the viewport.
+    pub fn world_to_screen(&self, p: F32Point) -> Option<U16Point> {
+        let local = (p - self.pos).floor();
+
+        if local.x < 0.0
+            || local.y < 0.0
+            || local.x >= self.viewport_size.x as f32
+            || local.y >= self.viewport_size.y as f32
+        {
+            return None;
+        }
+
+        Some(local.saturating_u16() + self.screen_offset)
+    }
+
+    pub fn screen_to_world(&self, p: U16Point) -> F32Point {
+        p.as_f32() - self.screen_offset.as_f32() + self.pos
+    }
+
+    /// Inclusive range of world cells (min, max) at least partially in the viewport.
+    pub fn visible_cells(&self) -> (I32Point, I32Point) {
+        let min = self.pos.floor().saturating_i32();
+        (
+            min,
+            min + self.viewport_size.cast::<i32>() - I32Point::new(1, 1),
+        )
+    }
+
+    pub fn center(&self) -> F32Point {
+        self.pos + self.viewport_size.as_f32() / 2.0
+    }
+
+    pub fn center_on(&mut self, target: F32Point) {
+        self.pos = self.pos_centered_on(target);
+        self.clamp_to_bounds();
+    }
+
+    /// Moves the camera towards the target once it leaves the dead zone. Meant to be called once
+    /// per update.
+    pub fn follow(&mut self, target: F32Point) {
+        let center = self.center();
+        let mut desired_center = center;
+
+        if target.x < center.x - self.dead_zone.x {
+            desired_center.x = target.x + self.dead_zone.x;
+        } else if target.x > center.x + self.dead_zone.x {
+            desired_center.x = target.x - self.dead_zone.x;
+        }
+
+        if target.y < center.y - self.dead_zone.y {
+            desired_center.y = target.y + self.dead_zone.y;
+        } else if target.y > center.y + self.dead_zone.y {
+            desired_center.y = target.y - self.dead_zone.y;
+        }
+
+        let desired_pos = self.pos_centered_on(desired_center);
+        let smoothing = self.smoothing.clamp(0.0, 1.0);
+        self.pos += (desired_pos - self.pos) * smoothing;
+
+        self.clamp_to_bounds();
+    }
+
+    fn pos_centered_on(&self, target: F32Point) -> F32Point {
+        target - self.viewport_size.as_f32() / 2.0
+    }
+
+    fn clamp_to_bounds(&mut self) {
+        if let Some((start, end)) = self.bounds {
+            // When the world is smaller than the viewport the start wins.
+            let max = end - self.viewport_size.as_f32();
+            self.pos.x = self.pos.x.min(max.x).max(start.x);
+            self.pos.y = self.pos.y.min(max.y).max(start.y);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn follows_outside_of_the_dead_zone() {
+        let mut camera = Camera::new(U16Point::new(10, 4));
+        camera.dead_zone = F32Point::new(2.0, 1.0);
+        assert_eq!(camera.center(), F32Point::new(5.0, 2.0));
+
+        // Inside the dead zone nothing moves.
+        camera.follow(F32Point::new(6.5, 3.0));
+        assert_eq!(camera.pos, F32Point::new(0.0, 0.0));
+
+        // Outside the target ends up on the edge of the dead zone.
+        camera.follow(F32Point::new(10.0, 0.0));
+        assert_eq!(camera.center(), F32Point::new(8.0, 1.0));
+
+        camera.smoothing = 0.5;
+        camera.follow(F32Point::new(14.0, 1.0));
+        assert_eq!(camera.center(), F32Point::new(10.0, 1.0));
+    }
+
+    #[test]
+    fn stays_inside_of_the_bounds() {
+        let mut camera = Camera::new(U16Point::new(10, 4));
+        camera.set_bounds(F32Point::new(0.0, 0.0), F32Point::new(20.0, 8.0));
+
+        camera.center_on(F32Point::new(100.0, 100.0));
+        assert_eq!(camera.pos, F32Point::new(10.0, 4.0));
+        camera.follow(F32Point::new(-50.0, -50.0));
+        assert_eq!(camera.pos, F32Point::new(0.0, 0.0));
+
+        // A world smaller than the viewport sticks to its start.
+        camera.set_bounds(F32Point::new(0.0, 0.0), F32Point::new(6.0, 2.0));
+        camera.center_on(F32Point::new(3.0, 1.0));
+        assert_eq!(camera.pos, F32Point::new(0.0, 0.0));
+    }
+
+    #[test]
+    fn converts_between_world_and_screen() {
+        let mut camera = Camera::new(U16Point::new(10, 4));
+        camera.set_viewport(U16Point::new(2, 1), U16Point::new(10, 4));
+        camera.pos = F32Point::new(5.0, 5.0);
+
+        assert_eq!(
+            camera.world_to_screen(F32Point::new(5.5, 6.0)),
+            Some(U16Point::new(2, 2))
+        );
+        assert_eq!(camera.world_to_screen(F32Point::new(4.9, 6.0)), None);
+        assert_eq!(camera.world_to_screen(F32Point::new(15.0, 6.0)), None);
+        assert_eq!(
+            camera.screen_to_world(U16Point::new(2, 2)),
+            F32Point::new(5.0, 6.0)
+        );
+        assert_eq!(
+            camera.visible_cells(),
+            (I32Point::new(5, 5), I32Point::new(14, 8))
+        );
+    }
+}
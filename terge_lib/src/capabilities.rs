@@ -0,0 +1,329 @@
+use std::borrow::Cow;
+
+use crate::common::*;
+
+/// The 16 ANSI colors as xterm shows them, normal ones first.
+const CAPABILITIES_ANSI_COLORS: [RgbColor; 16] = [
+    RgbColor(0, 0, 0),
+    RgbColor(205, 0, 0),
+    RgbColor(0, 205, 0),
+    RgbColor(205, 205, 0),
+    RgbColor(0, 0, 238),
+    RgbColor(205, 0, 205),
+    RgbColor(0, 205, 205),
+    RgbColor(229, 229, 229),
+    RgbColor(127, 127, 127),
+    RgbColor(255, 0, 0),
+    RgbColor(0, 255, 0),
+    RgbColor(255, 255, 0),
+    RgbColor(92, 92, 255),
+    RgbColor(255, 0, 255),
+    RgbColor(0, 255, 255),
+    RgbColor(255, 255, 255),
+];
+
+/// Channel values of the 6x6x6 color cube of the 256 color palette.
+const CAPABILITIES_CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// Colors a terminal can show, each level includes the ones below.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ColorSupport {
+    /// No colors, eg. `TERM=dumb` or `NO_COLOR` set. Bold, underline and friends still work.
+    Monochrome,
+    /// 30-37 and 40-47.
+    Ansi8,
+    /// The bright 90-97 and 100-107 too.
+    Ansi16,
+    /// `38;5;n`.
+    Indexed256,
+    /// `38;2;r;g;b`.
+    TrueColor,
+}
+
+impl ColorSupport {
+    /// The SGR code as the terminal can show it: bright colors turn normal on 8 color terminals
+    /// and colors are dropped on monochrome ones. Other codes are kept.
+    pub fn sgr_code(self, code: u8) -> Option<u8> {
+        let is_color = matches!(code, 30..=49 | 90..=97 | 100..=107);
+        match self {
+            ColorSupport::Monochrome if is_color => None,
+            ColorSupport::Ansi8 if matches!(code, 90..=97 | 100..=107) => Some(code - 60),
+            _ => Some(code),
+        }
+    }
+
+    /// SGR parameters for the foreground color closest to `color` the terminal can show.
+    pub fn rgb_sgr(self, color: RgbColor) -> Option<String> {
+        match self {
+            ColorSupport::Monochrome => None,
+            ColorSupport::Ansi8 => Some((30 + nearest_ansi_color(color, 8)).to_string()),
+            ColorSupport::Ansi16 => {
+                let index = nearest_ansi_color(color, 16);
+                let code = if index < 8 {
+                    30 + index
+                } else {
+                    90 + index - 8
+                };
+                Some(code.to_string())
+            }
+            ColorSupport::Indexed256 => Some(format!("38;5;{}", rgb_to_256(color))),
+            ColorSupport::TrueColor => Some(format!("38;2;{};{};{}", color.0, color.1, color.2)),
+        }
+    }
+}
+
+/// What the terminal can do, see `Capabilities::detect`. `Gfx` degrades its output to match:
+/// colors are brought down to `colors` and without `unicode` every non-ASCII character is
+/// replaced (see `ascii_fallback`), so box drawing, blocks and sprites keep their shape.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capabilities {
+    pub colors: ColorSupport,
+    /// Box drawing, blocks and the rest of Unicode can be printed.
+    pub unicode: bool,
+    /// Mouse events can be captured.
+    pub mouse: bool,
+    /// The kitty keyboard protocol is understood, so key releases and all modifier combinations
+    /// can be reported once enabled.
+    pub keyboard_enhancement: bool,
+}
+
+impl Default for Capabilities {
+    /// What the engine assumed before detection: a modern terminal.
+    fn default() -> Self {
+        Self {
+            colors: ColorSupport::TrueColor,
+            unicode: true,
+            mouse: true,
+            keyboard_enhancement: false,
+        }
+    }
+}
+
+impl Capabilities {
+    /// Looks at the environment of the process and asks the terminal about the keyboard
+    /// protocol, which is only answered by terminals on STDIN/STDOUT.
+    pub fn detect() -> Self {
+        let mut capabilities = Self::from_env(|name| std::env::var(name).ok());
+        capabilities.keyboard_enhancement = capabilities.mouse
+            && crossterm::terminal::supports_keyboard_enhancement().unwrap_or(false);
+        capabilities
+    }
+
+    /// Capabilities from environment variables given by `var`: `TERM`, `COLORTERM`, `NO_COLOR`,
+    /// the locale (`LC_ALL`, `LC_CTYPE`, `LANG`) and `WT_SESSION` of Windows Terminal. The
+    /// keyboard protocol can't be told from those, it stays off.
+    pub fn from_env(var: impl Fn(&str) -> Option<String>) -> Self {
+        let var = |name: &str| var(name).filter(|value| !value.is_empty());
+        let term = var("TERM").unwrap_or_default().to_lowercase();
+        let windows_terminal = var("WT_SESSION").is_some();
+        let dumb = (term.is_empty() && !cfg!(windows)) || term == "dumb";
+        // Hardware terminals and the Linux console without gpm.
+        let basic = term.starts_with("vt") || term == "linux" || term == "ansi";
+
+        let colorterm = var("COLORTERM").unwrap_or_default().to_lowercase();
+        let colors = if dumb || var("NO_COLOR").is_some() || term.starts_with("vt") {
+            ColorSupport::Monochrome
+        } else if colorterm == "truecolor" || colorterm == "24bit" || windows_terminal {
+            ColorSupport::TrueColor
+        } else if term.contains("256color") {
+            ColorSupport::Indexed256
+        } else if term == "linux" || term == "ansi" {
+            ColorSupport::Ansi8
+        } else {
+            ColorSupport::Ansi16
+        };
+
+        let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+            .into_iter()
+            .find_map(var)
+            .unwrap_or_default()
+            .to_lowercase();
+        let unicode =
+            (cfg!(windows) && !dumb) || locale.contains("utf-8") || locale.contains("utf8");
+
+        Self {
+            colors,
+            unicode,
+            mouse: !dumb && !basic,
+            keyboard_enhancement: false,
+        }
+    }
+}
+
+/// `text` with every non-ASCII character replaced by `ascii_fallback`.
+pub fn to_ascii(text: &str) -> Cow<'_, str> {
+    if text.is_ascii() {
+        Cow::Borrowed(text)
+    } else {
+        Cow::Owned(text.chars().map(ascii_fallback).collect())
+    }
+}
+
+/// One ASCII character in place of `ch` taking up the same cell: lines of box drawing become
+/// `-`, `|` and `+`, shades and blocks `.`, `:` and `#`, arrows `<`, `>`, `^` and `v`.
+/// Anything without a look-alike is `?`.
+pub fn ascii_fallback(ch: char) -> char {
+    match ch {
+        ch if ch.is_ascii() => ch,
+        '─' | '━' | '═' | '┄' | '┅' | '┈' | '┉' | '╌' | '╍' | '╴' | '╶' | '╸' | '╺' | '╼' | '╾' => {
+            '-'
+        }
+        '│' | '┃' | '║' | '┆' | '┇' | '┊' | '┋' | '╎' | '╏' | '╵' | '╷' | '╹' | '╻' | '╽' | '╿' => {
+            '|'
+        }
+        '╱' => '/',
+        '╲' => '\\',
+        '╳' => 'X',
+        '\u{2500}'..='\u{257F}' => '+',
+        '░' => '.',
+        '▒' => ':',
+        '\u{2580}'..='\u{259F}' | '■' | '\u{2800}'..='\u{28FF}' => '#',
+        '←' | '◀' | '◄' => '<',
+        '→' | '▶' | '►' => '>',
+        '↑' | '▲' => '^',
+        '↓' | '▼' => 'v',
+        '•' | '·' | '∙' => '.',
+        '●' | '○' | '◯' | '◆' | '◇' => 'o',
+        '…' => '.',
+        '‘' | '’' => '\'',
+        '“' | '”' => '"',
+        '–' | '—' => '-',
+        '×' => 'x',
+        '\u{A0}' => ' ',
+        _ => '?',
+    }
+}
+
+/// Index of the closest of the first `count` ANSI colors.
+fn nearest_ansi_color(color: RgbColor, count: usize) -> u8 {
+    let distance = |other: &RgbColor| {
+        let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2);
+        d(color.0, other.0) + d(color.1, other.1) + d(color.2, other.2)
+    };
+
+    CAPABILITIES_ANSI_COLORS[..count]
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, other)| distance(other))
+        .map(|(i, _)| i as u8)
+        .unwrap_or(0)
+}
+
+/// Closest color of the 6x6x6 cube or the gray ramp of the 256 color palette.
+fn rgb_to_256(color: RgbColor) -> u8 {
+    let RgbColor(r, g, b) = color;
+    if r == g && g == b {
+        return match r {
+            0..8 => 16,
+            248.. => 231,
+            _ => 232 + ((r - 8 + 5) / 10).min(23),
+        };
+    }
+
+    let level = |c: u8| {
+        CAPABILITIES_CUBE_LEVELS
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, level)| (c as i16 - **level as i16).abs())
+            .map(|(i, _)| i as u8)
+            .unwrap_or(0)
+    };
+    16 + 36 * level(r) + 6 * level(g) + level(b)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::io;
+
+    use super::*;
+    use crate::gfx::Gfx;
+    use crate::rect::Rect;
+
+    fn from_vars(vars: &[(&str, &str)]) -> Capabilities {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        Capabilities::from_env(|name| vars.get(name).cloned())
+    }
+
+    #[test]
+    fn detects_from_env() {
+        let modern = from_vars(&[
+            ("TERM", "xterm-256color"),
+            ("COLORTERM", "truecolor"),
+            ("LANG", "en_US.UTF-8"),
+        ]);
+        assert_eq!(modern.colors, ColorSupport::TrueColor);
+        assert!(modern.unicode);
+        assert!(modern.mouse);
+
+        let console = from_vars(&[("TERM", "linux"), ("LANG", "C"), ("LC_ALL", "")]);
+        assert_eq!(console.colors, ColorSupport::Ansi8);
+        assert!(!console.unicode);
+        assert!(!console.mouse);
+
+        // The more specific locale variable wins.
+        let no_color = from_vars(&[
+            ("TERM", "xterm-256color"),
+            ("NO_COLOR", "1"),
+            ("LC_ALL", "C"),
+            ("LANG", "en_US.UTF-8"),
+        ]);
+        assert_eq!(no_color.colors, ColorSupport::Monochrome);
+        assert!(!no_color.unicode);
+
+        let dumb = from_vars(&[("TERM", "dumb")]);
+        assert_eq!(dumb.colors, ColorSupport::Monochrome);
+        assert!(!dumb.mouse);
+    }
+
+    #[test]
+    fn degrades_colors() {
+        assert_eq!(ColorSupport::Ansi8.sgr_code(91), Some(31));
+        assert_eq!(ColorSupport::Ansi8.sgr_code(1), Some(1));
+        assert_eq!(ColorSupport::Ansi16.sgr_code(91), Some(91));
+        assert_eq!(ColorSupport::Monochrome.sgr_code(32), None);
+        assert_eq!(ColorSupport::Monochrome.sgr_code(4), Some(4));
+
+        let orange = RgbColor(255, 135, 0);
+        assert_eq!(
+            ColorSupport::TrueColor.rgb_sgr(orange).unwrap(),
+            "38;2;255;135;0"
+        );
+        assert_eq!(
+            ColorSupport::Indexed256.rgb_sgr(orange).unwrap(),
+            "38;5;208"
+        );
+        assert_eq!(ColorSupport::Ansi16.rgb_sgr(orange).unwrap(), "33");
+        assert_eq!(
+            ColorSupport::Ansi16
+                .rgb_sgr(RgbColor(250, 250, 10))
+                .unwrap(),
+            "93"
+        );
+        assert_eq!(
+            ColorSupport::Ansi8.rgb_sgr(RgbColor(250, 250, 10)).unwrap(),
+            "33"
+        );
+        assert_eq!(ColorSupport::Monochrome.rgb_sgr(orange), None);
+        assert_eq!(rgb_to_256(RgbColor(128, 128, 128)), 244);
+    }
+
+    #[test]
+    fn ascii_output_without_unicode() {
+        assert_eq!(to_ascii("╔═╗ ░▓ …"), "+-+ .# .");
+
+        let mut gfx = Gfx::with_output(Box::new(io::sink()));
+        gfx.resize(6, 3);
+        gfx.capabilities.unicode = false;
+        gfx.draw_rect(&Rect::new(U16Point::new(0, 0), U16Point::new(5, 2)), 31);
+
+        let text = gfx.capture().to_text();
+        assert_eq!(
+            text.lines().collect::<Vec<_>>(),
+            vec!["+----+", "|    |", "+----+"]
+        );
+    }
+}
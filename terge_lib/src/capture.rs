@@ -0,0 +1,644 @@
+use std::fmt::Write as _;
+use std::io;
+use std::path::Path;
+
+pub const CAPTURE_DEFAULT_FOREGROUND: (u8, u8, u8) = (0xD0, 0xD0, 0xD0);
+pub const CAPTURE_DEFAULT_BACKGROUND: (u8, u8, u8) = (0x00, 0x00, 0x00);
+/// Cell size of SVG exports, in pixels.
+pub const CAPTURE_SVG_CELL_WIDTH: f32 = 8.4;
+pub const CAPTURE_SVG_CELL_HEIGHT: f32 = 17.0;
+pub const CAPTURE_SVG_FONT_SIZE: f32 = 14.0;
+
+/// The 16 basic terminal colors, xterm defaults.
+const CAPTURE_BASIC_COLORS: [(u8, u8, u8); 16] = [
+    (0x00, 0x00, 0x00),
+    (0xCD, 0x00, 0x00),
+    (0x00, 0xCD, 0x00),
+    (0xCD, 0xCD, 0x00),
+    (0x00, 0x00, 0xEE),
+    (0xCD, 0x00, 0xCD),
+    (0x00, 0xCD, 0xCD),
+    (0xE5, 0xE5, 0xE5),
+    (0x7F, 0x7F, 0x7F),
+    (0xFF, 0x00, 0x00),
+    (0x00, 0xFF, 0x00),
+    (0xFF, 0xFF, 0x00),
+    (0x5C, 0x5C, 0xFF),
+    (0xFF, 0x00, 0xFF),
+    (0x00, 0xFF, 0xFF),
+    (0xFF, 0xFF, 0xFF),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CellColor {
+    /// 256 color palette index, 0..16 are the basic colors.
+    Indexed(u8),
+    Rgb(u8, u8, u8),
+}
+
+impl CellColor {
+    pub fn rgb(self) -> (u8, u8, u8) {
+        match self {
+            CellColor::Indexed(i @ 0..16) => CAPTURE_BASIC_COLORS[i as usize],
+            CellColor::Indexed(i @ 16..232) => {
+                let level = |v: u8| if v == 0 { 0 } else { 55 + v * 40 };
+                let i = i - 16;
+                (level(i / 36), level(i / 6 % 6), level(i % 6))
+            }
+            CellColor::Indexed(i) => {
+                let gray = 8 + (i - 232) * 10;
+                (gray, gray, gray)
+            }
+            CellColor::Rgb(r, g, b) => (r, g, b),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CellStyle {
+    pub foreground: Option<CellColor>,
+    pub background: Option<CellColor>,
+    pub bold: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub reverse: bool,
+}
+
+impl CellStyle {
+    /// Applies the parameters of an SGR (`ESC [ ... m`) sequence.
+    fn apply_sgr(&mut self, params: &[u16]) {
+        if params.is_empty() {
+            *self = Self::default();
+            return;
+        }
+
+        let mut params = params.iter().copied();
+        while let Some(param) = params.next() {
+            match param {
+                0 => *self = Self::default(),
+                1 => self.bold = true,
+                3 => self.italic = true,
+                4 => self.underline = true,
+                7 => self.reverse = true,
+                22 => self.bold = false,
+                23 => self.italic = false,
+                24 => self.underline = false,
+                27 => self.reverse = false,
+                30..=37 => self.foreground = Some(CellColor::Indexed((param - 30) as u8)),
+                38 => self.foreground = extended_color(&mut params),
+                39 => self.foreground = None,
+                40..=47 => self.background = Some(CellColor::Indexed((param - 40) as u8)),
+                48 => self.background = extended_color(&mut params),
+                49 => self.background = None,
+                90..=97 => self.foreground = Some(CellColor::Indexed((param - 90 + 8) as u8)),
+                100..=107 => self.background = Some(CellColor::Indexed((param - 100 + 8) as u8)),
+                _ => {}
+            }
+        }
+    }
+
+    /// SGR parameters setting this style from a reset.
+    fn sgr(&self) -> String {
+        let mut codes = vec!["0".to_string()];
+        if self.bold {
+            codes.push("1".to_string());
+        }
+        if self.italic {
+            codes.push("3".to_string());
+        }
+        if self.underline {
+            codes.push("4".to_string());
+        }
+        if self.reverse {
+            codes.push("7".to_string());
+        }
+        for (color, base) in [(self.foreground, 30), (self.background, 40)] {
+            match color {
+                Some(CellColor::Indexed(i @ 0..8)) => codes.push((base + i as u16).to_string()),
+                Some(CellColor::Indexed(i @ 8..16)) => {
+                    codes.push((base + 60 + i as u16 - 8).to_string())
+                }
+                Some(CellColor::Indexed(i)) => codes.push(format!("{};5;{}", base + 8, i)),
+                Some(CellColor::Rgb(r, g, b)) => {
+                    codes.push(format!("{};2;{};{};{}", base + 8, r, g, b))
+                }
+                None => {}
+            }
+        }
+        codes.join(";")
+    }
+
+    /// Foreground and background as drawn, reverse applied.
+    fn colors(&self) -> ((u8, u8, u8), (u8, u8, u8)) {
+        let foreground = self
+            .foreground
+            .map_or(CAPTURE_DEFAULT_FOREGROUND, CellColor::rgb);
+        let background = self
+            .background
+            .map_or(CAPTURE_DEFAULT_BACKGROUND, CellColor::rgb);
+        if self.reverse {
+            (background, foreground)
+        } else {
+            (foreground, background)
+        }
+    }
+
+    fn has_background(&self) -> bool {
+        self.background.is_some() || self.reverse
+    }
+}
+
+/// `38;5;n` or `38;2;r;g;b`, after the 38 (or 48).
+fn extended_color(params: &mut impl Iterator<Item = u16>) -> Option<CellColor> {
+    match params.next()? {
+        5 => Some(CellColor::Indexed(params.next()? as u8)),
+        2 => Some(CellColor::Rgb(
+            params.next()? as u8,
+            params.next()? as u8,
+            params.next()? as u8,
+        )),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cell {
+    pub ch: char,
+    pub style: CellStyle,
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Self {
+            ch: ' ',
+            style: CellStyle::default(),
+        }
+    }
+}
+
+/// Export format, see `Capture::save`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaptureFormat {
+    Text,
+    Ansi,
+    Html,
+    Svg,
+}
+
+impl CaptureFormat {
+    /// By extension: `.txt`, `.ans`, `.html` (or `.htm`) and `.svg`.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_lowercase().as_str() {
+            "txt" => Some(CaptureFormat::Text),
+            "ans" => Some(CaptureFormat::Ansi),
+            "html" | "htm" => Some(CaptureFormat::Html),
+            "svg" => Some(CaptureFormat::Svg),
+            _ => None,
+        }
+    }
+}
+
+/// Snapshot of the screen, see `Gfx::capture`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Capture {
+    pub width: u16,
+    pub height: u16,
+    /// Row by row.
+    pub cells: Vec<Cell>,
+}
+
+impl Capture {
+    pub fn new(width: u16, height: u16) -> Self {
+        Self {
+            width,
+            height,
+            cells: vec![Cell::default(); width as usize * height as usize],
+        }
+    }
+
+    pub fn get(&self, x: u16, y: u16) -> Option<&Cell> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        self.cells
+            .get(y as usize * self.width as usize + x as usize)
+    }
+
+    fn rows(&self) -> impl Iterator<Item = &[Cell]> {
+        self.cells.chunks(self.width.max(1) as usize)
+    }
+
+    /// Rows without the blank cells at their end.
+    fn trimmed_rows(&self) -> impl Iterator<Item = &[Cell]> {
+        self.rows().map(|row| {
+            let len = row
+                .iter()
+                .rposition(|cell| cell.ch != ' ' || cell.style.has_background())
+                .map_or(0, |i| i + 1);
+            &row[..len]
+        })
+    }
+
+    pub fn to_text(&self) -> String {
+        self.trimmed_rows()
+            .map(|row| row.iter().map(|cell| cell.ch).collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Text with SGR escape sequences, eg. for `cat` in a terminal.
+    pub fn to_ansi(&self) -> String {
+        let mut out = String::new();
+
+        for row in self.trimmed_rows() {
+            let mut style = CellStyle::default();
+            for cell in row {
+                if cell.style != style {
+                    style = cell.style;
+                    let _ = write!(out, "\x1B[{}m", style.sgr());
+                }
+                out.push(cell.ch);
+            }
+            if style != CellStyle::default() {
+                out.push_str("\x1B[0m");
+            }
+            out.push('\n');
+        }
+
+        out
+    }
+
+    /// A standalone page with a `<pre>` and inline styles.
+    pub fn to_html(&self) -> String {
+        let mut out = String::new();
+        let _ = write!(
+            out,
+            "<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"></head>\n<body>\n\
+             <pre style=\"font-family: monospace; line-height: 1.2; padding: 8px; \
+             color: {}; background-color: {};\">",
+            hex(CAPTURE_DEFAULT_FOREGROUND),
+            hex(CAPTURE_DEFAULT_BACKGROUND)
+        );
+
+        for (y, row) in self.trimmed_rows().enumerate() {
+            if y > 0 {
+                out.push('\n');
+            }
+            for run in style_runs(row) {
+                let text = escape_xml(&run.iter().map(|cell| cell.ch).collect::<String>());
+                let style = run[0].style;
+                if style == CellStyle::default() {
+                    out.push_str(&text);
+                    continue;
+                }
+
+                let (foreground, background) = style.colors();
+                let mut css = format!("color: {};", hex(foreground));
+                if style.has_background() {
+                    let _ = write!(css, " background-color: {};", hex(background));
+                }
+                if style.bold {
+                    css.push_str(" font-weight: bold;");
+                }
+                if style.italic {
+                    css.push_str(" font-style: italic;");
+                }
+                if style.underline {
+                    css.push_str(" text-decoration: underline;");
+                }
+                let _ = write!(out, "<span style=\"{}\">{}</span>", css, text);
+            }
+        }
+
+        out.push_str("</pre>\n</body>\n</html>\n");
+        out
+    }
+
+    /// Monospace text over colored cell backgrounds. Every run of text is stretched to its cells,
+    /// so the grid holds whatever font the viewer picks.
+    pub fn to_svg(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" \
+             font-family=\"monospace\" font-size=\"{}\">",
+            self.width as f32 * CAPTURE_SVG_CELL_WIDTH,
+            self.height as f32 * CAPTURE_SVG_CELL_HEIGHT,
+            CAPTURE_SVG_FONT_SIZE
+        );
+        let _ = writeln!(
+            out,
+            "<rect width=\"100%\" height=\"100%\" fill=\"{}\"/>",
+            hex(CAPTURE_DEFAULT_BACKGROUND)
+        );
+
+        for (y, row) in self.trimmed_rows().enumerate() {
+            let top = y as f32 * CAPTURE_SVG_CELL_HEIGHT;
+            let mut x = 0;
+            for run in style_runs(row) {
+                let left = x as f32 * CAPTURE_SVG_CELL_WIDTH;
+                let width = run.len() as f32 * CAPTURE_SVG_CELL_WIDTH;
+                let style = run[0].style;
+                let (foreground, background) = style.colors();
+                x += run.len();
+
+                if style.has_background() {
+                    let _ = writeln!(
+                        out,
+                        "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>",
+                        left,
+                        top,
+                        width,
+                        CAPTURE_SVG_CELL_HEIGHT,
+                        hex(background)
+                    );
+                }
+
+                let text: String = run.iter().map(|cell| cell.ch).collect();
+                if text.trim().is_empty() {
+                    continue;
+                }
+                let mut attributes = String::new();
+                if style.bold {
+                    attributes.push_str(" font-weight=\"bold\"");
+                }
+                if style.italic {
+                    attributes.push_str(" font-style=\"italic\"");
+                }
+                if style.underline {
+                    attributes.push_str(" text-decoration=\"underline\"");
+                }
+                let _ = writeln!(
+                    out,
+                    "<text x=\"{}\" y=\"{}\" fill=\"{}\" textLength=\"{}\" \
+                     lengthAdjust=\"spacingAndGlyphs\" xml:space=\"preserve\"{}>{}</text>",
+                    left,
+                    top + CAPTURE_SVG_CELL_HEIGHT * 0.8,
+                    hex(foreground),
+                    width,
+                    attributes,
+                    escape_xml(&text)
+                );
+            }
+        }
+
+        out.push_str("</svg>\n");
+        out
+    }
+
+    pub fn export(&self, format: CaptureFormat) -> String {
+        match format {
+            CaptureFormat::Text => self.to_text(),
+            CaptureFormat::Ansi => self.to_ansi(),
+            CaptureFormat::Html => self.to_html(),
+            CaptureFormat::Svg => self.to_svg(),
+        }
+    }
+
+    /// Format by the extension of the file, see `CaptureFormat::from_path`.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        let format = CaptureFormat::from_path(path).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Unknown capture format: {:?}", path),
+            )
+        })?;
+        std::fs::write(path, self.export(format))
+    }
+}
+
+/// Neighbouring cells of the same style.
+fn style_runs(row: &[Cell]) -> impl Iterator<Item = &[Cell]> {
+    row.chunk_by(|lhs, rhs| lhs.style == rhs.style)
+}
+
+fn hex((r, g, b): (u8, u8, u8)) -> String {
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+fn escape_xml(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ParseState {
+    Text,
+    Escape,
+    Csi(String),
+    /// OSC, eg. the clipboard, skipped until BEL or ST.
+    Osc,
+    OscEscape,
+}
+
+/// Cell buffer kept by `Gfx`, built from what it writes to the terminal. Only the sequences
+/// `Gfx` itself uses are understood: cursor moves, SGR and clearing.
+#[derive(Debug, Clone)]
+pub(crate) struct ScreenBuffer {
+    screen: Capture,
+    cursor: (u16, u16),
+    style: CellStyle,
+    state: ParseState,
+}
+
+impl ScreenBuffer {
+    pub(crate) fn new() -> Self {
+        Self {
+            screen: Capture::new(0, 0),
+            cursor: (0, 0),
+            style: CellStyle::default(),
+            state: ParseState::Text,
+        }
+    }
+
+    pub(crate) fn capture(&self) -> Capture {
+        self.screen.clone()
+    }
+
+    /// Keeps what still fits.
+    pub(crate) fn resize(&mut self, width: u16, height: u16) {
+        if (width, height) == (self.screen.width, self.screen.height) {
+            return;
+        }
+
+        let mut screen = Capture::new(width, height);
+        for y in 0..height.min(self.screen.height) {
+            for x in 0..width.min(self.screen.width) {
+                screen.cells[y as usize * width as usize + x as usize] =
+                    *self.screen.get(x, y).unwrap();
+            }
+        }
+        self.screen = screen;
+    }
+
+    pub(crate) fn feed(&mut self, text: &str) {
+        for c in text.chars() {
+            self.state = match std::mem::replace(&mut self.state, ParseState::Text) {
+                ParseState::Text => match c {
+                    '\x1B' => ParseState::Escape,
+                    '\r' => {
+                        self.cursor.0 = 0;
+                        ParseState::Text
+                    }
+                    '\n' => {
+                        self.cursor.1 += 1;
+                        ParseState::Text
+                    }
+                    c if c.is_control() => ParseState::Text,
+                    c => {
+                        self.put(c);
+                        ParseState::Text
+                    }
+                },
+                ParseState::Escape => match c {
+                    '[' => ParseState::Csi(String::new()),
+                    ']' => ParseState::Osc,
+                    _ => ParseState::Text,
+                },
+                ParseState::Csi(mut params) => {
+                    if ('\x40'..='\x7E').contains(&c) {
+                        self.csi(&params, c);
+                        ParseState::Text
+                    } else {
+                        params.push(c);
+                        ParseState::Csi(params)
+                    }
+                }
+                ParseState::Osc => match c {
+                    '\x07' => ParseState::Text,
+                    '\x1B' => ParseState::OscEscape,
+                    _ => ParseState::Osc,
+                },
+                ParseState::OscEscape => ParseState::Text,
+            };
+        }
+    }
+
+    fn put(&mut self, ch: char) {
+        let (x, y) = self.cursor;
+        if x >= self.screen.width {
+            return;
+        }
+        if y < self.screen.height {
+            self.screen.cells[y as usize * self.screen.width as usize + x as usize] = Cell {
+                ch,
+                style: self.style,
+            };
+        }
+        self.cursor.0 += 1;
+    }
+
+    fn csi(&mut self, params: &str, command: char) {
+        // Private modes, eg. hiding the cursor.
+        if params.starts_with('?') {
+            return;
+        }
+
+        let numbers: Vec<u16> = params
+            .split(';')
+            .filter(|param| !param.is_empty())
+            .map(|param| param.parse().unwrap_or(0))
+            .collect();
+        let first = numbers.first().copied().unwrap_or(1).max(1);
+
+        match command {
+            'H' | 'f' => {
+                let row = numbers.first().copied().unwrap_or(1).max(1);
+                let column = numbers.get(1).copied().unwrap_or(1).max(1);
+                self.cursor = (column - 1, row - 1);
+            }
+            'A' => self.cursor.1 = self.cursor.1.saturating_sub(first),
+            'B' => self.cursor.1 = self.cursor.1.saturating_add(first),
+            'C' => self.cursor.0 = self.cursor.0.saturating_add(first),
+            'D' => self.cursor.0 = self.cursor.0.saturating_sub(first),
+            'G' => self.cursor.0 = first - 1,
+            'J' if matches!(numbers.first(), Some(2) | Some(3)) => {
+                self.screen.cells.fill(Cell::default());
+            }
+            'm' => self.style.apply_sgr(&numbers),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn screen(width: u16, height: u16, output: &str) -> Capture {
+        let mut buffer = ScreenBuffer::new();
+        buffer.resize(width, height);
+        buffer.feed(output);
+        buffer.capture()
+    }
+
+    #[test]
+    fn follows_cursor_moves_and_colors() {
+        let capture = screen(6, 2, "\x1B[2J\x1B[2;3H\x1B[91mhi\x1B[0m\x1B[1;1Hab");
+
+        assert_eq!(capture.to_text(), "ab\n  hi");
+        assert_eq!(
+            capture.get(2, 1).unwrap().style.foreground,
+            Some(CellColor::Indexed(9))
+        );
+        assert_eq!(capture.get(0, 0).unwrap().style, CellStyle::default());
+    }
+
+    #[test]
+    fn skips_private_modes_and_osc() {
+        let capture = screen(
+            4,
+            1,
+            "\x1B[?25l\x1B]52;c;aGk=\x07x\x1B[38;2;1;2;3;48;5;196my",
+        );
+
+        assert_eq!(capture.to_text(), "xy");
+        let style = capture.get(1, 0).unwrap().style;
+        assert_eq!(style.foreground, Some(CellColor::Rgb(1, 2, 3)));
+        assert_eq!(style.background.map(CellColor::rgb), Some((255, 0, 0)));
+    }
+
+    #[test]
+    fn ansi_export_restyles_runs() {
+        let capture = screen(4, 1, "a\x1B[1;32mbc");
+
+        assert_eq!(capture.to_ansi(), "a\x1B[0;1;32mbc\x1B[0m\n");
+    }
+
+    #[test]
+    fn html_and_svg_exports() {
+        let capture = screen(5, 1, "<\x1B[7m&\x1B[0m");
+
+        let html = capture.to_html();
+        assert!(html.contains(
+            "&lt;<span style=\"color: #000000; background-color: #d0d0d0;\">&amp;</span></pre>"
+        ));
+
+        let svg = capture.to_svg();
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"42\""));
+        assert!(
+            svg.contains("<rect x=\"8.4\" y=\"0\" width=\"8.4\" height=\"17\" fill=\"#d0d0d0\"/>")
+        );
+        assert!(svg.contains(">&lt;</text>"));
+    }
+
+    #[test]
+    fn format_from_extension() {
+        assert_eq!(
+            CaptureFormat::from_path(Path::new("shot.SVG")),
+            Some(CaptureFormat::Svg)
+        );
+        assert_eq!(CaptureFormat::from_path(Path::new("shot.png")), None);
+    }
+}
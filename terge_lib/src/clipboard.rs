@@ -0,0 +1,114 @@
+const CLIPBOARD_BASE64_CHARS: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Clipboard of `Gfx`. Copying sets the system clipboard with an OSC 52 escape sequence sent
+/// with the next frame, which works over ssh too when the terminal supports it. Terminals rarely
+/// allow reading it back, so pasting from outside arrives as `Event::Paste` instead (see
+/// `EventGroup::pasted_text`), and `text` returns what the app copied itself.
+#[derive(Debug, Clone, Default)]
+pub struct Clipboard {
+    text: Option<String>,
+    /// Only kept in memory, nothing is written to the terminal.
+    in_memory: bool,
+    /// Escape sequence waiting for the next frame.
+    pending: Option<String>,
+}
+
+impl Clipboard {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A clipboard that never touches the terminal, eg. when there is none.
+    pub fn in_memory() -> Self {
+        Self {
+            text: None,
+            in_memory: true,
+            pending: None,
+        }
+    }
+
+    pub fn is_in_memory(&self) -> bool {
+        self.in_memory
+    }
+
+    pub fn copy(&mut self, text: &str) {
+        if !self.in_memory {
+            self.pending = Some(osc52_sequence(text));
+        }
+        self.text = Some(text.to_string());
+    }
+
+    /// Last text copied by the app.
+    pub fn text(&self) -> Option<&str> {
+        self.text.as_deref()
+    }
+
+    pub fn clear(&mut self) {
+        self.text = None;
+    }
+
+    pub(crate) fn take_pending(&mut self) -> Option<String> {
+        self.pending.take()
+    }
+}
+
+/// Escape sequence setting the system clipboard to `text`.
+pub fn osc52_sequence(text: &str) -> String {
+    format!("\x1B]52;c;{}\x07", base64_encode(text.as_bytes()))
+}
+
+fn base64_encode(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+
+    for chunk in bytes.chunks(3) {
+        let b = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(CLIPBOARD_BASE64_CHARS[(n >> (18 - 6 * i) & 0x3F) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base64_padding() {
+        assert_eq!(base64_encode(b""), "");
+        assert_eq!(base64_encode(b"f"), "Zg==");
+        assert_eq!(base64_encode(b"fo"), "Zm8=");
+        assert_eq!(base64_encode(b"foo"), "Zm9v");
+        assert_eq!(base64_encode(b"foobar"), "Zm9vYmFy");
+        assert_eq!(base64_encode("ü".as_bytes()), "w7w=");
+    }
+
+    #[test]
+    fn osc52() {
+        assert_eq!(osc52_sequence("hi"), "\x1B]52;c;aGk=\x07");
+    }
+
+    #[test]
+    fn in_memory_copy() {
+        let mut clipboard = Clipboard::in_memory();
+        assert_eq!(clipboard.text(), None);
+
+        clipboard.copy("label");
+        assert_eq!(clipboard.text(), Some("label"));
+
+        clipboard.clear();
+        assert_eq!(clipboard.text(), None);
+    }
+}
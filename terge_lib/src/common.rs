@@ -1,4 +1,6 @@
-use crate::{Rect, line::Line};
+use crate::{Rect, geometry::segment_rect_intersections, line::Line};
+
+pub use crate::point::{Point, Vec2};
 
 pub const BLOCK_CHAR: &'static str = "░";
 pub const BOX_TOP_LEFT_CORNER_CHAR: &'static str = "╔";
@@ -10,20 +12,33 @@ pub const BOX_HORIZONTAL_CHAR: &'static str = "═";
 pub const LINE_CONNECTION_CHAR: &'static str = "X";
 pub const DEFAULT_COLOR_CODE: u8 = 0;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum TextHorizontalAlign {
+    #[default]
     Left,
     Center,
+    Right,
+    /// Spreads the words to fill the line, except on the last line of a paragraph.
+    Justify,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum TextVercticalAlign {
+    #[default]
     Top,
     Center,
+    Bottom,
 }
 
-pub type I32Point = (i32, i32);
-pub type U16Point = (u16, u16);
-pub type UsizePoint = (usize, usize);
-pub type F32Point = (f32, f32);
+/// 24-bit color, for terminals supporting true color.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RgbColor(pub u8, pub u8, pub u8);
+
+pub type I32Point = Point<i32>;
+pub type U16Point = Point<u16>;
+pub type UsizePoint = Point<usize>;
+pub type F32Point = Point<f32>;
 
 pub fn between_u16_inclusive(lhs: u16, rhs: u16, v: u16) -> bool {
     if lhs < rhs {
@@ -33,127 +48,27 @@ pub fn between_u16_inclusive(lhs: u16, rhs: u16, v: u16) -> bool {
     }
 }
 
-pub fn i32point_to_u16point(p: I32Point) -> U16Point {
-    (p.0 as u16, p.1 as u16)
-}
-
-pub fn u16point_to_i32point(p: U16Point) -> I32Point {
-    (p.0 as i32, p.1 as i32)
-}
-
-pub fn f32point_to_u16point(p: F32Point) -> U16Point {
-    (p.0 as u16, p.1 as u16)
-}
-
 /// range must be ordered.
-pub fn u16_value_included_in_range(v: u16, range: U16Point) -> bool {
+pub fn u16_value_included_in_range(v: u16, range: (u16, u16)) -> bool {
     range.0 <= v && range.1 >= v
 }
 
 /// ranges must be ordered
-pub fn u16_range_overlap(lhs: U16Point, rhs: U16Point) -> bool {
+pub fn u16_range_overlap(lhs: (u16, u16), rhs: (u16, u16)) -> bool {
     !(rhs.1 < lhs.0 || rhs.0 > lhs.1)
 }
 
-pub trait Arithmetics<TPair, T> {
-    fn add(&self, other: TPair) -> TPair;
-    fn sub(&self, other: TPair) -> TPair;
-    fn div(&self, divisor: T) -> TPair;
-}
-
-impl Arithmetics<I32Point, i32> for I32Point {
-    fn add(&self, other: I32Point) -> I32Point {
-        (self.0 + other.0, self.1 + other.1)
-    }
-
-    fn sub(&self, other: I32Point) -> I32Point {
-        (self.0 - other.0, self.1 - other.1)
-    }
-
-    fn div(&self, divisor: i32) -> I32Point {
-        (self.0 / divisor, self.1 / divisor)
-    }
-}
-
-impl Arithmetics<U16Point, u16> for U16Point {
-    fn add(&self, other: U16Point) -> U16Point {
-        (self.0.wrapping_add(other.0), self.1.wrapping_add(other.1))
-    }
-
-    fn sub(&self, other: U16Point) -> U16Point {
-        (self.0.wrapping_sub(other.0), self.1.wrapping_sub(other.1))
-    }
-
-    fn div(&self, divisor: u16) -> U16Point {
-        (self.0 / divisor, self.1 / divisor)
-    }
-}
-
-impl Arithmetics<F32Point, f32> for F32Point {
-    fn add(&self, other: F32Point) -> F32Point {
-        (self.0 + other.0, self.1 + other.1)
-    }
-
-    fn sub(&self, other: F32Point) -> F32Point {
-        (self.0 - other.0, self.1 - other.1)
-    }
-
-    fn div(&self, divisor: f32) -> F32Point {
-        (self.0 / divisor, self.1 / divisor)
-    }
-}
-
-pub fn point_pair_minmax(lhs: U16Point, rhs: U16Point) -> (u16, u16, u16, u16) {
-    (
-        lhs.0.min(rhs.0),
-        lhs.1.min(rhs.1),
-        lhs.0.max(rhs.0),
-        lhs.1.max(rhs.1),
-    )
+/// Upper-left and lower-right corner of the area spanned by the two points.
+pub fn point_pair_minmax(lhs: U16Point, rhs: U16Point) -> (U16Point, U16Point) {
+    (lhs.min(rhs), lhs.max(rhs))
 }
 
+/// Points where the line crosses the border of the rect, ordered from the line start.
 pub fn intersection_of_rect_and_line(rect: &Rect, line: &Line) -> Vec<U16Point> {
-    let slope = line.slope();
-    let mut out = vec![];
-
-    if line.y_range().contains(&rect.start.1) {
-        let intersect_top_x =
-            ((rect.start.1 as f32 - line.start.1 as f32) / slope) + line.start.0 as f32;
-        if intersect_top_x >= rect.start.0 as f32 && intersect_top_x <= rect.end().0 as f32 {
-            out.push((intersect_top_x.round() as u16, rect.start.1));
-        }
-    }
-
-    if line.y_range().contains(&(rect.start.1 + rect.size.1)) {
-        let intersect_bottom_x = ((rect.start.1 as f32 + rect.size.1 as f32 - line.start.1 as f32)
-            / slope)
-            + line.start.0 as f32;
-        if intersect_bottom_x >= rect.start.0 as f32 && intersect_bottom_x <= rect.end().0 as f32 {
-            out.push((
-                intersect_bottom_x.round() as u16,
-                rect.start.1 + rect.size.1,
-            ));
-        }
-    }
-
-    if line.x_range().contains(&rect.start.0) {
-        let intersect_left_y =
-            (slope * (rect.start.0 as f32 - line.start.0 as f32)) + line.start.1 as f32;
-        if intersect_left_y >= rect.start.1 as f32 && intersect_left_y <= rect.end().1 as f32 {
-            out.push((rect.start.0, intersect_left_y.round() as u16));
-        }
-    }
-
-    if line.x_range().contains(&(rect.start.0 + rect.size.0)) {
-        let intersect_right_y = (slope
-            * (rect.start.0 as f32 + rect.size.0 as f32 - line.start.0 as f32))
-            + line.start.1 as f32;
-        if intersect_right_y >= rect.start.1 as f32 && intersect_right_y <= rect.end().1 as f32 {
-            out.push((rect.start.0 + rect.size.0, intersect_right_y.round() as u16));
-        }
-    }
-
-    out
+    segment_rect_intersections(line.start.as_f32(), line.end.as_f32(), rect)
+        .into_iter()
+        .map(|p| p.round().saturating_u16())
+        .collect()
 }
 
 pub fn multiline_text_line_start(
@@ -165,56 +80,28 @@ pub fn multiline_text_line_start(
     vertical_align: TextVercticalAlign,
 ) -> U16Point {
     let x = match horizontal_align {
-        TextHorizontalAlign::Left => start.0,
+        // A single line has nothing to spread out.
+        TextHorizontalAlign::Left | TextHorizontalAlign::Justify => start.x,
+        TextHorizontalAlign::Right => start.x.saturating_sub(line_length),
         TextHorizontalAlign::Center => {
-            if start.0 < (line_length / 2) {
+            if start.x < (line_length / 2) {
                 0
             } else {
-                start.0 - (line_length / 2)
+                start.x - (line_length / 2)
             }
         }
     };
     let y = match vertical_align {
         TextVercticalAlign::Center => {
-            if (start.1 + line_index) < (line_count / 2) {
+            if (start.y + line_index) < (line_count / 2) {
                 0
             } else {
-                start.1 + line_index - (line_count / 2)
+                start.y + line_index - (line_count / 2)
             }
         }
-        TextVercticalAlign::Top => start.1 + line_index,
+        TextVercticalAlign::Top => start.y + line_index,
+        TextVercticalAlign::Bottom => (start.y + line_index + 1).saturating_sub(line_count),
     };
 
-    (x, y)
-}
-
-#[derive(Debug)]
-pub struct Gravity {
-    g: f32,
-    max_vy: f32,
-}
-
-impl Gravity {
-    pub fn new(g: f32, max_vy: f32) -> Self {
-        Self { g, max_vy }
-    }
-
-    pub fn apply(&self, p: &mut F32Point, v: &mut F32Point) {
-        static FLOAT_ZERO_THRESHOLD: f32 = 0.2;
-
-        if v.1 < 0.0 {
-            // Raising up.
-            v.1 *= 1.0 / self.g;
-
-            // Falling back.
-            if v.1.abs() <= FLOAT_ZERO_THRESHOLD {
-                v.1 = FLOAT_ZERO_THRESHOLD;
-            }
-        } else {
-            // Falling down.
-            v.1 = self.max_vy.min(v.1 * self.g);
-        }
-
-        p.1 += v.1;
-    }
+    U16Point::new(x, y)
 }
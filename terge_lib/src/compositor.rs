@@ -0,0 +1,535 @@
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers, MouseEvent, MouseEventKind};
+
+use crate::App;
+use crate::common::*;
+use crate::event_group::EventGroup;
+use crate::gfx::Gfx;
+use crate::layout::{Constraint, Direction, Layout};
+use crate::rect::Rect;
+
+/// Alt + arrow moves the focus to the pane in that direction.
+pub const COMPOSITOR_FOCUS_MODIFIERS: KeyModifiers = KeyModifiers::ALT;
+/// Alt + Shift + arrow resizes the focused pane: right and down grow, left and up shrink.
+pub const COMPOSITOR_RESIZE_MODIFIERS: KeyModifiers = KeyModifiers::ALT.union(KeyModifiers::SHIFT);
+pub const COMPOSITOR_DEFAULT_WEIGHT: u16 = 10;
+pub const COMPOSITOR_BORDER_COLOR: u8 = 90;
+pub const COMPOSITOR_FOCUSED_BORDER_COLOR: u8 = 96;
+
+/// An app of a `Compositor` inside a border.
+pub struct Pane {
+    pub title: String,
+    app: Box<dyn App>,
+    /// Share of the tiled area, like `Constraint::Fill`.
+    pub weight: u16,
+    /// Floating panes sit above the tiled ones at this rect of the screen, `None` when tiled.
+    pub floating: Option<Rect>,
+    /// Where the pane is on the screen, border included. `None` when it doesn't fit.
+    rect: Option<Rect>,
+    /// Events of the pane app, mouse state included.
+    events: EventGroup,
+    /// Size the app was last told about.
+    size: Option<(u16, u16)>,
+}
+
+impl Pane {
+    pub fn tiled(title: &str, app: Box<dyn App>) -> Self {
+        Self {
+            title: title.to_string(),
+            app,
+            weight: COMPOSITOR_DEFAULT_WEIGHT,
+            floating: None,
+            rect: None,
+            events: EventGroup::new(),
+            size: None,
+        }
+    }
+
+    pub fn floating(title: &str, app: Box<dyn App>, rect: Rect) -> Self {
+        Self {
+            floating: Some(rect),
+            ..Self::tiled(title, app)
+        }
+    }
+
+    pub fn with_weight(mut self, weight: u16) -> Self {
+        self.weight = weight.max(1);
+        self
+    }
+
+    /// Where the app draws, inside the border.
+    fn inner_rect(&self) -> Option<Rect> {
+        self.rect.and_then(|rect| rect.inset(1))
+    }
+}
+
+/// An app hosting several apps in panes. Tiled panes share the screen along `direction`,
+/// floating ones are drawn above them. Every app draws on its own surface (see
+/// `Gfx::with_surface`) and gets its own events: mouse events over its pane in local
+/// coordinates, keys and pastes only when focused. Clicking a pane focuses it, see
+/// `COMPOSITOR_FOCUS_MODIFIERS` and `COMPOSITOR_RESIZE_MODIFIERS` for the keyboard.
+///
+/// An app returning false from `update` closes its pane, the compositor stops with the last one.
+pub struct Compositor {
+    pub direction: Direction,
+    panes: Vec<Pane>,
+    focused: usize,
+    /// Pane getting the mouse between a press and the release, even when leaving the pane.
+    mouse_capture: Option<usize>,
+    pub border_color: u8,
+    pub focused_border_color: u8,
+}
+
+impl Compositor {
+    pub fn new(direction: Direction) -> Self {
+        Self {
+            direction,
+            panes: vec![],
+            focused: 0,
+            mouse_capture: None,
+            border_color: COMPOSITOR_BORDER_COLOR,
+            focused_border_color: COMPOSITOR_FOCUSED_BORDER_COLOR,
+        }
+    }
+
+    pub fn with_pane(mut self, pane: Pane) -> Self {
+        self.panes.push(pane);
+        self
+    }
+
+    pub fn panes(&self) -> &[Pane] {
+        &self.panes
+    }
+
+    pub fn focused(&self) -> usize {
+        self.focused
+    }
+
+    pub fn focus(&mut self, index: usize) {
+        if index < self.panes.len() {
+            self.focused = index;
+        }
+    }
+
+    /// Where the pane was laid out last, border included.
+    pub fn pane_rect(&self, index: usize) -> Option<Rect> {
+        self.panes.get(index).and_then(|pane| pane.rect)
+    }
+
+    fn layout(&mut self, screen: &Rect) {
+        let tiled: Vec<usize> = (0..self.panes.len())
+            .filter(|i| self.panes[*i].floating.is_none())
+            .collect();
+        let constraints: Vec<Constraint> = tiled
+            .iter()
+            .map(|i| Constraint::Fill(self.panes[*i].weight))
+            .collect();
+        let rects = Layout::new(self.direction, constraints).split(screen);
+
+        for (i, rect) in tiled.into_iter().zip(rects) {
+            self.panes[i].rect = rect;
+        }
+        for pane in &mut self.panes {
+            if let Some(rect) = pane.floating {
+                pane.rect = rect.intersection(screen);
+            }
+        }
+    }
+
+    /// Topmost pane under `pos`, floating panes first.
+    fn pane_at(&self, pos: U16Point) -> Option<usize> {
+        let floating = (0..self.panes.len())
+            .rev()
+            .filter(|i| self.panes[*i].floating.is_some());
+        let tiled = (0..self.panes.len()).filter(|i| self.panes[*i].floating.is_none());
+
+        floating.chain(tiled).find(|i| {
+            self.panes[*i]
+                .rect
+                .is_some_and(|rect| rect.is_point_on(pos))
+        })
+    }
+
+    /// Nearest pane from the focused one towards `key`, judged by the middle of the panes.
+    fn neighbour(&self, key: KeyCode) -> Option<usize> {
+        let from = self.pane_rect(self.focused)?.midpoint();
+        let from = (from.x as i32, from.y as i32);
+
+        (0..self.panes.len())
+            .filter(|i| *i != self.focused)
+            .filter_map(|i| {
+                let to = self.panes[i].rect?.midpoint();
+                let (dx, dy) = (to.x as i32 - from.0, to.y as i32 - from.1);
+                let (along, across) = match key {
+                    KeyCode::Left => (-dx, dy),
+                    KeyCode::Right => (dx, dy),
+                    KeyCode::Up => (-dy, dx),
+                    KeyCode::Down => (dy, dx),
+                    _ => return None,
+                };
+                (along > 0).then_some((i, along + across.abs() * 2))
+            })
+            .min_by_key(|(_, distance)| *distance)
+            .map(|(i, _)| i)
+    }
+
+    fn resize_focused(&mut self, key: KeyCode, screen: &Rect) {
+        let Some(pane) = self.panes.get_mut(self.focused) else {
+            return;
+        };
+
+        match pane.floating.as_mut() {
+            Some(rect) => {
+                let mut size = rect.size;
+                match key {
+                    KeyCode::Left => size.x = size.x.saturating_sub(1),
+                    KeyCode::Right => size.x = size.x.saturating_add(1),
+                    KeyCode::Up => size.y = size.y.saturating_sub(1),
+                    KeyCode::Down => size.y = size.y.saturating_add(1),
+                    _ => {}
+                }
+                // Room for the border and a cell inside, no bigger than the screen.
+                let max = screen.end().saturating_sub(rect.start);
+                rect.size =
+                    U16Point::new(size.x.clamp(2, max.x.max(2)), size.y.clamp(2, max.y.max(2)));
+            }
+            None => match key {
+                KeyCode::Right | KeyCode::Down => pane.weight = pane.weight.saturating_add(1),
+                KeyCode::Left | KeyCode::Up => pane.weight = pane.weight.saturating_sub(1).max(1),
+                _ => {}
+            },
+        }
+    }
+
+    /// Handles compositor shortcuts, false when the key is for the focused app.
+    fn on_key(&mut self, key_event: &KeyEvent, screen: &Rect) -> bool {
+        if !matches!(
+            key_event.code,
+            KeyCode::Left | KeyCode::Right | KeyCode::Up | KeyCode::Down
+        ) {
+            return false;
+        }
+
+        if key_event.modifiers == COMPOSITOR_RESIZE_MODIFIERS {
+            self.resize_focused(key_event.code, screen);
+            self.layout(screen);
+            true
+        } else if key_event.modifiers == COMPOSITOR_FOCUS_MODIFIERS {
+            if let Some(i) = self.neighbour(key_event.code) {
+                self.focused = i;
+            }
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Sorts this frame's events out into one list per pane.
+    fn route(&mut self, events: &EventGroup, screen: &Rect) -> Vec<Vec<Event>> {
+        let mut routed = vec![vec![]; self.panes.len()];
+
+        for event in &events.events {
+            match event {
+                Event::Key(key_event) => {
+                    if !self.on_key(key_event, screen) && self.focused < routed.len() {
+                        routed[self.focused].push(event.clone());
+                    }
+                }
+                Event::Paste(_) => {
+                    if self.focused < routed.len() {
+                        routed[self.focused].push(event.clone());
+                    }
+                }
+                Event::Mouse(mouse_event) => {
+                    let pos = U16Point::new(mouse_event.column, mouse_event.row);
+                    let captured = self.mouse_capture;
+                    let Some(i) = captured.or_else(|| self.pane_at(pos)) else {
+                        continue;
+                    };
+                    let Some(inner) = self.panes[i].inner_rect() else {
+                        continue;
+                    };
+                    let over_app = inner.is_point_on(pos);
+
+                    match mouse_event.kind {
+                        MouseEventKind::Down(_) => {
+                            self.focused = i;
+                            if over_app {
+                                self.mouse_capture = Some(i);
+                            }
+                        }
+                        MouseEventKind::Up(_) => self.mouse_capture = None,
+                        _ => {}
+                    }
+
+                    // Borders belong to the compositor.
+                    if captured.is_none() && !over_app {
+                        continue;
+                    }
+
+                    let local = pos.saturating_sub(inner.start).min(inner.size);
+                    routed[i].push(Event::Mouse(MouseEvent {
+                        column: local.x,
+                        row: local.y,
+                        ..*mouse_event
+                    }));
+                }
+                // Resizes are sent per pane once the layout is known.
+                Event::Resize(_, _) => {}
+                Event::FocusGained | Event::FocusLost => {
+                    for pane_events in &mut routed {
+                        pane_events.push(event.clone());
+                    }
+                }
+            }
+        }
+
+        routed
+    }
+
+    /// Removes the pane, keeping the focus on the same app where possible.
+    fn close(&mut self, index: usize) {
+        self.panes.remove(index);
+        if self.focused > index || self.focused >= self.panes.len() {
+            self.focused = self.focused.saturating_sub(1);
+        }
+        self.mouse_capture = None;
+    }
+}
+
+impl App for Compositor {
+    fn reset(&mut self, gfx: &mut Gfx) {
+        let screen = gfx.screen_rect();
+        self.layout(&screen);
+
+        for pane in &mut self.panes {
+            pane.size = None;
+            if let Some(inner) = pane.inner_rect() {
+                pane.size = Some((inner.width() as u16, inner.height() as u16));
+                gfx.with_surface(&inner, |gfx| pane.app.reset(gfx));
+            }
+        }
+    }
+
+    fn update(&mut self, events: &EventGroup, gfx: &mut Gfx) -> bool {
+        let screen = gfx.screen_rect();
+        self.layout(&screen);
+
+        let routed = self.route(events, &screen);
+        let mut closed = vec![];
+
+        for (i, (pane, mut pane_events)) in self.panes.iter_mut().zip(routed).enumerate() {
+            let Some(inner) = pane.inner_rect() else {
+                continue;
+            };
+
+            let size = (inner.width() as u16, inner.height() as u16);
+            if pane.size != Some(size) {
+                pane.size = Some(size);
+                pane_events.insert(0, Event::Resize(size.0, size.1));
+            }
+
+            pane.events.events = pane_events;
+            pane.events.current_ms = events.current_ms;
+            pane.events.delta_ms = events.delta_ms;
+            pane.events
+                .mouse
+                .update(&pane.events.events, events.current_ms);
+
+            if !gfx.with_surface(&inner, |gfx| pane.app.update(&pane.events, gfx)) {
+                closed.push(i);
+            }
+        }
+
+        for i in closed.into_iter().rev() {
+            self.close(i);
+        }
+        self.layout(&screen);
+
+        !self.panes.is_empty()
+    }
+
+    fn draw(&self, gfx: &mut Gfx) {
+        gfx.clear_screen();
+
+        let tiled = self
+            .panes
+            .iter()
+            .enumerate()
+            .filter(|(_, pane)| pane.floating.is_none());
+        let floating = self
+            .panes
+            .iter()
+            .enumerate()
+            .filter(|(_, pane)| pane.floating.is_some());
+
+        for (i, pane) in tiled.chain(floating) {
+            let Some(rect) = pane.rect else {
+                continue;
+            };
+            let focused = i == self.focused;
+            let color = if focused {
+                self.focused_border_color
+            } else {
+                self.border_color
+            };
+
+            gfx.draw_rect(&rect, color);
+            if !pane.title.is_empty() {
+                gfx.with_surface(&rect, |gfx| {
+                    gfx.draw_text(&format!(" {} ", pane.title), 1, 0, color);
+                });
+            }
+
+            let Some(inner) = pane.inner_rect() else {
+                continue;
+            };
+            // Only the focused app gets to show the cursor.
+            let cursor = gfx.requested_cursor();
+            gfx.with_surface(&inner, |gfx| {
+                if pane.floating.is_some() {
+                    gfx.clear_screen();
+                }
+                pane.app.draw(gfx);
+            });
+            if !focused {
+                gfx.set_requested_cursor(cursor);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::io;
+    use std::rc::Rc;
+
+    use super::*;
+    use crate::test_util::{click, key, key_with_modifiers};
+
+    /// Events a probe got, shared with the test.
+    type Recorded = Rc<RefCell<Vec<Event>>>;
+
+    /// Keeps the events it got for the test to look at.
+    #[derive(Default)]
+    struct Probe {
+        events: Recorded,
+        text: String,
+    }
+
+    impl App for Probe {
+        fn reset(&mut self, _gfx: &mut Gfx) {}
+
+        fn update(&mut self, events: &EventGroup, _gfx: &mut Gfx) -> bool {
+            self.events
+                .borrow_mut()
+                .extend(events.events.iter().cloned());
+            true
+        }
+
+        fn draw(&self, gfx: &mut Gfx) {
+            gfx.draw_text_uncoloured(&self.text, 0, 0);
+        }
+    }
+
+    fn probe(text: &str) -> (Box<dyn App>, Recorded) {
+        let events = Rc::new(RefCell::new(vec![]));
+        let probe = Probe {
+            events: events.clone(),
+            text: text.to_string(),
+        };
+        (Box::new(probe), events)
+    }
+
+    fn frame(compositor: &mut Compositor, gfx: &mut Gfx, events: Vec<Event>) {
+        let mut group = EventGroup::new();
+        group.events = events;
+        compositor.update(&group, gfx);
+    }
+
+    fn setup() -> (Compositor, Gfx, Recorded, Recorded) {
+        let mut gfx = Gfx::with_output(Box::new(io::sink()));
+        gfx.resize(20, 6);
+
+        let (left, left_events) = probe("left pane text");
+        let (right, right_events) = probe("right");
+        let mut compositor = Compositor::new(Direction::Horizontal)
+            .with_pane(Pane::tiled("a", left))
+            .with_pane(Pane::tiled("b", right));
+        compositor.reset(&mut gfx);
+
+        (compositor, gfx, left_events, right_events)
+    }
+
+    #[test]
+    fn routes_keys_to_focus_and_mouse_in_local_coordinates() {
+        let (mut compositor, mut gfx, left_events, right_events) = setup();
+        assert_eq!(
+            compositor.pane_rect(1),
+            Some(Rect::new(U16Point::new(10, 0), U16Point::new(9, 5)))
+        );
+
+        frame(&mut compositor, &mut gfx, vec![key(KeyCode::Char('a'))]);
+        // Sizes were given on reset, only changes come as resize events.
+        assert_eq!(*left_events.borrow(), vec![key(KeyCode::Char('a'))]);
+        assert!(right_events.borrow().is_empty());
+
+        frame(&mut compositor, &mut gfx, click(12, 3));
+        assert_eq!(compositor.focused(), 1);
+        assert_eq!(*right_events.borrow(), click(1, 2));
+
+        // A click on the border focuses without reaching the app.
+        frame(&mut compositor, &mut gfx, click(0, 0));
+        assert_eq!(compositor.focused(), 0);
+        assert_eq!(left_events.borrow().len(), 1);
+    }
+
+    #[test]
+    fn shortcuts_move_focus_and_resize() {
+        let (mut compositor, mut gfx, left_events, _) = setup();
+
+        frame(
+            &mut compositor,
+            &mut gfx,
+            vec![key_with_modifiers(
+                KeyCode::Right,
+                COMPOSITOR_FOCUS_MODIFIERS,
+            )],
+        );
+        assert_eq!(compositor.focused(), 1);
+        frame(
+            &mut compositor,
+            &mut gfx,
+            vec![key_with_modifiers(
+                KeyCode::Left,
+                COMPOSITOR_FOCUS_MODIFIERS,
+            )],
+        );
+        assert_eq!(compositor.focused(), 0);
+
+        for _ in 0..10 {
+            frame(
+                &mut compositor,
+                &mut gfx,
+                vec![key_with_modifiers(
+                    KeyCode::Right,
+                    COMPOSITOR_RESIZE_MODIFIERS,
+                )],
+            );
+        }
+        assert_eq!(compositor.pane_rect(0).unwrap().width(), 14);
+        assert_eq!(*left_events.borrow().last().unwrap(), Event::Resize(12, 4));
+    }
+
+    #[test]
+    fn panes_draw_clipped_to_their_surface() {
+        let (compositor, mut gfx, _, _) = setup();
+
+        compositor.draw(&mut gfx);
+
+        let text = gfx.capture().to_text();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], "╔ a ═════╗╔ b ═════╗");
+        assert_eq!(lines[1], "║left pan║║right   ║");
+    }
+}
@@ -0,0 +1,572 @@
+use std::any::{Any, TypeId};
+use std::cell::{Ref, RefCell, RefMut};
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::event_group::EventGroup;
+use crate::gfx::Gfx;
+
+/// Handle of a game object. Handles of despawned entities stay invalid, even when their slot is
+/// reused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Entity {
+    index: u32,
+    generation: u32,
+}
+
+impl Entity {
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+}
+
+/// All components of one type, packed for fast iteration (a sparse set).
+#[derive(Debug)]
+pub struct Storage<T> {
+    /// Entity index to position in `components`.
+    sparse: Vec<Option<usize>>,
+    entities: Vec<Entity>,
+    components: Vec<T>,
+}
+
+impl<T> Storage<T> {
+    fn new() -> Self {
+        Self {
+            sparse: vec![],
+            entities: vec![],
+            components: vec![],
+        }
+    }
+
+    fn position(&self, entity: Entity) -> Option<usize> {
+        let i = (*self.sparse.get(entity.index as usize)?)?;
+        (self.entities[i] == entity).then_some(i)
+    }
+
+    pub fn get(&self, entity: Entity) -> Option<&T> {
+        self.position(entity).map(|i| &self.components[i])
+    }
+
+    pub fn get_mut(&mut self, entity: Entity) -> Option<&mut T> {
+        self.position(entity).map(|i| &mut self.components[i])
+    }
+
+    /// Entities having the component, in storage order.
+    pub fn entities(&self) -> &[Entity] {
+        &self.entities
+    }
+
+    pub fn len(&self) -> usize {
+        self.components.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.components.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Entity, &T)> {
+        self.entities.iter().copied().zip(&self.components)
+    }
+
+    /// The previous component of the entity, if any.
+    fn insert(&mut self, entity: Entity, component: T) -> Option<T> {
+        if let Some(i) = self.position(entity) {
+            return Some(std::mem::replace(&mut self.components[i], component));
+        }
+
+        let index = entity.index as usize;
+        if self.sparse.len() <= index {
+            self.sparse.resize(index + 1, None);
+        }
+        self.sparse[index] = Some(self.components.len());
+        self.entities.push(entity);
+        self.components.push(component);
+        None
+    }
+
+    fn remove(&mut self, entity: Entity) -> Option<T> {
+        let i = self.position(entity)?;
+        self.sparse[entity.index as usize] = None;
+        self.entities.swap_remove(i);
+        let component = self.components.swap_remove(i);
+        if let Some(moved) = self.entities.get(i) {
+            self.sparse[moved.index as usize] = Some(i);
+        }
+        Some(component)
+    }
+}
+
+trait AnyStorage {
+    fn remove_entity(&mut self, entity: Entity);
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: 'static> AnyStorage for Storage<T> {
+    fn remove_entity(&mut self, entity: Entity) {
+        self.remove(entity);
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+type Insert = Box<dyn FnOnce(&mut World, Entity)>;
+
+/// Components for an entity spawned later, see `World::spawn_later`.
+#[derive(Default)]
+pub struct EntityBuilder {
+    inserts: Vec<Insert>,
+}
+
+impl EntityBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with<T: 'static>(mut self, component: T) -> Self {
+        self.inserts.push(Box::new(move |world, entity| {
+            world.insert(entity, component);
+        }));
+        self
+    }
+}
+
+/// Entities with typed components and shared resources. Iterate with `query`, eg.
+/// `world.query::<(&mut Pos, &Vel)>(|entity, (pos, vel)| ...)`.
+///
+/// Components are borrowed like `RefCell`s: a query can't hold the same component type both
+/// mutably and immutably, and the world can't be changed during a query. Use `spawn_later` and
+/// `despawn_later` there instead.
+#[derive(Default)]
+pub struct World {
+    generations: Vec<u32>,
+    alive: Vec<bool>,
+    free: Vec<u32>,
+    storages: HashMap<TypeId, RefCell<Box<dyn AnyStorage>>>,
+    resources: HashMap<TypeId, RefCell<Box<dyn Any>>>,
+    spawn_queue: RefCell<Vec<EntityBuilder>>,
+    despawn_queue: RefCell<Vec<Entity>>,
+}
+
+impl fmt::Debug for World {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("World")
+            .field("entities", &self.len())
+            .field("component_types", &self.storages.len())
+            .field("resources", &self.resources.len())
+            .finish()
+    }
+}
+
+impl World {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn spawn(&mut self) -> Entity {
+        if let Some(index) = self.free.pop() {
+            self.alive[index as usize] = true;
+            return Entity {
+                index,
+                generation: self.generations[index as usize],
+            };
+        }
+
+        self.generations.push(0);
+        self.alive.push(true);
+        Entity {
+            index: self.generations.len() as u32 - 1,
+            generation: 0,
+        }
+    }
+
+    pub fn spawn_with(&mut self, builder: EntityBuilder) -> Entity {
+        let entity = self.spawn();
+        for insert in builder.inserts {
+            insert(self, entity);
+        }
+        entity
+    }
+
+    /// False if the entity was already gone.
+    pub fn despawn(&mut self, entity: Entity) -> bool {
+        if !self.is_alive(entity) {
+            return false;
+        }
+
+        for storage in self.storages.values_mut() {
+            storage.get_mut().remove_entity(entity);
+        }
+        let index = entity.index as usize;
+        self.alive[index] = false;
+        self.generations[index] = self.generations[index].wrapping_add(1);
+        self.free.push(entity.index);
+        true
+    }
+
+    pub fn is_alive(&self, entity: Entity) -> bool {
+        let index = entity.index as usize;
+        self.alive.get(index) == Some(&true) && self.generations[index] == entity.generation
+    }
+
+    /// Number of live entities.
+    pub fn len(&self) -> usize {
+        self.alive.len() - self.free.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Despawns everything, resources are kept.
+    pub fn clear(&mut self) {
+        for index in 0..self.alive.len() {
+            if self.alive[index] {
+                self.despawn(Entity {
+                    index: index as u32,
+                    generation: self.generations[index],
+                });
+            }
+        }
+        self.spawn_queue.get_mut().clear();
+        self.despawn_queue.get_mut().clear();
+    }
+
+    /// Adds or replaces the component of the entity. False if the entity is gone.
+    pub fn insert<T: 'static>(&mut self, entity: Entity, component: T) -> bool {
+        if !self.is_alive(entity) {
+            return false;
+        }
+
+        self.storages
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| RefCell::new(Box::new(Storage::<T>::new())))
+            .get_mut()
+            .as_any_mut()
+            .downcast_mut::<Storage<T>>()
+            .expect("Storage of the wrong type")
+            .insert(entity, component);
+        true
+    }
+
+    pub fn remove<T: 'static>(&mut self, entity: Entity) -> Option<T> {
+        self.storages
+            .get_mut(&TypeId::of::<T>())?
+            .get_mut()
+            .as_any_mut()
+            .downcast_mut::<Storage<T>>()?
+            .remove(entity)
+    }
+
+    /// `None` until a component of the type is inserted.
+    pub fn storage<T: 'static>(&self) -> Option<Ref<'_, Storage<T>>> {
+        let storage = self.storages.get(&TypeId::of::<T>())?.borrow();
+        Ref::filter_map(storage, |storage| storage.as_any().downcast_ref()).ok()
+    }
+
+    pub fn storage_mut<T: 'static>(&self) -> Option<RefMut<'_, Storage<T>>> {
+        let storage = self.storages.get(&TypeId::of::<T>())?.borrow_mut();
+        RefMut::filter_map(storage, |storage| storage.as_any_mut().downcast_mut()).ok()
+    }
+
+    pub fn get<T: 'static>(&self, entity: Entity) -> Option<Ref<'_, T>> {
+        Ref::filter_map(self.storage::<T>()?, |storage| storage.get(entity)).ok()
+    }
+
+    pub fn get_mut<T: 'static>(&self, entity: Entity) -> Option<RefMut<'_, T>> {
+        RefMut::filter_map(self.storage_mut::<T>()?, |storage| storage.get_mut(entity)).ok()
+    }
+
+    pub fn has<T: 'static>(&self, entity: Entity) -> bool {
+        self.get::<T>(entity).is_some()
+    }
+
+    /// Calls `f` for every entity having all the components of the query, see `Query`.
+    pub fn query<Q: Query>(&self, f: impl FnMut(Entity, Q::Items<'_>)) {
+        Q::for_each(self, f);
+    }
+
+    /// Spawned by `apply_deferred`, safe to call during a query.
+    pub fn spawn_later(&self, builder: EntityBuilder) {
+        self.spawn_queue.borrow_mut().push(builder);
+    }
+
+    /// Despawned by `apply_deferred`, safe to call during a query.
+    pub fn despawn_later(&self, entity: Entity) {
+        self.despawn_queue.borrow_mut().push(entity);
+    }
+
+    pub fn apply_deferred(&mut self) {
+        for entity in std::mem::take(self.despawn_queue.get_mut()) {
+            self.despawn(entity);
+        }
+        for builder in std::mem::take(self.spawn_queue.get_mut()) {
+            self.spawn_with(builder);
+        }
+    }
+
+    /// Shared state of the systems, one per type. Replaces the previous one.
+    pub fn insert_resource<T: 'static>(&mut self, resource: T) {
+        self.resources
+            .insert(TypeId::of::<T>(), RefCell::new(Box::new(resource)));
+    }
+
+    pub fn resource<T: 'static>(&self) -> Option<Ref<'_, T>> {
+        let resource = self.resources.get(&TypeId::of::<T>())?.borrow();
+        Ref::filter_map(resource, |resource| resource.downcast_ref()).ok()
+    }
+
+    pub fn resource_mut<T: 'static>(&self) -> Option<RefMut<'_, T>> {
+        let resource = self.resources.get(&TypeId::of::<T>())?.borrow_mut();
+        RefMut::filter_map(resource, |resource| resource.downcast_mut()).ok()
+    }
+}
+
+/// One part of a query: `&T` or `&mut T`.
+pub trait QueryParam {
+    type Borrow<'w>;
+    type Item<'b>;
+
+    fn borrow(world: &World) -> Option<Self::Borrow<'_>>;
+    fn entities<'a>(borrow: &'a Self::Borrow<'_>) -> &'a [Entity];
+    fn fetch<'b>(borrow: &'b mut Self::Borrow<'_>, entity: Entity) -> Option<Self::Item<'b>>;
+}
+
+impl<T: 'static> QueryParam for &T {
+    type Borrow<'w> = Ref<'w, Storage<T>>;
+    type Item<'b> = &'b T;
+
+    fn borrow(world: &World) -> Option<Self::Borrow<'_>> {
+        world.storage::<T>()
+    }
+
+    fn entities<'a>(borrow: &'a Self::Borrow<'_>) -> &'a [Entity] {
+        borrow.entities()
+    }
+
+    fn fetch<'b>(borrow: &'b mut Self::Borrow<'_>, entity: Entity) -> Option<Self::Item<'b>> {
+        borrow.get(entity)
+    }
+}
+
+impl<T: 'static> QueryParam for &mut T {
+    type Borrow<'w> = RefMut<'w, Storage<T>>;
+    type Item<'b> = &'b mut T;
+
+    fn borrow(world: &World) -> Option<Self::Borrow<'_>> {
+        world.storage_mut::<T>()
+    }
+
+    fn entities<'a>(borrow: &'a Self::Borrow<'_>) -> &'a [Entity] {
+        borrow.entities()
+    }
+
+    fn fetch<'b>(borrow: &'b mut Self::Borrow<'_>, entity: Entity) -> Option<Self::Item<'b>> {
+        borrow.get_mut(entity)
+    }
+}
+
+/// Tuples of one to four `QueryParam`s. Entities are visited in the storage order of the first
+/// component, so put the rarest one first.
+pub trait Query {
+    type Items<'b>;
+
+    fn for_each(world: &World, f: impl FnMut(Entity, Self::Items<'_>));
+}
+
+macro_rules! impl_query {
+    ($first:ident $(, $rest:ident)*) => {
+        impl<$first: QueryParam $(, $rest: QueryParam)*> Query for ($first, $($rest,)*) {
+            type Items<'b> = ($first::Item<'b>, $($rest::Item<'b>,)*);
+
+            #[allow(non_snake_case)]
+            fn for_each(world: &World, mut f: impl FnMut(Entity, Self::Items<'_>)) {
+                let Some(mut $first) = $first::borrow(world) else {
+                    return;
+                };
+                $(
+                    let Some(mut $rest) = $rest::borrow(world) else {
+                        return;
+                    };
+                )*
+
+                let entities = $first::entities(&$first).to_vec();
+                for entity in entities {
+                    let (Some($first), $(Some($rest),)*) = (
+                        $first::fetch(&mut $first, entity),
+                        $($rest::fetch(&mut $rest, entity),)*
+                    ) else {
+                        continue;
+                    };
+                    f(entity, ($first, $($rest,)*));
+                }
+            }
+        }
+    };
+}
+
+impl_query!(A);
+impl_query!(A, B);
+impl_query!(A, B, C);
+impl_query!(A, B, C, D);
+
+type UpdateSystem = Box<dyn FnMut(&mut World, &EventGroup, &mut Gfx)>;
+type DrawSystem = Box<dyn Fn(&World, &Gfx)>;
+
+/// Systems of the update and the draw phase, run in the order they were added.
+#[derive(Default)]
+pub struct Schedule {
+    update: Vec<UpdateSystem>,
+    draw: Vec<DrawSystem>,
+}
+
+impl fmt::Debug for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Schedule")
+            .field("update", &self.update.len())
+            .field("draw", &self.draw.len())
+            .finish()
+    }
+}
+
+impl Schedule {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_update(
+        mut self,
+        system: impl FnMut(&mut World, &EventGroup, &mut Gfx) + 'static,
+    ) -> Self {
+        self.update.push(Box::new(system));
+        self
+    }
+
+    pub fn with_draw(mut self, system: impl Fn(&World, &Gfx) + 'static) -> Self {
+        self.draw.push(Box::new(system));
+        self
+    }
+
+    /// Deferred spawns and despawns are applied after every system.
+    pub fn update(&mut self, world: &mut World, events: &EventGroup, gfx: &mut Gfx) {
+        for system in &mut self.update {
+            system(world, events, gfx);
+            world.apply_deferred();
+        }
+    }
+
+    pub fn draw(&self, world: &World, gfx: &Gfx) {
+        for system in &self.draw {
+            system(world, gfx);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Pos(i32);
+    #[derive(Debug, PartialEq)]
+    struct Vel(i32);
+    struct Frozen;
+
+    #[test]
+    fn despawned_handles_stay_invalid() {
+        let mut world = World::new();
+        let first = world.spawn();
+        world.insert(first, Pos(1));
+
+        assert!(world.despawn(first));
+        assert!(!world.despawn(first));
+
+        let second = world.spawn();
+        assert_eq!(second.index(), first.index());
+        assert!(!world.is_alive(first));
+        assert!(!world.insert(first, Pos(2)));
+        assert!(world.get::<Pos>(second).is_none());
+        assert_eq!(world.len(), 1);
+    }
+
+    #[test]
+    fn queries_match_all_components() {
+        let mut world = World::new();
+        let moving = world.spawn_with(EntityBuilder::new().with(Pos(0)).with(Vel(2)));
+        let still = world.spawn_with(EntityBuilder::new().with(Pos(5)));
+        let frozen = world.spawn_with(EntityBuilder::new().with(Pos(9)).with(Vel(1)));
+        world.insert(frozen, Frozen);
+
+        world.query::<(&mut Pos, &Vel)>(|_, (pos, vel)| pos.0 += vel.0);
+        assert_eq!(*world.get::<Pos>(moving).unwrap(), Pos(2));
+        assert_eq!(*world.get::<Pos>(still).unwrap(), Pos(5));
+        assert_eq!(*world.get::<Pos>(frozen).unwrap(), Pos(10));
+
+        let mut frozen_found = vec![];
+        world.query::<(&Frozen, &Pos)>(|entity, _| frozen_found.push(entity));
+        assert_eq!(frozen_found, vec![frozen]);
+
+        world.remove::<Vel>(moving);
+        let mut count = 0;
+        world.query::<(&Vel,)>(|_, _| count += 1);
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn deferred_spawn_and_despawn() {
+        let mut world = World::new();
+        for i in 0..4 {
+            world.spawn_with(EntityBuilder::new().with(Pos(i)));
+        }
+
+        world.query::<(&Pos,)>(|entity, (pos,)| {
+            if pos.0 % 2 == 0 {
+                world.despawn_later(entity);
+                world.spawn_later(EntityBuilder::new().with(Pos(pos.0 + 10)));
+            }
+        });
+        assert_eq!(world.len(), 4);
+
+        world.apply_deferred();
+        let mut values = vec![];
+        world.query::<(&Pos,)>(|_, (pos,)| values.push(pos.0));
+        values.sort();
+        assert_eq!(values, vec![1, 3, 10, 12]);
+    }
+
+    #[test]
+    fn schedule_runs_systems_with_resources() {
+        struct Speed(i32);
+
+        let mut world = World::new();
+        world.insert_resource(Speed(3));
+        let entity = world.spawn_with(EntityBuilder::new().with(Pos(0)));
+
+        let mut schedule = Schedule::new()
+            .with_update(|world, _, _| {
+                let speed = world.resource::<Speed>().unwrap().0;
+                world.query::<(&mut Pos,)>(|_, (pos,)| pos.0 += speed);
+            })
+            .with_update(|world, _, _| {
+                world.query::<(&Pos,)>(|entity, (pos,)| {
+                    if pos.0 > 3 {
+                        world.despawn_later(entity);
+                    }
+                })
+            });
+
+        let mut gfx = Gfx::new();
+        schedule.update(&mut world, &EventGroup::default(), &mut gfx);
+        assert!(world.is_alive(entity));
+
+        world.resource_mut::<Speed>().unwrap().0 = 1;
+        schedule.update(&mut world, &EventGroup::default(), &mut gfx);
+        assert!(!world.is_alive(entity));
+    }
+}
@@ -1,8 +1,18 @@
 use crossterm::event::{Event, KeyCode, KeyEvent};
 
+use crate::common::*;
+use crate::get_current_ms;
+use crate::mouse::MouseState;
+
 #[derive(Debug, Default)]
 pub struct EventGroup {
     pub events: Vec<Event>,
+    /// Start time of the current frame.
+    pub current_ms: u128,
+    /// Time passed since the start of the previous frame.
+    pub delta_ms: u128,
+    /// Buttons, drags, clicks and wheel, tracked across frames.
+    pub mouse: MouseState,
 }
 
 impl EventGroup {
@@ -10,6 +20,19 @@ impl EventGroup {
         Self::default()
     }
 
+    /// Moves on to the next frame with the events arrived since the last one.
+    pub(crate) fn start_frame(&mut self, events: Vec<Event>) {
+        self.start_frame_at(events, get_current_ms());
+    }
+
+    /// A clock stepping back gives a frame with no time passed.
+    fn start_frame_at(&mut self, events: Vec<Event>, frame_start_ms: u128) {
+        self.delta_ms = frame_start_ms.saturating_sub(self.current_ms);
+        self.current_ms = frame_start_ms;
+        self.events = events;
+        self.mouse.update(&self.events, self.current_ms);
+    }
+
     pub fn first_pressed_char(&self) -> Option<char> {
         for e in &self.events {
             match e {
@@ -37,13 +60,40 @@ impl EventGroup {
         false
     }
 
-    pub fn last_mouse_pos(&self) -> Option<(u16, u16)> {
+    /// Text pasted into the terminal this frame, each paste arrives whole as one event.
+    pub fn pasted_text(&self) -> Option<&str> {
+        self.events.iter().find_map(|e| match e {
+            Event::Paste(text) => Some(text.as_str()),
+            _ => None,
+        })
+    }
+
+    pub fn last_mouse_pos(&self) -> Option<U16Point> {
         for e in self.events.iter().rev() {
             match e {
-                Event::Mouse(mouse_event) => return Some((mouse_event.column, mouse_event.row)),
+                Event::Mouse(mouse_event) => {
+                    return Some(U16Point::new(mouse_event.column, mouse_event.row));
+                }
                 _ => {}
             }
         }
         None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clock_stepping_back_passes_no_time() {
+        let mut events = EventGroup::new();
+        events.start_frame_at(vec![], 1_000);
+        events.start_frame_at(vec![], 1_016);
+        assert_eq!(events.delta_ms, 16);
+
+        events.start_frame_at(vec![], 500);
+        assert_eq!(events.delta_ms, 0);
+        assert_eq!(events.current_ms, 500);
+    }
+}
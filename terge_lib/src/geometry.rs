@@ -0,0 +1,328 @@
+use crate::common::*;
+use crate::line::Line;
+use crate::rect::Rect;
+
+/// Tolerance for float comparisons, lines are built from cell coordinates so this is plenty.
+const EPSILON: f32 = 1e-4;
+
+const OUTCODE_LEFT: u8 = 0b0001;
+const OUTCODE_RIGHT: u8 = 0b0010;
+const OUTCODE_TOP: u8 = 0b0100;
+const OUTCODE_BOTTOM: u8 = 0b1000;
+
+/// Crossing point of two segments. Zero-length segments are treated as points. Overlapping
+/// collinear segments return the shared point closest to `a_start`.
+pub fn segment_intersection(
+    a_start: F32Point,
+    a_end: F32Point,
+    b_start: F32Point,
+    b_end: F32Point,
+) -> Option<F32Point> {
+    let r = a_end - a_start;
+    let s = b_end - b_start;
+
+    if r.length_squared() <= EPSILON {
+        return is_point_on_segment(a_start, b_start, b_end).then_some(a_start);
+    }
+    if s.length_squared() <= EPSILON {
+        return is_point_on_segment(b_start, a_start, a_end).then_some(b_start);
+    }
+
+    let offset = b_start - a_start;
+    let denominator = r.cross(s);
+
+    if denominator.abs() > EPSILON {
+        let t = offset.cross(s) / denominator;
+        let u = offset.cross(r) / denominator;
+        return (is_unit(t) && is_unit(u)).then(|| a_start + r * t.clamp(0.0, 1.0));
+    }
+
+    if offset.cross(r).abs() > EPSILON {
+        // Parallel.
+        return None;
+    }
+
+    // Collinear, project `b` onto `a` and check whether the two ranges meet.
+    let t0 = offset.dot(r) / r.length_squared();
+    let t1 = t0 + s.dot(r) / r.length_squared();
+    let t_min = t0.min(t1).max(0.0);
+    let t_max = t0.max(t1).min(1.0);
+
+    (t_min <= t_max + EPSILON).then(|| a_start + r * t_min)
+}
+
+/// Points where the segment crosses the border of the rect, ordered from `start`.
+pub fn segment_rect_intersections(start: F32Point, end: F32Point, rect: &Rect) -> Vec<F32Point> {
+    let mut out: Vec<F32Point> = vec![];
+
+    for (edge_start, edge_end) in rect_edges(rect) {
+        if let Some(p) = segment_intersection(start, end, edge_start, edge_end)
+            && !out.iter().any(|other| other.distance(p) <= EPSILON)
+        {
+            out.push(p);
+        }
+    }
+
+    out.sort_by(|lhs, rhs| start.distance(*lhs).total_cmp(&start.distance(*rhs)));
+    out
+}
+
+/// First point where the ray hits the border of the rect. A ray starting inside hits it on its
+/// way out.
+pub fn ray_rect_intersection(
+    origin: F32Point,
+    direction: F32Point,
+    rect: &Rect,
+) -> Option<F32Point> {
+    if direction.length_squared() <= EPSILON {
+        return None;
+    }
+
+    let min = rect.start.as_f32();
+    let max = rect.end().as_f32();
+    let mut t_near = f32::NEG_INFINITY;
+    let mut t_far = f32::INFINITY;
+
+    for (origin, direction, min, max) in [
+        (origin.x, direction.x, min.x, max.x),
+        (origin.y, direction.y, min.y, max.y),
+    ] {
+        if direction == 0.0 {
+            if origin < min || origin > max {
+                return None;
+            }
+            continue;
+        }
+
+        let t1 = (min - origin) / direction;
+        let t2 = (max - origin) / direction;
+        t_near = t_near.max(t1.min(t2));
+        t_far = t_far.min(t1.max(t2));
+    }
+
+    if t_far < t_near || t_far < 0.0 {
+        return None;
+    }
+
+    let t = if t_near >= 0.0 { t_near } else { t_far };
+    Some(origin + direction * t)
+}
+
+/// Cohen-Sutherland clipping, `None` if the segment is completely outside.
+pub fn clip_segment_to_rect(
+    start: F32Point,
+    end: F32Point,
+    rect: &Rect,
+) -> Option<(F32Point, F32Point)> {
+    let min = rect.start.as_f32();
+    let max = rect.end().as_f32();
+    let outcode = |p: F32Point| {
+        let mut code = 0;
+        if p.x < min.x {
+            code |= OUTCODE_LEFT;
+        } else if p.x > max.x {
+            code |= OUTCODE_RIGHT;
+        }
+        if p.y < min.y {
+            code |= OUTCODE_TOP;
+        } else if p.y > max.y {
+            code |= OUTCODE_BOTTOM;
+        }
+        code
+    };
+
+    let (mut start, mut end) = (start, end);
+    let (mut start_code, mut end_code) = (outcode(start), outcode(end));
+
+    loop {
+        if start_code | end_code == 0 {
+            return Some((start, end));
+        }
+        if start_code & end_code != 0 {
+            return None;
+        }
+
+        let code = if start_code != 0 {
+            start_code
+        } else {
+            end_code
+        };
+        let d = end - start;
+        // The outcode guarantees movement along the axis being clipped, no division by zero.
+        let p = if code & OUTCODE_TOP != 0 {
+            F32Point::new(start.x + d.x * (min.y - start.y) / d.y, min.y)
+        } else if code & OUTCODE_BOTTOM != 0 {
+            F32Point::new(start.x + d.x * (max.y - start.y) / d.y, max.y)
+        } else if code & OUTCODE_RIGHT != 0 {
+            F32Point::new(max.x, start.y + d.y * (max.x - start.x) / d.x)
+        } else {
+            F32Point::new(min.x, start.y + d.y * (min.x - start.x) / d.x)
+        };
+
+        if code == start_code {
+            start = p;
+            start_code = outcode(start);
+        } else {
+            end = p;
+            end_code = outcode(end);
+        }
+    }
+}
+
+/// Cell based version of `clip_segment_to_rect`.
+pub fn clip_line_to_rect(line: &Line, rect: &Rect) -> Option<Line> {
+    clip_segment_to_rect(line.start.as_f32(), line.end.as_f32(), rect).map(|(start, end)| Line {
+        start: start.round().saturating_u16(),
+        end: end.round().saturating_u16(),
+    })
+}
+
+fn is_point_on_segment(p: F32Point, start: F32Point, end: F32Point) -> bool {
+    let d = end - start;
+    if d.length_squared() <= EPSILON {
+        return p.distance(start) <= EPSILON;
+    }
+
+    (p - start).cross(d).abs() <= EPSILON * d.length()
+        && is_unit((p - start).dot(d) / d.length_squared())
+}
+
+fn is_unit(t: f32) -> bool {
+    (-EPSILON..=1.0 + EPSILON).contains(&t)
+}
+
+fn rect_edges(rect: &Rect) -> [(F32Point, F32Point); 4] {
+    let min = rect.start.as_f32();
+    let max = rect.end().as_f32();
+    let top_right = F32Point::new(max.x, min.y);
+    let bottom_left = F32Point::new(min.x, max.y);
+
+    [
+        (min, top_right),
+        (top_right, max),
+        (max, bottom_left),
+        (bottom_left, min),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::rect_from_size;
+
+    fn p(x: f32, y: f32) -> F32Point {
+        F32Point::new(x, y)
+    }
+
+    #[test]
+    fn crossing_segments() {
+        assert_eq!(
+            segment_intersection(p(0.0, 0.0), p(4.0, 4.0), p(0.0, 4.0), p(4.0, 0.0)),
+            Some(p(2.0, 2.0))
+        );
+        assert_eq!(
+            segment_intersection(p(0.0, 0.0), p(1.0, 1.0), p(0.0, 4.0), p(4.0, 0.0)),
+            None
+        );
+    }
+
+    #[test]
+    fn vertical_and_horizontal_segments() {
+        assert_eq!(
+            segment_intersection(p(3.0, 0.0), p(3.0, 10.0), p(0.0, 5.0), p(8.0, 5.0)),
+            Some(p(3.0, 5.0))
+        );
+    }
+
+    #[test]
+    fn parallel_and_collinear_segments() {
+        assert_eq!(
+            segment_intersection(p(0.0, 0.0), p(5.0, 0.0), p(0.0, 1.0), p(5.0, 1.0)),
+            None
+        );
+        assert_eq!(
+            segment_intersection(p(0.0, 0.0), p(5.0, 0.0), p(3.0, 0.0), p(9.0, 0.0)),
+            Some(p(3.0, 0.0))
+        );
+        assert_eq!(
+            segment_intersection(p(0.0, 0.0), p(5.0, 0.0), p(6.0, 0.0), p(9.0, 0.0)),
+            None
+        );
+    }
+
+    #[test]
+    fn zero_length_segments() {
+        assert_eq!(
+            segment_intersection(p(2.0, 2.0), p(2.0, 2.0), p(0.0, 0.0), p(4.0, 4.0)),
+            Some(p(2.0, 2.0))
+        );
+        assert_eq!(
+            segment_intersection(p(0.0, 0.0), p(4.0, 0.0), p(1.0, 1.0), p(1.0, 1.0)),
+            None
+        );
+    }
+
+    #[test]
+    fn vertical_segment_through_rect() {
+        let r = rect_from_size(2, 2, 6, 4);
+
+        assert_eq!(
+            segment_rect_intersections(p(5.0, 0.0), p(5.0, 10.0), &r),
+            vec![p(5.0, 2.0), p(5.0, 6.0)]
+        );
+        assert_eq!(
+            segment_rect_intersections(p(5.0, 4.0), p(5.0, 0.0), &r),
+            vec![p(5.0, 2.0)]
+        );
+    }
+
+    #[test]
+    fn segment_through_corner_is_reported_once() {
+        assert_eq!(
+            segment_rect_intersections(p(0.0, 0.0), p(2.0, 2.0), &rect_from_size(2, 2, 2, 2)),
+            vec![p(2.0, 2.0)]
+        );
+    }
+
+    #[test]
+    fn ray_from_outside_and_inside() {
+        let r = rect_from_size(2, 2, 6, 4);
+
+        assert_eq!(
+            ray_rect_intersection(p(0.0, 4.0), p(1.0, 0.0), &r),
+            Some(p(2.0, 4.0))
+        );
+        assert_eq!(
+            ray_rect_intersection(p(5.0, 4.0), p(0.0, -1.0), &r),
+            Some(p(5.0, 2.0))
+        );
+        assert_eq!(ray_rect_intersection(p(0.0, 4.0), p(-1.0, 0.0), &r), None);
+        assert_eq!(ray_rect_intersection(p(0.0, 0.0), p(0.0, 1.0), &r), None);
+    }
+
+    #[test]
+    fn clipping() {
+        let r = rect_from_size(0, 0, 10, 10);
+
+        assert_eq!(
+            clip_segment_to_rect(p(-5.0, 5.0), p(15.0, 5.0), &r),
+            Some((p(0.0, 5.0), p(10.0, 5.0)))
+        );
+        assert_eq!(
+            clip_segment_to_rect(p(5.0, -5.0), p(5.0, 3.0), &r),
+            Some((p(5.0, 0.0), p(5.0, 3.0)))
+        );
+        assert_eq!(clip_segment_to_rect(p(11.0, 0.0), p(20.0, 10.0), &r), None);
+        assert_eq!(
+            clip_line_to_rect(
+                &Line {
+                    start: U16Point::new(0, 0),
+                    end: U16Point::new(40, 20),
+                },
+                &r
+            )
+            .map(|line| line.end),
+            Some(U16Point::new(10, 5))
+        );
+    }
+}
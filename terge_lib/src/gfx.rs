@@ -1,21 +1,199 @@
+use std::cell::RefCell;
 use std::io::{self, Write};
+use std::path::Path;
 
-use crossterm::{ExecutableCommand, terminal};
+use crossterm::{cursor, terminal};
+use log::warn;
 
+use crate::asset::Assets;
+use crate::capabilities::{Capabilities, to_ascii};
+use crate::capture::{Capture, ScreenBuffer};
+use crate::clipboard::Clipboard;
 use crate::common::*;
-use crate::line::{Line, LinePointsIterator};
+use crate::geometry::clip_line_to_rect;
+use crate::line::Line;
+use crate::random::Random;
+use crate::recorder::Recorder;
 use crate::rect::Rect;
+use crate::sprite::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CursorShape {
+    #[default]
+    Block,
+    BlinkingBlock,
+    Bar,
+    BlinkingBar,
+    Underline,
+    BlinkingUnderline,
+}
+
+impl CursorShape {
+    fn style(self) -> cursor::SetCursorStyle {
+        match self {
+            CursorShape::Block => cursor::SetCursorStyle::SteadyBlock,
+            CursorShape::BlinkingBlock => cursor::SetCursorStyle::BlinkingBlock,
+            CursorShape::Bar => cursor::SetCursorStyle::SteadyBar,
+            CursorShape::BlinkingBar => cursor::SetCursorStyle::BlinkingBar,
+            CursorShape::Underline => cursor::SetCursorStyle::SteadyUnderScore,
+            CursorShape::BlinkingUnderline => cursor::SetCursorStyle::BlinkingUnderScore,
+        }
+    }
+}
 
 pub struct Gfx {
     pub width: u16,
     pub height: u16,
+    pub assets: Assets,
+    /// Seedable, see `Terge::set_seed`.
+    pub rng: Random,
+    pub clipboard: Clipboard,
+    /// Output is degraded to what the terminal can show, see `Capabilities`.
+    pub capabilities: Capabilities,
+    /// Requested for the current frame.
+    cursor: Option<(U16Point, CursorShape)>,
+    /// What the terminal shows right now, `None` when hidden.
+    shown_cursor_shape: Option<CursorShape>,
+    /// Where the frame goes, STDOUT unless the app is served over the network.
+    out: RefCell<Box<dyn Write>>,
+    /// Gets a copy of the output while recording.
+    recorder: RefCell<Option<Recorder>>,
+    /// Mirrors the terminal for `capture`.
+    screen: RefCell<ScreenBuffer>,
+    /// Upper-left corner of the current surface on the terminal, see `with_surface`.
+    origin: U16Point,
+    /// Origin, width and height of the enclosing surfaces, the terminal itself first.
+    parent_surfaces: Vec<(U16Point, u16, u16)>,
 }
 
 impl Gfx {
     pub(crate) fn new() -> Self {
+        Self::with_output(Box::new(io::stdout()))
+    }
+
+    pub(crate) fn with_output(out: Box<dyn Write>) -> Self {
         Self {
             width: 0,
             height: 0,
+            assets: Assets::new(),
+            rng: Random::from_entropy(),
+            clipboard: Clipboard::new(),
+            capabilities: Capabilities::default(),
+            cursor: None,
+            shown_cursor_shape: None,
+            out: RefCell::new(out),
+            recorder: RefCell::new(None),
+            screen: RefCell::new(ScreenBuffer::new()),
+            origin: U16Point::new(0, 0),
+            parent_surfaces: vec![],
+        }
+    }
+
+    /// Raw bytes to the terminal, escape sequences included. Non-ASCII text is replaced when
+    /// the terminal can't show Unicode.
+    pub(crate) fn write_raw(&self, text: &str) {
+        let text = match self.capabilities.unicode {
+            true => text.into(),
+            false => to_ascii(text),
+        };
+        let text = text.as_ref();
+        self.out
+            .borrow_mut()
+            .write_all(text.as_bytes())
+            .expect("Failed writing bytes");
+        if let Some(recorder) = self.recorder.borrow_mut().as_mut() {
+            recorder.output(text);
+        }
+
+        let (width, height) = self.terminal_size();
+        let mut screen = self.screen.borrow_mut();
+        screen.resize(width, height);
+        screen.feed(text);
+    }
+
+    /// What is on the screen right now, for exporting with `Capture::save` and friends.
+    pub fn capture(&self) -> Capture {
+        let (width, height) = self.terminal_size();
+        let mut screen = self.screen.borrow_mut();
+        screen.resize(width, height);
+        screen.capture()
+    }
+
+    /// Runs `f` with drawing limited to `rect` of the current surface. Inside, coordinates start
+    /// at the corner of `rect`, `width` and `height` are its size and whatever falls outside is
+    /// clipped instead of wrapping around. Surfaces nest.
+    pub fn with_surface<R>(&mut self, rect: &Rect, f: impl FnOnce(&mut Gfx) -> R) -> R {
+        let (origin, width, height) = match rect.intersection(&self.screen_rect()) {
+            Some(rect) => (
+                self.origin.saturating_add(rect.start),
+                rect.width() as u16,
+                rect.height() as u16,
+            ),
+            None => (self.origin, 0, 0),
+        };
+
+        self.parent_surfaces
+            .push((self.origin, self.width, self.height));
+        self.origin = origin;
+        self.width = width;
+        self.height = height;
+
+        let out = f(self);
+
+        let (origin, width, height) = self
+            .parent_surfaces
+            .pop()
+            .expect("Failed restoring parent surface");
+        self.origin = origin;
+        self.width = width;
+        self.height = height;
+
+        out
+    }
+
+    /// Whether drawing goes to a part of the terminal, see `with_surface`.
+    pub fn is_surface(&self) -> bool {
+        !self.parent_surfaces.is_empty()
+    }
+
+    fn terminal_size(&self) -> (u16, u16) {
+        match self.parent_surfaces.first() {
+            Some((_, width, height)) => (*width, *height),
+            None => (self.width, self.height),
+        }
+    }
+
+    pub(crate) fn queue(&self, command: impl crossterm::Command) {
+        let mut ansi = String::new();
+        command
+            .write_ansi(&mut ansi)
+            .expect("Failed running crossterm commands");
+        self.write_raw(&ansi);
+    }
+
+    /// Records everything written to the terminal from now on into an asciicast v2 file, see
+    /// `Recorder`. Replaces a recording in progress.
+    pub fn start_recording(&mut self, path: impl AsRef<Path>) -> io::Result<()> {
+        let (width, height) = self.terminal_size();
+        *self.recorder.get_mut() = Some(Recorder::create(path, width, height)?);
+        Ok(())
+    }
+
+    pub fn stop_recording(&mut self) {
+        self.recorder.get_mut().take();
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recorder.borrow().is_some()
+    }
+
+    pub(crate) fn resize(&mut self, width: u16, height: u16) {
+        self.width = width;
+        self.height = height;
+        if let Some(recorder) = self.recorder.get_mut()
+            && let Err(err) = recorder.resize(width, height)
+        {
+            warn!("Failed recording resize: {}", err);
         }
     }
 
@@ -26,37 +204,94 @@ impl Gfx {
         self.height = size.1;
     }
 
+    /// On a surface only the surface is cleared.
     pub fn clear_screen(&self) {
-        io::stdout()
-            .execute(terminal::Clear(terminal::ClearType::All))
-            .expect("Failed cleaning terminal");
-        self.flush_buffer();
+        if self.is_surface() {
+            let blank = " ".repeat(self.width as usize);
+            for y in 0..self.height {
+                self.draw_text_uncoloured(&blank, 0, y);
+            }
+            return;
+        }
+
+        self.queue(terminal::Clear(terminal::ClearType::All));
+        self.out
+            .borrow_mut()
+            .flush()
+            .expect("Failed flushing output");
     }
 
-    fn draw_pos(&self, x: u16, y: u16) {
-        io::stdout()
-            .execute(crossterm::cursor::MoveTo(x % self.width, y % self.height))
-            .expect("Failed moving cursor position");
+    /// Moves to (x, y) and returns the part of `text` that fits. Positions wrap around the
+    /// terminal, but on a surface they are clipped (counting chars) and `None` is outside.
+    fn draw_pos<'a>(&self, text: &'a str, x: u16, y: u16) -> Option<&'a str> {
+        if !self.is_surface() {
+            self.queue(cursor::MoveTo(x % self.width, y % self.height));
+            return Some(text);
+        }
+
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        self.queue(cursor::MoveTo(self.origin.x + x, self.origin.y + y));
+
+        match text.char_indices().nth((self.width - x) as usize) {
+            Some((end, _)) => Some(&text[..end]),
+            None => Some(text),
+        }
     }
 
     pub fn draw_text(&self, text: &str, x: u16, y: u16, color: u8) {
-        self.draw_pos(x, y);
-        print!("\x1B[{}m{}\x1B[0m", color, text);
+        let Some(text) = self.draw_pos(text, x, y) else {
+            return;
+        };
+        self.write_raw(&format!("{}{}\x1B[0m", self.sgr(&[color]), text));
+    }
+
+    /// Escape sequence setting the SGR codes the terminal can show, empty when none.
+    fn sgr(&self, codes: &[u8]) -> String {
+        let codes: Vec<String> = codes
+            .iter()
+            .filter_map(|code| self.capabilities.colors.sgr_code(*code))
+            .map(|code| code.to_string())
+            .collect();
+
+        if codes.is_empty() {
+            String::new()
+        } else {
+            format!("\x1B[{}m", codes.join(";"))
+        }
+    }
+
+    pub fn draw_text_rgb(&self, text: &str, x: u16, y: u16, color: RgbColor) {
+        let Some(text) = self.draw_pos(text, x, y) else {
+            return;
+        };
+        match self.capabilities.colors.rgb_sgr(color) {
+            Some(params) => self.write_raw(&format!("\x1B[{}m{}\x1B[0m", params, text)),
+            None => self.write_raw(text),
+        }
+    }
+
+    /// Text with several SGR codes at once, eg. `[1, 31]` for bold red.
+    pub fn draw_styled_text(&self, text: &str, x: u16, y: u16, codes: &[u8]) {
+        let Some(text) = self.draw_pos(text, x, y) else {
+            return;
+        };
+        self.write_raw(&format!("{}{}\x1B[0m", self.sgr(codes), text));
     }
 
     pub fn draw_text_uncoloured(&self, text: &str, x: u16, y: u16) {
-        self.draw_pos(x, y);
-        io::stdout()
-            .write_all(text.as_bytes())
-            .expect("Failed writing bytes");
+        if let Some(text) = self.draw_pos(text, x, y) {
+            self.write_raw(text);
+        }
     }
 
     pub fn draw_text_to_current_pos(&self, text: &str) {
-        print!("{}", text);
+        self.write_raw(text);
     }
 
     pub fn draw_text_at_point(&self, text: &str, p: U16Point, color: u8) {
-        self.draw_text(text, p.0, p.1, color);
+        self.draw_text(text, p.x, p.y, color);
     }
 
     pub fn draw_multiline_text(&self, lines: &Vec<String>, x: u16, y: u16, color: u8) {
@@ -65,18 +300,84 @@ impl Gfx {
         }
     }
 
-    pub(crate) fn flush_buffer(&self) {
-        std::io::stdout().flush().expect("Failed flushing STDOUT");
+    /// End of the frame, also the end of a recorded event.
+    pub(crate) fn flush_buffer(&mut self) {
+        if let Some(sequence) = self.clipboard.take_pending() {
+            self.write_raw(&sequence);
+        }
+        self.out
+            .borrow_mut()
+            .flush()
+            .expect("Failed flushing output");
+        if let Some(recorder) = self.recorder.get_mut()
+            && let Err(err) = recorder.flush()
+        {
+            warn!("Failed writing recording: {}", err);
+        }
+    }
+
+    /// Shows the real terminal cursor at `pos` once the frame is flushed. Only lasts for the
+    /// current frame, so apps call it from `draw` as long as they need the cursor. Ignored
+    /// outside the current surface.
+    pub fn show_cursor(&mut self, pos: U16Point, shape: CursorShape) {
+        if self.is_surface() && (pos.x >= self.width || pos.y >= self.height) {
+            return;
+        }
+        self.cursor = Some((self.origin.saturating_add(pos), shape));
+    }
+
+    /// Takes back a `show_cursor` of the current frame.
+    pub fn hide_cursor(&mut self) {
+        self.cursor = None;
+    }
+
+    pub(crate) fn requested_cursor(&self) -> Option<(U16Point, CursorShape)> {
+        self.cursor
+    }
+
+    pub(crate) fn set_requested_cursor(&mut self, cursor: Option<(U16Point, CursorShape)>) {
+        self.cursor = cursor;
+    }
+
+    /// Moves the cursor into place at the end of the frame (drawing moves it around), or hides it
+    /// when nobody asked for it this frame.
+    pub(crate) fn apply_cursor(&mut self) {
+        match self.cursor.take() {
+            Some((pos, shape)) => {
+                self.queue(cursor::MoveTo(pos.x, pos.y));
+                if self.shown_cursor_shape != Some(shape) {
+                    self.queue(shape.style());
+                }
+                if self.shown_cursor_shape.is_none() {
+                    self.queue(cursor::Show);
+                }
+                self.shown_cursor_shape = Some(shape);
+            }
+            None => {
+                if self.shown_cursor_shape.take().is_some() {
+                    self.queue(cursor::Hide);
+                }
+            }
+        }
+    }
+
+    /// The whole terminal as a rect.
+    pub fn screen_rect(&self) -> Rect {
+        Rect::new(
+            U16Point::new(0, 0),
+            U16Point::new(self.width.saturating_sub(1), self.height.saturating_sub(1)),
+        )
     }
 
     pub fn draw_rect(&self, rect: &Rect, color_code: u8) {
-        self.draw_rect_from_points(rect.start, rect.start.add(rect.size), color_code);
+        self.draw_rect_from_points(rect.start, rect.end(), color_code);
     }
 
     pub fn draw_rect_from_points(&self, lhs: U16Point, rhs: U16Point, color_code: u8) {
-        let (x_min, y_min, x_max, y_max) = point_pair_minmax(lhs, rhs);
+        let (min, max) = point_pair_minmax(lhs, rhs);
+        let (x_min, y_min, x_max, y_max) = (min.x, min.y, max.x, max.y);
 
-        self.draw_text_to_current_pos(&format!("\x1B[{}m", color_code));
+        self.draw_text_to_current_pos(&self.sgr(&[color_code]));
 
         for y in y_min..=y_max {
             self.draw_text_uncoloured(BOX_VERTICAL_CHAR, x_min, y);
@@ -108,16 +409,39 @@ impl Gfx {
         self.draw_line_from_points(line.start, line.end, color);
     }
 
+    /// Only the part of the line on the screen is drawn.
     pub fn draw_line_from_points(&self, start: U16Point, end: U16Point, color: u8) {
-        self.draw_text_to_current_pos(&format!("\x1B[{}m", color));
+        let Some(visible) = clip_line_to_rect(&Line { start, end }, &self.screen_rect()) else {
+            return;
+        };
+
+        self.draw_text_to_current_pos(&self.sgr(&[color]));
 
-        for (x, y) in LinePointsIterator::new(start, end) {
-            self.draw_text_uncoloured(BLOCK_CHAR, x, y);
+        for p in visible.iter() {
+            self.draw_text_uncoloured(BLOCK_CHAR, p.x, p.y);
         }
 
-        self.draw_text_uncoloured(LINE_CONNECTION_CHAR, start.0, start.1);
-        self.draw_text_uncoloured(LINE_CONNECTION_CHAR, end.0, end.1);
+        self.draw_text_uncoloured(LINE_CONNECTION_CHAR, start.x, start.y);
+        self.draw_text_uncoloured(LINE_CONNECTION_CHAR, end.x, end.y);
 
         self.draw_text_to_current_pos("\x1B[0m");
     }
+
+    /// Draws the sprite with its upper-left corner at (x, y). Transparent cells are skipped.
+    pub fn draw_sprite(&self, sprite: &Sprite, x: u16, y: u16) {
+        for (row_i, row) in sprite.rows.iter().enumerate() {
+            for (col_i, cell) in row.iter().enumerate() {
+                if cell.ch == SPRITE_TRANSPARENT_CHAR {
+                    continue;
+                }
+
+                self.draw_text(
+                    cell.ch.encode_utf8(&mut [0; 4]),
+                    x + col_i as u16,
+                    y + row_i as u16,
+                    cell.color,
+                );
+            }
+        }
+    }
 }
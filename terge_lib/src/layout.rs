@@ -0,0 +1,347 @@
+use crate::common::*;
+use crate::rect::Rect;
+
+/// How many cells a part of a `Layout` gets along the split direction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Constraint {
+    /// Exactly this many cells.
+    Length(u16),
+    /// Percent of the available cells, rounded down.
+    Percentage(u16),
+    /// `numerator / denominator` of the available cells, rounded down.
+    Ratio(u32, u32),
+    /// At least this many cells, grows like `Fill(1)`.
+    Min(u16),
+    /// Grows like `Fill(1)` but never beyond this many cells.
+    Max(u16),
+    /// Shares the cells left over by the others in proportion to the weight.
+    Fill(u16),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Direction {
+    /// Parts side by side, left to right.
+    Horizontal,
+    /// Parts stacked, top to bottom.
+    #[default]
+    Vertical,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Alignment {
+    #[default]
+    Start,
+    Center,
+    End,
+}
+
+/// Describes how to cut a rect into rows or columns. Splitting is cheap, so apps usually split
+/// `gfx.screen_rect()` every frame and get the new rects after a resize for free.
+///
+/// When the constraints ask for more cells than there are the parts at the end are cut first.
+/// Cells nobody asks for (no `Fill`, `Min` or `Max` parts) are left empty according to `align`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Layout {
+    pub direction: Direction,
+    pub constraints: Vec<Constraint>,
+    /// Cells kept empty on every side of the split rect.
+    pub margin: u16,
+    /// Cells kept empty between neighbouring parts.
+    pub spacing: u16,
+    pub align: Alignment,
+}
+
+impl Layout {
+    pub fn new(direction: Direction, constraints: impl Into<Vec<Constraint>>) -> Self {
+        Self {
+            direction,
+            constraints: constraints.into(),
+            margin: 0,
+            spacing: 0,
+            align: Alignment::Start,
+        }
+    }
+
+    pub fn horizontal(constraints: impl Into<Vec<Constraint>>) -> Self {
+        Self::new(Direction::Horizontal, constraints)
+    }
+
+    pub fn vertical(constraints: impl Into<Vec<Constraint>>) -> Self {
+        Self::new(Direction::Vertical, constraints)
+    }
+
+    pub fn with_margin(mut self, margin: u16) -> Self {
+        self.margin = margin;
+        self
+    }
+
+    pub fn with_spacing(mut self, spacing: u16) -> Self {
+        self.spacing = spacing;
+        self
+    }
+
+    pub fn with_align(mut self, align: Alignment) -> Self {
+        self.align = align;
+        self
+    }
+
+    /// One entry per constraint. Rects can't be empty, so parts that get no cells are `None`.
+    pub fn split(&self, area: &Rect) -> Vec<Option<Rect>> {
+        let Some(area) = area.inset(self.margin) else {
+            return vec![None; self.constraints.len()];
+        };
+
+        let (available, cross) = match self.direction {
+            Direction::Horizontal => (area.width(), area.height()),
+            Direction::Vertical => (area.height(), area.width()),
+        };
+        let gaps = self.spacing as u32 * (self.constraints.len() as u32).saturating_sub(1);
+        let available = available.saturating_sub(gaps);
+
+        let lengths = self.resolve(available);
+        let leftover = available - lengths.iter().sum::<u32>();
+        let mut offset = match self.align {
+            Alignment::Start => 0,
+            Alignment::Center => leftover / 2,
+            Alignment::End => leftover,
+        };
+
+        lengths
+            .into_iter()
+            .map(|length| {
+                let start = offset;
+                offset += length + self.spacing as u32;
+                if length == 0 {
+                    return None;
+                }
+
+                let (start, size) = match self.direction {
+                    Direction::Horizontal => (
+                        U16Point::new(start as u16, 0),
+                        U16Point::new(length as u16 - 1, cross as u16 - 1),
+                    ),
+                    Direction::Vertical => (
+                        U16Point::new(0, start as u16),
+                        U16Point::new(cross as u16 - 1, length as u16 - 1),
+                    ),
+                };
+                Some(Rect::new(area.start + start, size))
+            })
+            .collect()
+    }
+
+    /// Length of each part, in cells, never more than `available` in total.
+    fn resolve(&self, available: u32) -> Vec<u32> {
+        let mut lengths: Vec<u32> = self
+            .constraints
+            .iter()
+            .map(|constraint| match *constraint {
+                Constraint::Length(length) | Constraint::Min(length) => length as u32,
+                Constraint::Percentage(percent) => available * percent.min(100) as u32 / 100,
+                Constraint::Ratio(numerator, denominator) => {
+                    (available as u64 * numerator.min(denominator) as u64)
+                        .checked_div(denominator as u64)
+                        .unwrap_or(0) as u32
+                }
+                Constraint::Max(_) | Constraint::Fill(_) => 0,
+            })
+            .collect();
+
+        // Too much asked for, cut from the end.
+        let mut excess = lengths.iter().sum::<u32>().saturating_sub(available);
+        for length in lengths.iter_mut().rev() {
+            let cut = excess.min(*length);
+            *length -= cut;
+            excess -= cut;
+        }
+
+        let mut leftover = available - lengths.iter().sum::<u32>();
+        let mut growing: Vec<usize> = (0..lengths.len())
+            .filter(|i| weight(&self.constraints[*i]) > 0)
+            .collect();
+
+        // Hand out the leftover by weight. Parts hitting their `Max` drop out and the rest is
+        // shared again among the others.
+        while leftover > 0 && !growing.is_empty() {
+            let total_weight: u32 = growing.iter().map(|i| weight(&self.constraints[*i])).sum();
+            let mut shares: Vec<u32> = growing
+                .iter()
+                .map(|i| leftover * weight(&self.constraints[*i]) / total_weight)
+                .collect();
+            let rounding = leftover - shares.iter().sum::<u32>();
+            for share in shares.iter_mut().take(rounding as usize) {
+                *share += 1;
+            }
+
+            let mut capped = false;
+            for (i, share) in growing.iter().zip(&mut shares) {
+                if let Constraint::Max(max) = self.constraints[*i] {
+                    let room = (max as u32).saturating_sub(lengths[*i]);
+                    if *share >= room {
+                        *share = room;
+                        capped = true;
+                    }
+                }
+            }
+
+            for (i, share) in growing.iter().zip(&shares) {
+                lengths[*i] += share;
+                leftover -= share;
+            }
+
+            if !capped {
+                break;
+            }
+            growing.retain(|i| match self.constraints[*i] {
+                Constraint::Max(max) => lengths[*i] < max as u32,
+                _ => true,
+            });
+        }
+
+        lengths
+    }
+}
+
+fn weight(constraint: &Constraint) -> u32 {
+    match *constraint {
+        Constraint::Min(_) | Constraint::Max(_) => 1,
+        Constraint::Fill(weight) => weight as u32,
+        _ => 0,
+    }
+}
+
+/// Rect of `width` x `height` cells placed inside `area`, shrunk to fit if needed. `None` for a
+/// zero width or height.
+pub fn align_rect(
+    area: &Rect,
+    width: u16,
+    height: u16,
+    horizontal: Alignment,
+    vertical: Alignment,
+) -> Option<Rect> {
+    if width == 0 || height == 0 {
+        return None;
+    }
+
+    let width = (width as u32).min(area.width());
+    let height = (height as u32).min(area.height());
+    let offset = |free: u32, align: Alignment| {
+        (match align {
+            Alignment::Start => 0,
+            Alignment::Center => free / 2,
+            Alignment::End => free,
+        }) as u16
+    };
+
+    Some(Rect::new(
+        area.start
+            + U16Point::new(
+                offset(area.width() - width, horizontal),
+                offset(area.height() - height, vertical),
+            ),
+        U16Point::new(width as u16 - 1, height as u16 - 1),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Constraint::*;
+    use super::*;
+    use crate::test_util::rect_from_size;
+
+    // 80 x 24 cells.
+    fn screen() -> Rect {
+        rect_from_size(0, 0, 79, 23)
+    }
+
+    #[test]
+    fn status_bar_below_fill() {
+        assert_eq!(
+            Layout::vertical([Fill(1), Length(1)]).split(&screen()),
+            vec![
+                Some(rect_from_size(0, 0, 79, 22)),
+                Some(rect_from_size(0, 23, 79, 0))
+            ]
+        );
+    }
+
+    #[test]
+    fn percentages_ratios_and_fill_weights() {
+        let parts = Layout::horizontal([Percentage(25), Fill(1), Fill(2), Ratio(1, 4)])
+            .split(&screen())
+            .into_iter()
+            .map(|part| part.unwrap().width())
+            .collect::<Vec<_>>();
+
+        assert_eq!(parts, vec![20, 14, 26, 20]);
+    }
+
+    #[test]
+    fn min_and_max() {
+        let lengths = |constraints: Vec<Constraint>| Layout::horizontal(constraints).resolve(20);
+
+        assert_eq!(lengths(vec![Max(5), Fill(1)]), vec![5, 15]);
+        assert_eq!(lengths(vec![Max(50), Min(4)]), vec![8, 12]);
+        assert_eq!(lengths(vec![Min(15), Fill(1)]), vec![18, 2]);
+        assert_eq!(lengths(vec![Max(3), Max(4)]), vec![3, 4]);
+    }
+
+    #[test]
+    fn overflow_cuts_from_the_end() {
+        let parts = Layout::vertical([Length(20), Length(10), Length(5)]).split(&screen());
+
+        assert_eq!(parts[0].unwrap().height(), 20);
+        assert_eq!(parts[1].unwrap().height(), 4);
+        assert_eq!(parts[2], None);
+    }
+
+    #[test]
+    fn margin_spacing_and_alignment() {
+        let layout = Layout::horizontal([Length(10), Length(10)])
+            .with_margin(2)
+            .with_spacing(1);
+
+        assert_eq!(
+            layout.split(&screen()),
+            vec![
+                Some(rect_from_size(2, 2, 9, 19)),
+                Some(rect_from_size(13, 2, 9, 19))
+            ]
+        );
+        assert_eq!(
+            layout
+                .with_align(Alignment::End)
+                .split(&screen())
+                .last()
+                .copied()
+                .flatten(),
+            Some(rect_from_size(68, 2, 9, 19))
+        );
+        assert_eq!(
+            Layout::vertical([Fill(1)]).with_margin(12).split(&screen()),
+            vec![None]
+        );
+    }
+
+    #[test]
+    fn aligned_rect() {
+        assert_eq!(
+            align_rect(&screen(), 12, 1, Alignment::Center, Alignment::End),
+            Some(rect_from_size(34, 23, 11, 0))
+        );
+        assert_eq!(
+            align_rect(
+                &rect_from_size(5, 5, 3, 3),
+                10,
+                2,
+                Alignment::End,
+                Alignment::Start
+            ),
+            Some(rect_from_size(5, 5, 3, 1))
+        );
+        assert_eq!(
+            align_rect(&screen(), 0, 1, Alignment::Start, Alignment::Start),
+            None
+        );
+    }
+}
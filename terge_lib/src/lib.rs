@@ -1,5 +1,6 @@
 use std::{
     io::{self},
+    path::{Path, PathBuf},
     sync::{Arc, atomic::AtomicBool, mpsc},
     thread,
     time::Duration,
@@ -9,16 +10,47 @@ use crossterm::{
     ExecutableCommand, cursor,
     event::{self, Event, KeyCode, poll, read},
 };
-use log::trace;
+use log::{trace, warn};
 
+pub mod asset;
+pub mod banner;
+pub mod camera;
+pub mod capabilities;
+pub mod capture;
+pub mod clipboard;
 pub mod common;
+pub mod compositor;
+pub mod ecs;
 pub mod event_group;
+pub mod geometry;
 pub mod gfx;
+pub mod layout;
 pub mod line;
+pub mod mouse;
+pub mod particle;
+pub mod pathfinding;
+pub mod physics;
+pub mod point;
+pub mod random;
+pub mod recorder;
 pub mod rect;
+#[cfg(feature = "serde")]
+pub mod save;
+pub mod server;
+pub mod spatial;
+pub mod sprite;
+pub mod text;
+pub mod tilemap;
+pub mod tween;
+pub mod widget;
 
+#[cfg(test)]
+mod test_util;
+
+use capabilities::Capabilities;
 use event_group::*;
 use gfx::*;
+use random::Random;
 use rect::*;
 
 pub trait App {
@@ -27,27 +59,170 @@ pub trait App {
     fn draw(&self, gfx: &mut Gfx);
 }
 
-fn get_current_ms() -> u128 {
+pub(crate) fn get_current_ms() -> u128 {
     std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
         .unwrap()
         .as_millis()
 }
 
+/// Engine side of a frame: quits on Esc, follows resizes, then updates and draws the app. False
+/// once the app should stop.
+pub(crate) fn run_frame(app: &mut dyn App, gfx: &mut Gfx, events: &EventGroup) -> bool {
+    let mut keep_running = true;
+
+    for event in &events.events {
+        match event {
+            Event::Key(key_event) if key_event.code == KeyCode::Esc => keep_running = false,
+            Event::Resize(width, height) => gfx.resize(*width, *height),
+            _ => {}
+        }
+    }
+
+    if !app.update(events, gfx) {
+        keep_running = false;
+    }
+    gfx.assets.reload_changed();
+    app.draw(gfx);
+
+    gfx.apply_cursor();
+    gfx.flush_buffer();
+
+    keep_running
+}
+
+pub(crate) fn sleep_until_next_frame(frame_start_ms: u128, target_frame_length_ms: u128) {
+    let elapsed_ms = get_current_ms().saturating_sub(frame_start_ms);
+
+    if elapsed_ms < target_frame_length_ms {
+        std::thread::sleep(Duration::from_millis(
+            (target_frame_length_ms - elapsed_ms) as u64,
+        ));
+    }
+}
+
+/// Command line flags understood by the engine: `--seed <n>` starts the RNG from a given seed,
+/// `--print-seed` prints the seed in use on exit, `--record <path>` records the session into an
+/// asciicast file, `--ascii` draws without Unicode.
+#[derive(Debug, Default)]
+struct EngineArgs {
+    seed: Option<u64>,
+    print_seed: bool,
+    record: Option<PathBuf>,
+    ascii: bool,
+}
+
+impl EngineArgs {
+    /// Errors on a missing or invalid seed, falling back to a random one would make the run
+    /// impossible to reproduce.
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut out = Self::default();
+
+        while let Some(arg) = args.next() {
+            if arg == "--print-seed" {
+                out.print_seed = true;
+            } else if arg == "--ascii" {
+                out.ascii = true;
+            } else if arg == "--seed" {
+                out.seed = Some(parse_seed(args.next().as_deref())?);
+            } else if let Some(value) = arg.strip_prefix("--seed=") {
+                out.seed = Some(parse_seed(Some(value))?);
+            } else if arg == "--record" {
+                out.record = args.next().map(PathBuf::from);
+            } else if let Some(value) = arg.strip_prefix("--record=") {
+                out.record = Some(PathBuf::from(value));
+            }
+        }
+
+        Ok(out)
+    }
+}
+
+fn parse_seed(value: Option<&str>) -> Result<u64, String> {
+    let value = value.ok_or_else(|| "Missing value of --seed".to_string())?;
+    value
+        .parse()
+        .map_err(|_| format!("Invalid value of --seed: {:?}", value))
+}
+
 pub struct Terge {
     app: Box<dyn App>,
     gfx: Gfx,
     target_frame_length_ms: u128,
     should_terminate: bool,
+    print_seed: bool,
+    /// Started on `run`.
+    record_path: Option<PathBuf>,
+    /// Pressing the key starts or stops recording into the file.
+    record_hotkey: Option<(KeyCode, PathBuf)>,
+    /// Pressing the key saves the screen into the file.
+    capture_hotkey: Option<(KeyCode, PathBuf)>,
 }
 
 impl Terge {
     pub fn new(app: Box<dyn App>) -> Self {
+        let args = EngineArgs::parse(std::env::args().skip(1)).unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            std::process::exit(2);
+        });
+        let mut gfx = Gfx::new();
+        if let Some(seed) = args.seed {
+            gfx.rng = Random::new(seed);
+        }
+        gfx.capabilities = Capabilities::detect();
+        if args.ascii {
+            gfx.capabilities.unicode = false;
+        }
+
         Self {
             app,
-            gfx: Gfx::new(),
+            gfx,
             target_frame_length_ms: 16,
             should_terminate: false,
+            print_seed: args.print_seed,
+            record_path: args.record,
+            record_hotkey: None,
+            capture_hotkey: None,
+        }
+    }
+
+    /// What was detected about the terminal, apps can also read `gfx.capabilities`.
+    pub fn capabilities(&self) -> &Capabilities {
+        &self.gfx.capabilities
+    }
+
+    /// Overrides the detected capabilities, eg. to force ASCII output.
+    pub fn set_capabilities(&mut self, capabilities: Capabilities) {
+        self.gfx.capabilities = capabilities;
+    }
+
+    /// Overrides the seed given on the command line.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.gfx.rng.reseed(seed);
+    }
+
+    /// Records the session from the start into an asciicast v2 file, like `--record <path>`.
+    pub fn record_to(&mut self, path: impl Into<PathBuf>) {
+        self.record_path = Some(path.into());
+    }
+
+    /// The key toggles recording into the file, each recording overwrites the previous one. The
+    /// app gets the key press too.
+    pub fn set_record_hotkey(&mut self, key: KeyCode, path: impl Into<PathBuf>) {
+        self.record_hotkey = Some((key, path.into()));
+    }
+
+    /// The key saves the frame into the file, in the format of its extension (see
+    /// `capture::CaptureFormat`). Each capture overwrites the previous one.
+    pub fn set_capture_hotkey(&mut self, key: KeyCode, path: impl Into<PathBuf>) {
+        self.capture_hotkey = Some((key, path.into()));
+    }
+
+    fn toggle_recording(&mut self, path: &Path) {
+        if self.gfx.is_recording() {
+            self.gfx.stop_recording();
+        } else if let Err(err) = self.gfx.start_recording(path) {
+            warn!("Failed starting recording {:?}: {}", path, err);
         }
     }
 
@@ -56,28 +231,44 @@ impl Terge {
         io::stdout()
             .execute(cursor::Hide)
             .expect("Failed running crossterm commands");
+        if self.gfx.capabilities.mouse {
+            io::stdout()
+                .execute(event::EnableMouseCapture)
+                .expect("Failed enabling mouse capture");
+        }
         io::stdout()
-            .execute(event::EnableMouseCapture)
-            .expect("Failed enabling mouse capture");
+            .execute(event::EnableBracketedPaste)
+            .expect("Failed enabling bracketed paste");
     }
 
     fn turn_off_terminal_raw_mode(&self) {
         crossterm::terminal::disable_raw_mode().expect("Failed to disable raw mode");
+        io::stdout()
+            .execute(cursor::SetCursorStyle::DefaultUserShape)
+            .expect("Failed running crossterm commands");
         io::stdout()
             .execute(cursor::Show)
             .expect("Failed running crossterm commands");
+        if self.gfx.capabilities.mouse {
+            io::stdout()
+                .execute(event::DisableMouseCapture)
+                .expect("Failed enabling mouse capture");
+        }
         io::stdout()
-            .execute(event::DisableMouseCapture)
-            .expect("Failed enabling mouse capture");
+            .execute(event::DisableBracketedPaste)
+            .expect("Failed disabling bracketed paste");
     }
 
     pub fn run(&mut self) {
         self.gfx.refresh_state();
+        if let Some(path) = &self.record_path
+            && let Err(err) = self.gfx.start_recording(path)
+        {
+            warn!("Failed starting recording {:?}: {}", path, err);
+        }
         self.app.reset(&mut self.gfx);
         self.turn_on_terminal_raw_mode();
 
-        let mut frame_start_ms;
-
         let (ch_writer, ch_reader) = mpsc::channel::<Event>();
         let event_thread_should_finish = Arc::new(AtomicBool::new(false));
 
@@ -98,46 +289,29 @@ impl Terge {
         });
 
         let mut events = EventGroup::new();
+        events.current_ms = get_current_ms();
 
         while !self.should_terminate {
-            frame_start_ms = get_current_ms();
+            events.start_frame(ch_reader.try_iter().collect());
 
-            let mut new_events = vec![];
-            while let Ok(e) = ch_reader.try_recv() {
-                new_events.push(e);
-            }
-            events.events = new_events;
-
-            for event in &events.events {
-                match event {
-                    Event::Key(key_event) => {
-                        if key_event.code == KeyCode::Esc {
-                            self.should_terminate = true;
-                        }
-                    }
-                    Event::Resize(width, height) => {
-                        self.gfx.width = *width;
-                        self.gfx.height = *height;
-                    }
-                    _ => {}
-                }
+            if let Some((key, path)) = self.record_hotkey.clone()
+                && events.did_press_key(key)
+            {
+                self.toggle_recording(&path);
             }
 
-            if !self.app.update(&events, &mut self.gfx) {
+            if !run_frame(self.app.as_mut(), &mut self.gfx, &events) {
                 self.should_terminate = true;
             }
-            self.app.draw(&mut self.gfx);
-
-            self.gfx.flush_buffer();
-
-            let current_ms = get_current_ms();
-            let elapsed_ms = current_ms - frame_start_ms;
 
-            if elapsed_ms < self.target_frame_length_ms {
-                std::thread::sleep(Duration::from_millis(
-                    (self.target_frame_length_ms - elapsed_ms) as u64,
-                ));
+            if let Some((key, path)) = &self.capture_hotkey
+                && events.did_press_key(*key)
+                && let Err(err) = self.gfx.capture().save(path)
+            {
+                warn!("Failed saving capture {:?}: {}", path, err);
             }
+
+            sleep_until_next_frame(events.current_ms, self.target_frame_length_ms);
         }
 
         event_thread_should_finish.store(true, std::sync::atomic::Ordering::Release);
@@ -156,10 +330,30 @@ impl Terge {
 impl Drop for Terge {
     fn drop(&mut self) {
         self.turn_off_terminal_raw_mode();
+
+        if self.print_seed {
+            println!("Seed: {}", self.gfx.rng.seed());
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    // use super::*;
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<EngineArgs, String> {
+        EngineArgs::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn seed_flag() {
+        assert_eq!(parse(&["--seed", "42"]).unwrap().seed, Some(42));
+        assert_eq!(parse(&["--seed=7", "--ascii"]).unwrap().seed, Some(7));
+        assert_eq!(parse(&["--ascii"]).unwrap().seed, None);
+
+        assert!(parse(&["--seed", "abc"]).is_err());
+        assert!(parse(&["--seed="]).is_err());
+        assert!(parse(&["--seed"]).is_err());
+        assert!(parse(&["--seed=-1"]).is_err());
+    }
 }
@@ -12,13 +12,13 @@ pub struct LinePointsIterator {
 
 impl LinePointsIterator {
     pub fn new(lhs: U16Point, rhs: U16Point) -> Self {
-        let diff_x = (rhs.0 as i32 - lhs.0 as i32) as f32;
-        let diff_y = (rhs.1 as i32 - lhs.1 as i32) as f32;
+        let diff_x = (rhs.x as i32 - lhs.x as i32) as f32;
+        let diff_y = (rhs.y as i32 - lhs.y as i32) as f32;
 
         let i = if diff_x.abs() >= diff_y.abs() {
-            lhs.0
+            lhs.x
         } else {
-            lhs.1
+            lhs.y
         };
 
         Self {
@@ -37,13 +37,13 @@ impl Iterator for LinePointsIterator {
     fn next(&mut self) -> Option<Self::Item> {
         if self.diff_x.abs() >= self.diff_y.abs() {
             if self.diff_x != 0.0 {
-                if !between_u16_inclusive(self.lhs.0, self.rhs.0, self.i) {
+                if !between_u16_inclusive(self.lhs.x, self.rhs.x, self.i) {
                     return None;
                 }
 
                 let x = self.i;
 
-                if self.lhs.0 < self.rhs.0 {
+                if self.lhs.x < self.rhs.x {
                     self.i += 1;
                 } else {
                     if self.i == 0 {
@@ -52,20 +52,20 @@ impl Iterator for LinePointsIterator {
                     self.i -= 1;
                 }
 
-                let y = ((self.diff_y / self.diff_x) * (x as f32 - self.lhs.0 as f32)
-                    + self.lhs.1 as f32)
+                let y = ((self.diff_y / self.diff_x) * (x as f32 - self.lhs.x as f32)
+                    + self.lhs.y as f32)
                     .round() as u16;
 
-                return Some((x, y));
+                return Some(U16Point::new(x, y));
             }
         } else {
             if self.diff_y != 0.0 {
-                if !between_u16_inclusive(self.lhs.1, self.rhs.1, self.i) {
+                if !between_u16_inclusive(self.lhs.y, self.rhs.y, self.i) {
                     return None;
                 }
 
                 let y = self.i;
-                if self.lhs.1 < self.rhs.1 {
+                if self.lhs.y < self.rhs.y {
                     self.i += 1;
                 } else {
                     if self.i == 0 {
@@ -74,10 +74,10 @@ impl Iterator for LinePointsIterator {
                     self.i -= 1;
                 }
 
-                let x = ((self.diff_x / self.diff_y) * (y as f32 - self.lhs.1 as f32)
-                    + self.lhs.0 as f32)
+                let x = ((self.diff_x / self.diff_y) * (y as f32 - self.lhs.y as f32)
+                    + self.lhs.x as f32)
                     .round() as u16;
-                return Some((x, y));
+                return Some(U16Point::new(x, y));
             }
         }
 
@@ -86,24 +86,19 @@ impl Iterator for LinePointsIterator {
 }
 
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Line {
     pub start: U16Point,
     pub end: U16Point,
 }
 
 impl Line {
-    pub fn slope(&self) -> f32 {
-        let dx = self.end.0 as i32 - self.start.0 as i32;
-        let dy = self.end.1 as i32 - self.start.1 as i32;
-        dy as f32 / dx as f32
-    }
-
     pub fn x_range(&self) -> ops::RangeInclusive<u16> {
-        self.start.0.min(self.end.0)..=self.start.0.max(self.end.0)
+        self.start.x.min(self.end.x)..=self.start.x.max(self.end.x)
     }
 
     pub fn y_range(&self) -> ops::RangeInclusive<u16> {
-        self.start.1.min(self.end.1)..=self.start.1.max(self.end.1)
+        self.start.y.min(self.end.y)..=self.start.y.max(self.end.y)
     }
 
     pub fn is_point_on(&self, p: U16Point) -> bool {
@@ -0,0 +1,374 @@
+use crossterm::event::{Event, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
+
+use crate::common::*;
+use crate::rect::Rect;
+
+/// Cells the mouse can move while held and still count as a click.
+pub const MOUSE_DRAG_THRESHOLD: u16 = 0;
+/// Longest gap between clicks of a double or triple click.
+pub const MOUSE_MULTI_CLICK_MS: u128 = 400;
+
+/// A press and release without dragging.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Click {
+    pub button: MouseButton,
+    pub pos: U16Point,
+    /// 1 for a single click, 2 for a double click, 3 for a triple click and so on.
+    pub count: u8,
+    /// Modifiers held when the button was released.
+    pub modifiers: KeyModifiers,
+    /// Frame time of the release.
+    pub ms: u128,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Drag {
+    pub button: MouseButton,
+    /// Where the button went down.
+    pub start: U16Point,
+    pub current: U16Point,
+    /// Movement during the current frame.
+    pub delta: I32Point,
+    /// Modifiers held when the button went down.
+    pub modifiers: KeyModifiers,
+}
+
+impl Drag {
+    /// Movement since the button went down.
+    pub fn offset(&self) -> I32Point {
+        self.current.cast::<i32>() - self.start.cast()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Press {
+    button: MouseButton,
+    start: U16Point,
+    /// Position at the start of the frame, for the drag delta.
+    frame_start: U16Point,
+    modifiers: KeyModifiers,
+    dragging: bool,
+}
+
+/// Mouse state tracked by the engine across frames, see `EventGroup::mouse`. Everything is
+/// derived from the crossterm mouse events, so apps don't have to pair downs and ups themselves.
+///
+/// Terminals report one button at a time, a second press replaces the first one.
+#[derive(Debug, Clone)]
+pub struct MouseState {
+    /// Last known position, `None` until the first mouse event.
+    pub pos: Option<U16Point>,
+    /// Modifiers held during the last mouse event.
+    pub modifiers: KeyModifiers,
+    /// Moving further than this (in cells, either axis) while held turns a press into a drag.
+    pub drag_threshold: u16,
+    pub multi_click_ms: u128,
+    previous_pos: Option<U16Point>,
+    press: Option<Press>,
+    pressed: Vec<MouseButton>,
+    released: Vec<MouseButton>,
+    clicks: Vec<Click>,
+    last_click: Option<Click>,
+    drag_ended: Option<Drag>,
+    wheel: I32Point,
+}
+
+impl Default for MouseState {
+    fn default() -> Self {
+        Self {
+            pos: None,
+            modifiers: KeyModifiers::NONE,
+            drag_threshold: MOUSE_DRAG_THRESHOLD,
+            multi_click_ms: MOUSE_MULTI_CLICK_MS,
+            previous_pos: None,
+            press: None,
+            pressed: vec![],
+            released: vec![],
+            clicks: vec![],
+            last_click: None,
+            drag_ended: None,
+            wheel: I32Point::new(0, 0),
+        }
+    }
+}
+
+impl MouseState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Called once per frame with the events of the frame.
+    pub(crate) fn update(&mut self, events: &[Event], current_ms: u128) {
+        self.previous_pos = self.pos;
+        self.pressed.clear();
+        self.released.clear();
+        self.clicks.clear();
+        self.drag_ended = None;
+        self.wheel = I32Point::new(0, 0);
+        if let Some(press) = self.press.as_mut()
+            && let Some(pos) = self.pos
+        {
+            press.frame_start = pos;
+        }
+
+        for event in events {
+            if let Event::Mouse(mouse_event) = event {
+                self.on_mouse_event(mouse_event, current_ms);
+            }
+        }
+    }
+
+    fn on_mouse_event(&mut self, mouse_event: &MouseEvent, current_ms: u128) {
+        let pos = U16Point::new(mouse_event.column, mouse_event.row);
+        self.pos = Some(pos);
+        self.modifiers = mouse_event.modifiers;
+
+        match mouse_event.kind {
+            MouseEventKind::Down(button) => {
+                self.pressed.push(button);
+                self.press = Some(Press {
+                    button,
+                    start: pos,
+                    frame_start: pos,
+                    modifiers: mouse_event.modifiers,
+                    dragging: false,
+                });
+            }
+            MouseEventKind::Drag(_) | MouseEventKind::Moved => {
+                if let Some(press) = self.press.as_mut()
+                    && !press.dragging
+                    && distance(press.start, pos) > self.drag_threshold
+                {
+                    press.dragging = true;
+                }
+            }
+            MouseEventKind::Up(button) => {
+                self.released.push(button);
+                // Some terminals don't tell which button went up, so any release ends the press.
+                let Some(press) = self.press.take() else {
+                    return;
+                };
+
+                if press.dragging || distance(press.start, pos) > self.drag_threshold {
+                    self.drag_ended = Some(self.drag_of(&press, pos));
+                } else {
+                    self.on_click(press.button, pos, current_ms);
+                }
+            }
+            MouseEventKind::ScrollUp => self.wheel.y -= 1,
+            MouseEventKind::ScrollDown => self.wheel.y += 1,
+            MouseEventKind::ScrollLeft => self.wheel.x -= 1,
+            MouseEventKind::ScrollRight => self.wheel.x += 1,
+        }
+    }
+
+    fn on_click(&mut self, button: MouseButton, pos: U16Point, current_ms: u128) {
+        let count = match self.last_click {
+            Some(last)
+                if last.button == button
+                    && distance(last.pos, pos) <= self.drag_threshold
+                    && current_ms.saturating_sub(last.ms) <= self.multi_click_ms =>
+            {
+                last.count.saturating_add(1)
+            }
+            _ => 1,
+        };
+
+        let click = Click {
+            button,
+            pos,
+            count,
+            modifiers: self.modifiers,
+            ms: current_ms,
+        };
+        self.clicks.push(click);
+        self.last_click = Some(click);
+    }
+
+    fn drag_of(&self, press: &Press, current: U16Point) -> Drag {
+        Drag {
+            button: press.button,
+            start: press.start,
+            current,
+            delta: current.cast::<i32>() - press.frame_start.cast(),
+            modifiers: press.modifiers,
+        }
+    }
+
+    pub fn is_held(&self, button: MouseButton) -> bool {
+        self.press.is_some_and(|press| press.button == button)
+    }
+
+    /// The button went down this frame.
+    pub fn pressed(&self, button: MouseButton) -> bool {
+        self.pressed.contains(&button)
+    }
+
+    /// The button went up this frame, after a click or a drag.
+    pub fn released(&self, button: MouseButton) -> bool {
+        self.released.contains(&button)
+    }
+
+    /// The drag in progress, once the held mouse moved past the threshold.
+    pub fn drag(&self) -> Option<Drag> {
+        let press = self.press.filter(|press| press.dragging)?;
+        Some(self.drag_of(&press, self.pos?))
+    }
+
+    /// The drag that ended with a release this frame.
+    pub fn drag_ended(&self) -> Option<Drag> {
+        self.drag_ended
+    }
+
+    /// Clicks finished this frame, in order.
+    pub fn clicks(&self) -> &[Click] {
+        &self.clicks
+    }
+
+    /// Last click of the button this frame, whatever its count.
+    pub fn clicked(&self, button: MouseButton) -> Option<Click> {
+        self.clicks
+            .iter()
+            .rev()
+            .find(|click| click.button == button)
+            .copied()
+    }
+
+    pub fn double_clicked(&self, button: MouseButton) -> Option<Click> {
+        self.clicked(button).filter(|click| click.count == 2)
+    }
+
+    pub fn triple_clicked(&self, button: MouseButton) -> Option<Click> {
+        self.clicked(button).filter(|click| click.count == 3)
+    }
+
+    /// Wheel steps this frame. Positive `y` is scrolling down, positive `x` is scrolling right.
+    pub fn wheel(&self) -> I32Point {
+        self.wheel
+    }
+
+    pub fn is_hovering(&self, rect: &Rect) -> bool {
+        self.pos.is_some_and(|pos| rect.is_point_on(pos))
+    }
+
+    /// The mouse moved into the rect this frame.
+    pub fn entered(&self, rect: &Rect) -> bool {
+        self.is_hovering(rect) && !self.previous_pos.is_some_and(|pos| rect.is_point_on(pos))
+    }
+
+    /// The mouse moved out of the rect this frame.
+    pub fn left(&self, rect: &Rect) -> bool {
+        !self.is_hovering(rect) && self.previous_pos.is_some_and(|pos| rect.is_point_on(pos))
+    }
+}
+
+/// Chebyshev distance, in cells.
+fn distance(lhs: U16Point, rhs: U16Point) -> u16 {
+    lhs.x.abs_diff(rhs.x).max(lhs.y.abs_diff(rhs.y))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{click, mouse, rect_from_size};
+
+    #[test]
+    fn press_hold_and_release() {
+        let mut mouse_state = MouseState::new();
+        mouse_state.update(&[mouse(MouseEventKind::Down(MouseButton::Left), 3, 3)], 0);
+        assert!(mouse_state.pressed(MouseButton::Left));
+        assert!(mouse_state.is_held(MouseButton::Left));
+        assert!(!mouse_state.is_held(MouseButton::Right));
+
+        mouse_state.update(&[], 16);
+        assert!(!mouse_state.pressed(MouseButton::Left));
+        assert!(mouse_state.is_held(MouseButton::Left));
+
+        mouse_state.update(&[mouse(MouseEventKind::Up(MouseButton::Left), 3, 3)], 32);
+        assert!(mouse_state.released(MouseButton::Left));
+        assert!(!mouse_state.is_held(MouseButton::Left));
+        assert_eq!(mouse_state.clicked(MouseButton::Left).unwrap().count, 1);
+    }
+
+    #[test]
+    fn drag_past_threshold() {
+        let mut mouse_state = MouseState::new();
+        mouse_state.drag_threshold = 1;
+        mouse_state.update(
+            &[
+                mouse(MouseEventKind::Down(MouseButton::Left), 10, 10),
+                mouse(MouseEventKind::Drag(MouseButton::Left), 11, 10),
+            ],
+            0,
+        );
+        assert_eq!(mouse_state.drag(), None);
+
+        mouse_state.update(&[mouse(MouseEventKind::Drag(MouseButton::Left), 13, 9)], 16);
+        let drag = mouse_state.drag().unwrap();
+        assert_eq!(drag.start, U16Point::new(10, 10));
+        assert_eq!(drag.current, U16Point::new(13, 9));
+        assert_eq!(drag.delta, I32Point::new(2, -1));
+        assert_eq!(drag.offset(), I32Point::new(3, -1));
+
+        mouse_state.update(&[mouse(MouseEventKind::Up(MouseButton::Left), 14, 9)], 32);
+        assert_eq!(mouse_state.drag(), None);
+        assert_eq!(mouse_state.drag_ended().unwrap().delta, I32Point::new(1, 0));
+        assert!(mouse_state.clicks().is_empty());
+    }
+
+    #[test]
+    fn double_and_triple_clicks() {
+        let mut mouse_state = MouseState::new();
+        mouse_state.update(&click(5, 5), 0);
+        mouse_state.update(&click(5, 5), 200);
+        assert!(mouse_state.double_clicked(MouseButton::Left).is_some());
+
+        mouse_state.update(&click(5, 5), 400);
+        assert!(mouse_state.triple_clicked(MouseButton::Left).is_some());
+
+        // Too slow.
+        mouse_state.update(&click(5, 5), 1000);
+        assert_eq!(mouse_state.clicked(MouseButton::Left).unwrap().count, 1);
+
+        // Moved away.
+        mouse_state.update(&click(6, 5), 1100);
+        assert_eq!(mouse_state.clicked(MouseButton::Left).unwrap().count, 1);
+
+        // The clock stepped back.
+        mouse_state.update(&click(6, 5), 900);
+        assert!(mouse_state.double_clicked(MouseButton::Left).is_some());
+    }
+
+    #[test]
+    fn wheel_hover_and_modifiers() {
+        let area = rect_from_size(0, 0, 4, 4);
+        let mut mouse_state = MouseState::new();
+        mouse_state.update(
+            &[
+                mouse(MouseEventKind::ScrollDown, 2, 2),
+                mouse(MouseEventKind::ScrollDown, 2, 2),
+                mouse(MouseEventKind::ScrollLeft, 2, 2),
+            ],
+            0,
+        );
+        assert_eq!(mouse_state.wheel(), I32Point::new(-1, 2));
+        assert!(mouse_state.entered(&area));
+
+        mouse_state.update(&[], 16);
+        assert_eq!(mouse_state.wheel(), I32Point::new(0, 0));
+        assert!(mouse_state.is_hovering(&area));
+        assert!(!mouse_state.entered(&area));
+
+        mouse_state.update(
+            &[Event::Mouse(MouseEvent {
+                kind: MouseEventKind::Moved,
+                column: 9,
+                row: 2,
+                modifiers: KeyModifiers::SHIFT,
+            })],
+            32,
+        );
+        assert!(mouse_state.left(&area));
+        assert_eq!(mouse_state.modifiers, KeyModifiers::SHIFT);
+    }
+}
@@ -0,0 +1,268 @@
+use std::ops::Range;
+
+use rand::Rng;
+
+use crate::common::*;
+use crate::gfx::Gfx;
+
+#[derive(Debug, Clone)]
+pub struct Particle {
+    pub pos: F32Point,
+    pub v: F32Point,
+    pub age: u32,
+    pub lifetime: u32,
+}
+
+impl Particle {
+    /// 0.0 when spawned, 1.0 at the end of its life.
+    pub fn progress(&self) -> f32 {
+        self.age as f32 / self.lifetime.max(1) as f32
+    }
+}
+
+/// Everything describing how an emitter spawns, moves and draws particles. Time is measured in
+/// updates.
+#[derive(Debug, Clone)]
+pub struct ParticleConfig {
+    /// Particles spawned per update while emitting, fractions accumulate.
+    pub spawn_rate: f32,
+    pub lifetime: Range<u32>,
+    pub velocity_x: Range<f32>,
+    pub velocity_y: Range<f32>,
+    /// Added to the velocity every update.
+    pub gravity: F32Point,
+    /// Portion of the velocity lost every update.
+    pub drag: f32,
+    /// Glyphs stepped through evenly over the lifetime.
+    pub glyphs: Vec<&'static str>,
+    /// Colors stepped through evenly over the lifetime.
+    pub colors: Vec<u8>,
+    /// Particles leaving this area (start, end) are removed.
+    pub bounds: Option<(F32Point, F32Point)>,
+}
+
+impl Default for ParticleConfig {
+    fn default() -> Self {
+        Self {
+            spawn_rate: 0.0,
+            lifetime: 10..20,
+            velocity_x: -1.0..1.0,
+            velocity_y: -1.0..1.0,
+            gravity: F32Point::new(0.0, 0.0),
+            drag: 0.0,
+            glyphs: vec!["*"],
+            colors: vec![DEFAULT_COLOR_CODE],
+            bounds: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ParticleEmitter {
+    pub pos: F32Point,
+    pub config: ParticleConfig,
+    pub emitting: bool,
+    pub particles: Vec<Particle>,
+    spawn_accumulator: f32,
+}
+
+impl ParticleEmitter {
+    pub fn new(pos: F32Point, config: ParticleConfig) -> Self {
+        Self {
+            pos,
+            config,
+            emitting: false,
+            particles: vec![],
+            spawn_accumulator: 0.0,
+        }
+    }
+
+    pub fn burst(&mut self, count: usize, rng: &mut impl Rng) {
+        for _ in 0..count {
+            self.spawn(rng);
+        }
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.emitting || !self.particles.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.particles.clear();
+        self.spawn_accumulator = 0.0;
+    }
+
+    /// Spawns (when emitting), moves, ages and culls particles.
+    pub fn update(&mut self, rng: &mut impl Rng) {
+        if self.emitting {
+            self.spawn_accumulator += self.config.spawn_rate;
+            while self.spawn_accumulator >= 1.0 {
+                self.spawn_accumulator -= 1.0;
+                self.spawn(rng);
+            }
+        }
+
+        for particle in &mut self.particles {
+            particle.v = (particle.v + self.config.gravity) * (1.0 - self.config.drag);
+            particle.pos += particle.v;
+            particle.age += 1;
+        }
+
+        let bounds = self.config.bounds;
+        self.particles.retain(|particle| {
+            if particle.age >= particle.lifetime {
+                return false;
+            }
+
+            bounds
+                .map(|(start, end)| {
+                    particle.pos.x >= start.x
+                        && particle.pos.y >= start.y
+                        && particle.pos.x < end.x
+                        && particle.pos.y < end.y
+                })
+                .unwrap_or(true)
+        });
+    }
+
+    pub fn draw(&self, gfx: &Gfx) {
+        for particle in &self.particles {
+            if particle.pos.x < 0.0 || particle.pos.y < 0.0 {
+                continue;
+            }
+
+            let progress = particle.progress();
+            let glyph = pick_by_progress(&self.config.glyphs, progress);
+            let color = pick_by_progress(&self.config.colors, progress);
+
+            if let (Some(glyph), Some(color)) = (glyph, color) {
+                gfx.draw_text(glyph, particle.pos.x as u16, particle.pos.y as u16, *color);
+            }
+        }
+    }
+
+    fn spawn(&mut self, rng: &mut impl Rng) {
+        let config = &self.config;
+
+        self.particles.push(Particle {
+            pos: self.pos,
+            v: F32Point::new(
+                random_in_range(rng, &config.velocity_x),
+                random_in_range(rng, &config.velocity_y),
+            ),
+            age: 0,
+            lifetime: if config.lifetime.is_empty() {
+                config.lifetime.start
+            } else {
+                rng.random_range(config.lifetime.clone())
+            },
+        });
+    }
+}
+
+fn random_in_range(rng: &mut impl Rng, range: &Range<f32>) -> f32 {
+    if range.is_empty() {
+        range.start
+    } else {
+        rng.random_range(range.clone())
+    }
+}
+
+fn pick_by_progress<T>(items: &[T], progress: f32) -> Option<&T> {
+    if items.is_empty() {
+        return None;
+    }
+
+    let index = (progress * items.len() as f32) as usize;
+    items.get(index.min(items.len() - 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::random::Random;
+
+    fn emitter(config: ParticleConfig) -> ParticleEmitter {
+        ParticleEmitter::new(F32Point::new(0.0, 0.0), config)
+    }
+
+    #[test]
+    fn spawn_rate_accumulates_fractions() {
+        let mut rng = Random::new(1);
+        let mut emitter = emitter(ParticleConfig {
+            spawn_rate: 0.5,
+            lifetime: 100..101,
+            ..ParticleConfig::default()
+        });
+        emitter.emitting = true;
+
+        emitter.update(&mut rng);
+        assert_eq!(emitter.particles.len(), 0);
+        for _ in 0..9 {
+            emitter.update(&mut rng);
+        }
+        assert_eq!(emitter.particles.len(), 5);
+
+        emitter.emitting = false;
+        emitter.update(&mut rng);
+        assert_eq!(emitter.particles.len(), 5);
+    }
+
+    #[test]
+    fn particles_expire_after_their_lifetime() {
+        let mut rng = Random::new(2);
+        let mut emitter = emitter(ParticleConfig {
+            lifetime: 3..6,
+            ..ParticleConfig::default()
+        });
+        emitter.burst(20, &mut rng);
+
+        let mut lifetimes: Vec<u32> = emitter.particles.iter().map(|p| p.lifetime).collect();
+        lifetimes.sort();
+        assert!(lifetimes.iter().all(|lifetime| (3..6).contains(lifetime)));
+
+        for age in 1..=5 {
+            emitter.update(&mut rng);
+            let alive = lifetimes.iter().filter(|lifetime| **lifetime > age).count();
+            assert_eq!(emitter.particles.len(), alive);
+        }
+        assert!(!emitter.is_alive());
+    }
+
+    #[test]
+    fn particles_leaving_the_bounds_are_culled() {
+        let mut rng = Random::new(3);
+        let mut emitter = emitter(ParticleConfig {
+            lifetime: 100..101,
+            velocity_x: 1.0..1.0,
+            velocity_y: 0.0..0.0,
+            bounds: Some((F32Point::new(0.0, 0.0), F32Point::new(3.0, 1.0))),
+            ..ParticleConfig::default()
+        });
+        emitter.burst(2, &mut rng);
+
+        emitter.update(&mut rng);
+        emitter.update(&mut rng);
+        assert_eq!(emitter.particles.len(), 2);
+        assert_eq!(emitter.particles[0].pos, F32Point::new(2.0, 0.0));
+
+        emitter.update(&mut rng);
+        assert!(emitter.particles.is_empty());
+    }
+
+    #[test]
+    fn same_seed_same_particles() {
+        let spawn = |seed| {
+            let mut emitter = emitter(ParticleConfig::default());
+            emitter.burst(10, &mut Random::new(seed));
+            emitter
+                .particles
+                .iter()
+                .map(|p| (p.v, p.lifetime))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(spawn(4), spawn(4));
+        assert_ne!(spawn(4), spawn(5));
+    }
+}
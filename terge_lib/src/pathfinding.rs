@@ -0,0 +1,413 @@
+use std::cmp::Reverse;
+use std::collections::hash_map::Entry;
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+
+use crate::common::*;
+use crate::tilemap::TileMap;
+
+/// Cost of a horizontal or vertical step.
+pub const PATH_STRAIGHT_COST: u32 = 10;
+/// Cost of a diagonal step, roughly `PATH_STRAIGHT_COST * sqrt(2)`.
+pub const PATH_DIAGONAL_COST: u32 = 14;
+
+const DIRECTIONS: [(i32, i32); 8] = [
+    (0, -1),
+    (1, 0),
+    (0, 1),
+    (-1, 0),
+    (1, -1),
+    (1, 1),
+    (-1, 1),
+    (-1, -1),
+];
+/// Direction of the start state, before any step was taken.
+const NO_DIRECTION: usize = DIRECTIONS.len();
+
+/// Cell and the index of the direction it was entered from.
+type SearchState = (U16Point, usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Connectivity {
+    /// Horizontal and vertical neighbours.
+    Four,
+    /// Diagonal neighbours too.
+    Eight,
+}
+
+impl Connectivity {
+    fn directions(&self) -> &'static [(i32, i32)] {
+        match self {
+            Connectivity::Four => &DIRECTIONS[..4],
+            Connectivity::Eight => &DIRECTIONS,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Path {
+    /// From start to goal, both included.
+    pub points: Vec<U16Point>,
+    pub cost: u32,
+}
+
+/// Searches over a `size` large cell grid.
+///
+/// A step costs `PATH_STRAIGHT_COST` (or `PATH_DIAGONAL_COST`), plus the cost of the entered
+/// cell, plus `turn_penalty` when it changes direction. Diagonal steps can't cut the corner of an
+/// unwalkable cell.
+pub struct PathFinder<'a> {
+    pub size: U16Point,
+    pub connectivity: Connectivity,
+    pub turn_penalty: u32,
+    is_walkable: Box<dyn Fn(U16Point) -> bool + 'a>,
+    cell_cost: Box<dyn Fn(U16Point) -> u32 + 'a>,
+}
+
+impl<'a> PathFinder<'a> {
+    pub fn new(size: U16Point, is_walkable: impl Fn(U16Point) -> bool + 'a) -> Self {
+        Self {
+            size,
+            connectivity: Connectivity::Four,
+            turn_penalty: 0,
+            is_walkable: Box::new(is_walkable),
+            cell_cost: Box::new(|_| 0),
+        }
+    }
+
+    /// Non-solid tiles are walkable.
+    pub fn for_tile_map(map: &'a TileMap) -> Self {
+        Self::new(map.size(), |p| !map.is_solid(p.cast()))
+    }
+
+    pub fn with_connectivity(mut self, connectivity: Connectivity) -> Self {
+        self.connectivity = connectivity;
+        self
+    }
+
+    pub fn with_turn_penalty(mut self, turn_penalty: u32) -> Self {
+        self.turn_penalty = turn_penalty;
+        self
+    }
+
+    /// Extra cost of entering a cell, on top of the step cost.
+    pub fn with_cell_cost(mut self, cell_cost: impl Fn(U16Point) -> u32 + 'a) -> Self {
+        self.cell_cost = Box::new(cell_cost);
+        self
+    }
+
+    /// Cheapest path, guided towards the goal.
+    pub fn a_star(&self, start: U16Point, goal: U16Point) -> Option<Path> {
+        self.search(start, goal, |p| self.heuristic(p, goal))
+    }
+
+    /// Cheapest path, exploring evenly in all directions.
+    pub fn dijkstra(&self, start: U16Point, goal: U16Point) -> Option<Path> {
+        self.search(start, goal, |_| 0)
+    }
+
+    /// Path with the fewest steps, costs and turn penalty are ignored.
+    pub fn bfs(&self, start: U16Point, goal: U16Point) -> Option<Vec<U16Point>> {
+        if !self.is_open(start) || !self.is_open(goal) {
+            return None;
+        }
+
+        let mut came_from = HashMap::from([(start, start)]);
+        let mut queue = VecDeque::from([start]);
+
+        while let Some(p) = queue.pop_front() {
+            if p == goal {
+                return Some(walk_back(&came_from, goal));
+            }
+
+            for (next, _) in self.neighbours(p) {
+                if let Entry::Vacant(entry) = came_from.entry(next) {
+                    entry.insert(p);
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        None
+    }
+
+    fn search(
+        &self,
+        start: U16Point,
+        goal: U16Point,
+        heuristic: impl Fn(U16Point) -> u32,
+    ) -> Option<Path> {
+        if !self.is_open(start) || !self.is_open(goal) {
+            return None;
+        }
+
+        // States include the direction of the last step, turns cost differently.
+        let start_state = (start, NO_DIRECTION);
+        let mut costs = HashMap::from([(start_state, 0)]);
+        let mut came_from: HashMap<SearchState, SearchState> = HashMap::new();
+        // Heap of (estimated total cost, index into `queued`), the index also keeps the order
+        // stable between equally good states.
+        let mut queued = vec![start_state];
+        let mut open = BinaryHeap::from([Reverse((heuristic(start), 0))]);
+
+        while let Some(Reverse((_, index))) = open.pop() {
+            let state = queued[index];
+            let (p, direction) = state;
+            let cost = costs[&state];
+
+            if p == goal {
+                let mut points = vec![p];
+                let mut current = state;
+                while let Some(previous) = came_from.get(&current) {
+                    points.push(previous.0);
+                    current = *previous;
+                }
+                points.reverse();
+                return Some(Path { points, cost });
+            }
+
+            for (next, next_direction) in self.neighbours(p) {
+                let mut next_cost = cost + self.step_cost(next_direction) + (self.cell_cost)(next);
+                if direction != NO_DIRECTION && direction != next_direction {
+                    next_cost += self.turn_penalty;
+                }
+
+                let next_state = (next, next_direction);
+                if costs
+                    .get(&next_state)
+                    .is_some_and(|known_cost| *known_cost <= next_cost)
+                {
+                    continue;
+                }
+
+                costs.insert(next_state, next_cost);
+                came_from.insert(next_state, state);
+                open.push(Reverse((next_cost + heuristic(next), queued.len())));
+                queued.push(next_state);
+            }
+        }
+
+        None
+    }
+
+    fn is_open(&self, p: U16Point) -> bool {
+        p.x < self.size.x && p.y < self.size.y && (self.is_walkable)(p)
+    }
+
+    /// Walkable neighbours with the index of the direction they are in.
+    fn neighbours(&self, p: U16Point) -> impl Iterator<Item = (U16Point, usize)> + '_ {
+        self.connectivity
+            .directions()
+            .iter()
+            .enumerate()
+            .filter_map(move |(i, (dx, dy))| {
+                let next = self.offset(p, *dx, *dy)?;
+                if !self.is_open(next) {
+                    return None;
+                }
+
+                let is_diagonal = *dx != 0 && *dy != 0;
+                if is_diagonal
+                    && !(self
+                        .offset(p, *dx, 0)
+                        .is_some_and(|side| self.is_open(side))
+                        && self
+                            .offset(p, 0, *dy)
+                            .is_some_and(|side| self.is_open(side)))
+                {
+                    return None;
+                }
+
+                Some((next, i))
+            })
+    }
+
+    fn offset(&self, p: U16Point, dx: i32, dy: i32) -> Option<U16Point> {
+        (p.cast::<i32>() + I32Point::new(dx, dy)).try_cast()
+    }
+
+    fn step_cost(&self, direction: usize) -> u32 {
+        let (dx, dy) = DIRECTIONS[direction];
+        if dx != 0 && dy != 0 {
+            PATH_DIAGONAL_COST
+        } else {
+            PATH_STRAIGHT_COST
+        }
+    }
+
+    /// Cheapest possible cost ignoring walls, never overestimates.
+    fn heuristic(&self, p: U16Point, goal: U16Point) -> u32 {
+        let dx = p.x.abs_diff(goal.x) as u32;
+        let dy = p.y.abs_diff(goal.y) as u32;
+
+        match self.connectivity {
+            Connectivity::Four => (dx + dy) * PATH_STRAIGHT_COST,
+            Connectivity::Eight => {
+                dx.min(dy) * PATH_DIAGONAL_COST + dx.abs_diff(dy) * PATH_STRAIGHT_COST
+            }
+        }
+    }
+}
+
+/// Every cell reachable from `start` through fillable cells, `start` included if fillable.
+pub fn flood_fill(
+    start: U16Point,
+    size: U16Point,
+    connectivity: Connectivity,
+    is_fillable: impl Fn(U16Point) -> bool,
+) -> Vec<U16Point> {
+    let is_open = |p: U16Point| p.x < size.x && p.y < size.y && is_fillable(p);
+    if !is_open(start) {
+        return vec![];
+    }
+
+    let mut seen = HashSet::from([start]);
+    let mut out = vec![];
+    let mut queue = VecDeque::from([start]);
+
+    while let Some(p) = queue.pop_front() {
+        out.push(p);
+
+        for (dx, dy) in connectivity.directions() {
+            let Some(next) = (p.cast::<i32>() + I32Point::new(*dx, *dy)).try_cast::<u16>() else {
+                continue;
+            };
+
+            if is_open(next) && seen.insert(next) {
+                queue.push_back(next);
+            }
+        }
+    }
+
+    out
+}
+
+fn walk_back(came_from: &HashMap<U16Point, U16Point>, goal: U16Point) -> Vec<U16Point> {
+    let mut points = vec![goal];
+    let mut current = goal;
+
+    while let Some(previous) = came_from
+        .get(&current)
+        .filter(|previous| **previous != current)
+    {
+        points.push(*previous);
+        current = *previous;
+    }
+
+    points.reverse();
+    points
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `#` cells are walls.
+    fn grid(rows: &[&str]) -> (U16Point, HashSet<U16Point>) {
+        let mut walls = HashSet::new();
+        for (y, row) in rows.iter().enumerate() {
+            for (x, ch) in row.chars().enumerate() {
+                if ch == '#' {
+                    walls.insert(U16Point::new(x as u16, y as u16));
+                }
+            }
+        }
+        (
+            U16Point::new(rows[0].len() as u16, rows.len() as u16),
+            walls,
+        )
+    }
+
+    fn p(x: u16, y: u16) -> U16Point {
+        U16Point::new(x, y)
+    }
+
+    #[test]
+    fn a_star_goes_around_walls() {
+        let (size, walls) = grid(&[
+            ".....", //
+            ".###.", //
+            "...#.", //
+        ]);
+        let finder = PathFinder::new(size, |p| !walls.contains(&p));
+
+        let path = finder.a_star(p(0, 2), p(4, 2)).unwrap();
+        assert_eq!(path.points.first(), Some(&p(0, 2)));
+        assert_eq!(path.points.last(), Some(&p(4, 2)));
+        assert_eq!(path.points.len(), 9);
+        assert_eq!(path.cost, 8 * PATH_STRAIGHT_COST);
+
+        assert_eq!(finder.dijkstra(p(0, 2), p(4, 2)).unwrap().cost, path.cost);
+        assert_eq!(finder.bfs(p(0, 2), p(4, 2)).unwrap().len(), 9);
+    }
+
+    #[test]
+    fn unreachable_goal() {
+        let (size, walls) = grid(&[
+            "..#..", //
+            "..#..", //
+        ]);
+        let finder = PathFinder::new(size, |p| !walls.contains(&p));
+
+        assert_eq!(finder.a_star(p(0, 0), p(4, 0)), None);
+        assert_eq!(finder.bfs(p(0, 0), p(4, 0)), None);
+        assert_eq!(finder.a_star(p(0, 0), p(2, 0)), None);
+        assert_eq!(finder.a_star(p(0, 0), p(9, 0)), None);
+    }
+
+    #[test]
+    fn eight_connectivity_does_not_cut_corners() {
+        let (size, walls) = grid(&[
+            "...", //
+            ".#.", //
+            "...", //
+        ]);
+        let finder =
+            PathFinder::new(size, |p| !walls.contains(&p)).with_connectivity(Connectivity::Eight);
+
+        assert_eq!(
+            finder.a_star(p(0, 2), p(2, 2)).unwrap().points,
+            vec![p(0, 2), p(1, 2), p(2, 2)]
+        );
+        assert_eq!(
+            finder.a_star(p(0, 0), p(1, 0)).unwrap().cost,
+            PATH_STRAIGHT_COST
+        );
+
+        let open = PathFinder::new(size, |_| true).with_connectivity(Connectivity::Eight);
+        assert_eq!(
+            open.a_star(p(0, 0), p(2, 2)).unwrap().cost,
+            2 * PATH_DIAGONAL_COST
+        );
+    }
+
+    #[test]
+    fn cell_cost_and_turn_penalty() {
+        let size = p(4, 4);
+
+        // Expensive column forces a detour.
+        let finder = PathFinder::new(size, |_| true)
+            .with_cell_cost(|p| if p.x == 1 && p.y < 3 { 100 } else { 0 });
+        let path = finder.a_star(p(0, 0), p(2, 0)).unwrap();
+        assert!(path.points.contains(&p(1, 3)));
+
+        // With a turn penalty the path prefers a single turn.
+        let finder = PathFinder::new(size, |_| true).with_turn_penalty(5);
+        let path = finder.a_star(p(0, 0), p(3, 3)).unwrap();
+        assert_eq!(path.cost, 6 * PATH_STRAIGHT_COST + 5);
+    }
+
+    #[test]
+    fn flood_fill_stays_inside_walls() {
+        let (size, walls) = grid(&[
+            "..#..", //
+            "..#..", //
+            "###..", //
+        ]);
+
+        let mut filled = flood_fill(p(0, 0), size, Connectivity::Four, |p| !walls.contains(&p));
+        filled.sort_by_key(|p| (p.y, p.x));
+        assert_eq!(filled, vec![p(0, 0), p(1, 0), p(0, 1), p(1, 1)]);
+
+        assert!(flood_fill(p(2, 0), size, Connectivity::Four, |p| !walls.contains(&p)).is_empty());
+    }
+}
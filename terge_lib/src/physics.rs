@@ -0,0 +1,345 @@
+use crate::common::*;
+
+/// How many times a body may slide along surfaces within one step.
+const MAX_SLIDE_ITERATIONS: usize = 3;
+
+/// Axis aligned bounding box in world (float cell) coordinates.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    /// Upper-left corner.
+    pub pos: F32Point,
+    pub size: F32Point,
+}
+
+impl Aabb {
+    pub fn new(pos: F32Point, size: F32Point) -> Self {
+        Self { pos, size }
+    }
+
+    pub fn max(&self) -> F32Point {
+        self.pos + self.size
+    }
+
+    pub fn center(&self) -> F32Point {
+        self.pos + self.size / 2.0
+    }
+
+    pub fn translated(&self, delta: F32Point) -> Self {
+        Self::new(self.pos + delta, self.size)
+    }
+
+    /// Touching edges do not count as overlap.
+    pub fn overlaps(&self, other: &Aabb) -> bool {
+        let max = self.max();
+        let other_max = other.max();
+
+        self.pos.x < other_max.x
+            && other.pos.x < max.x
+            && self.pos.y < other_max.y
+            && other.pos.y < max.y
+    }
+
+    pub fn contains_point(&self, p: F32Point) -> bool {
+        let max = self.max();
+        p.x >= self.pos.x && p.x < max.x && p.y >= self.pos.y && p.y < max.y
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Contact {
+    /// Portion of the movement (0.0..=1.0) done before the hit.
+    pub time: f32,
+    /// Unit vector pointing away from the surface that was hit.
+    pub normal: F32Point,
+}
+
+/// Continuous collision of `moving` travelling by `v` against a still `target`. For two moving
+/// boxes pass the relative velocity. Boxes already overlapping collide at time 0, pushed out
+/// along the axis of least penetration.
+pub fn sweep_aabb(moving: &Aabb, v: F32Point, target: &Aabb) -> Option<Contact> {
+    if moving.overlaps(target) {
+        return Some(Contact {
+            time: 0.0,
+            normal: least_penetration_normal(moving, target),
+        });
+    }
+
+    // Shrink the moving box to a point by growing the target with its size, then cast a ray.
+    let expanded = Aabb::new(target.pos - moving.size, target.size + moving.size);
+    let (entry_x, exit_x) = slab(moving.pos.x, v.x, expanded.pos.x, expanded.max().x)?;
+    let (entry_y, exit_y) = slab(moving.pos.y, v.y, expanded.pos.y, expanded.max().y)?;
+
+    let entry = entry_x.max(entry_y);
+    let exit = exit_x.min(exit_y);
+
+    if entry >= exit || !(0.0..=1.0).contains(&entry) {
+        return None;
+    }
+
+    let normal = if entry_x > entry_y {
+        F32Point::new(-v.x.signum(), 0.0)
+    } else {
+        F32Point::new(0.0, -v.y.signum())
+    };
+
+    Some(Contact {
+        time: entry,
+        normal,
+    })
+}
+
+/// Entry and exit time of a point moving along one axis through the open interval (min, max).
+fn slab(origin: f32, v: f32, min: f32, max: f32) -> Option<(f32, f32)> {
+    if v == 0.0 {
+        if origin > min && origin < max {
+            Some((f32::NEG_INFINITY, f32::INFINITY))
+        } else {
+            None
+        }
+    } else {
+        let t1 = (min - origin) / v;
+        let t2 = (max - origin) / v;
+        Some((t1.min(t2), t1.max(t2)))
+    }
+}
+
+fn least_penetration_normal(moving: &Aabb, target: &Aabb) -> F32Point {
+    let max = moving.max();
+    let target_max = target.max();
+
+    let push_left = max.x - target.pos.x;
+    let push_right = target_max.x - moving.pos.x;
+    let push_up = max.y - target.pos.y;
+    let push_down = target_max.y - moving.pos.y;
+
+    let smallest = push_left.min(push_right).min(push_up).min(push_down);
+    if smallest == push_up {
+        F32Point::new(0.0, -1.0)
+    } else if smallest == push_down {
+        F32Point::new(0.0, 1.0)
+    } else if smallest == push_left {
+        F32Point::new(-1.0, 0.0)
+    } else {
+        F32Point::new(1.0, 0.0)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Body {
+    pub aabb: Aabb,
+    /// Cells per update.
+    pub v: F32Point,
+    /// Multiplier of the world gravity, 0.0 ignores it.
+    pub gravity_scale: f32,
+}
+
+impl Body {
+    pub fn new(aabb: Aabb) -> Self {
+        Self {
+            aabb,
+            v: F32Point::new(0.0, 0.0),
+            gravity_scale: 1.0,
+        }
+    }
+}
+
+/// Gravity and movement rules shared by all bodies.
+#[derive(Debug, Clone)]
+pub struct PhysicsWorld {
+    /// Added to the velocity of every body each update.
+    pub gravity: F32Point,
+    /// Speed limit on both axes.
+    pub max_speed: F32Point,
+}
+
+impl Default for PhysicsWorld {
+    fn default() -> Self {
+        Self {
+            gravity: F32Point::new(0.0, 0.1),
+            max_speed: F32Point::new(f32::INFINITY, f32::INFINITY),
+        }
+    }
+}
+
+impl PhysicsWorld {
+    pub fn new(gravity: F32Point, max_speed: F32Point) -> Self {
+        Self { gravity, max_speed }
+    }
+
+    pub fn apply_gravity(&self, body: &mut Body) {
+        let v = body.v + self.gravity * body.gravity_scale;
+        body.v = F32Point::new(
+            v.x.clamp(-self.max_speed.x, self.max_speed.x),
+            v.y.clamp(-self.max_speed.y, self.max_speed.y),
+        );
+    }
+
+    /// Applies gravity, then moves the body by its velocity. Hitting a collider stops the
+    /// movement into it while the rest of the movement slides along the surface. Returns the
+    /// contacts in the order they happened.
+    pub fn step(&self, body: &mut Body, colliders: &[Aabb]) -> Vec<Contact> {
+        self.apply_gravity(body);
+        move_and_slide(body, colliders)
+    }
+}
+
+/// Moves the body by its velocity without tunneling through any of the colliders.
+pub fn move_and_slide(body: &mut Body, colliders: &[Aabb]) -> Vec<Contact> {
+    let mut contacts = vec![];
+    let mut remaining = body.v;
+
+    for _ in 0..MAX_SLIDE_ITERATIONS {
+        if remaining == F32Point::new(0.0, 0.0) {
+            break;
+        }
+
+        let first_contact = colliders
+            .iter()
+            .filter_map(|collider| sweep_aabb(&body.aabb, remaining, collider))
+            // Sliding along a surface touches it, moving away from it is free.
+            .filter(|contact| contact.normal.dot(remaining) < 0.0)
+            .min_by(|lhs, rhs| lhs.time.total_cmp(&rhs.time));
+
+        let Some(contact) = first_contact else {
+            body.aabb = body.aabb.translated(remaining);
+            break;
+        };
+
+        body.aabb = body.aabb.translated(remaining * contact.time);
+        remaining = remaining * (1.0 - contact.time);
+
+        if contact.normal.x != 0.0 {
+            remaining.x = 0.0;
+            body.v.x = 0.0;
+        }
+        if contact.normal.y != 0.0 {
+            remaining.y = 0.0;
+            body.v.y = 0.0;
+        }
+
+        contacts.push(contact);
+    }
+
+    contacts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit_box(x: f32, y: f32) -> Aabb {
+        Aabb::new(F32Point::new(x, y), F32Point::new(1.0, 1.0))
+    }
+
+    #[test]
+    fn hits_walls_along_each_axis() {
+        let moving = unit_box(0.0, 0.0);
+        let cases = [
+            (
+                F32Point::new(5.0, 0.0),
+                unit_box(3.0, 0.0),
+                F32Point::new(-1.0, 0.0),
+            ),
+            (
+                F32Point::new(-5.0, 0.0),
+                unit_box(-3.0, 0.0),
+                F32Point::new(1.0, 0.0),
+            ),
+            (
+                F32Point::new(0.0, 5.0),
+                unit_box(0.0, 3.0),
+                F32Point::new(0.0, -1.0),
+            ),
+            (
+                F32Point::new(0.0, -5.0),
+                unit_box(0.0, -3.0),
+                F32Point::new(0.0, 1.0),
+            ),
+        ];
+
+        for (v, wall, normal) in cases {
+            assert_eq!(
+                sweep_aabb(&moving, v, &wall),
+                Some(Contact { time: 0.4, normal })
+            );
+        }
+
+        // Too short to reach, or passing by.
+        assert_eq!(
+            sweep_aabb(&moving, F32Point::new(1.0, 0.0), &unit_box(3.0, 0.0)),
+            None
+        );
+        assert_eq!(
+            sweep_aabb(&moving, F32Point::new(5.0, 0.0), &unit_box(3.0, 1.0)),
+            None
+        );
+    }
+
+    #[test]
+    fn hits_corners() {
+        let moving = unit_box(0.0, 0.0);
+
+        // Reaching both faces at once counts as landing on top.
+        let contact = sweep_aabb(&moving, F32Point::new(2.0, 2.0), &unit_box(2.0, 2.0)).unwrap();
+        assert_eq!(contact.time, 0.5);
+        assert_eq!(contact.normal, F32Point::new(0.0, -1.0));
+
+        // Moving diagonally into the side of a box.
+        let contact = sweep_aabb(&moving, F32Point::new(4.0, 2.0), &unit_box(2.0, 0.5)).unwrap();
+        assert_eq!(contact.time, 0.25);
+        assert_eq!(contact.normal, F32Point::new(-1.0, 0.0));
+
+        // Missing the corner diagonally.
+        assert_eq!(
+            sweep_aabb(&moving, F32Point::new(2.0, 2.0), &unit_box(3.0, 0.0)),
+            None
+        );
+    }
+
+    #[test]
+    fn does_not_tunnel_at_high_speed() {
+        let wall = Aabb::new(F32Point::new(50.0, -5.0), F32Point::new(0.1, 10.0));
+        let mut body = Body::new(unit_box(0.0, 0.0));
+        body.v = F32Point::new(100.0, 0.0);
+
+        let contacts = move_and_slide(&mut body, &[wall]);
+        assert_eq!(contacts.len(), 1);
+        assert_eq!(body.aabb.pos, F32Point::new(49.0, 0.0));
+        assert_eq!(body.v, F32Point::new(0.0, 0.0));
+    }
+
+    #[test]
+    fn rests_and_slides_on_the_ground() {
+        let world = PhysicsWorld::default();
+        let ground = Aabb::new(F32Point::new(-10.0, 10.0), F32Point::new(20.0, 1.0));
+        let mut body = Body::new(Aabb::new(F32Point::new(0.0, 8.0), F32Point::new(1.0, 2.0)));
+
+        for _ in 0..10 {
+            let contacts = world.step(&mut body, &[ground]);
+            assert_eq!(contacts[0].normal, F32Point::new(0.0, -1.0));
+            assert_eq!(body.aabb.pos, F32Point::new(0.0, 8.0));
+            assert_eq!(body.v, F32Point::new(0.0, 0.0));
+        }
+
+        body.v.x = 1.0;
+        world.step(&mut body, &[ground]);
+        assert_eq!(body.aabb.pos, F32Point::new(1.0, 8.0));
+        assert_eq!(body.v, F32Point::new(1.0, 0.0));
+    }
+
+    #[test]
+    fn overlapping_boxes_are_pushed_out_the_shortest_way() {
+        let contact = sweep_aabb(
+            &unit_box(0.0, 0.8),
+            F32Point::new(0.0, 0.0),
+            &Aabb::new(F32Point::new(-5.0, 1.0), F32Point::new(10.0, 5.0)),
+        );
+        assert_eq!(
+            contact,
+            Some(Contact {
+                time: 0.0,
+                normal: F32Point::new(0.0, -1.0)
+            })
+        );
+    }
+}
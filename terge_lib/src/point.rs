@@ -0,0 +1,294 @@
+use std::ops::{Add, AddAssign, Div, Mul, Neg, Sub, SubAssign};
+
+/// 2D point or vector. Arithmetic follows the component type: unsigned subtraction below zero
+/// panics in debug builds, use `checked_sub` or `saturating_sub` where that can happen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Point<T> {
+    pub x: T,
+    pub y: T,
+}
+
+/// Same type as `Point`, for places where the value is a direction or an offset.
+pub type Vec2<T> = Point<T>;
+
+impl<T> Point<T> {
+    pub const fn new(x: T, y: T) -> Self {
+        Self { x, y }
+    }
+
+    /// Lossless conversion, eg. `u16` to `i32` or `f32`.
+    pub fn cast<U: From<T>>(self) -> Point<U> {
+        Point::new(U::from(self.x), U::from(self.y))
+    }
+
+    /// `None` if any of the components does not fit the new type.
+    pub fn try_cast<U: TryFrom<T>>(self) -> Option<Point<U>> {
+        Some(Point::new(
+            U::try_from(self.x).ok()?,
+            U::try_from(self.y).ok()?,
+        ))
+    }
+}
+
+impl<T: Copy + Ord> Point<T> {
+    /// Component-wise minimum.
+    pub fn min(self, other: Self) -> Self {
+        Self::new(self.x.min(other.x), self.y.min(other.y))
+    }
+
+    /// Component-wise maximum.
+    pub fn max(self, other: Self) -> Self {
+        Self::new(self.x.max(other.x), self.y.max(other.y))
+    }
+}
+
+impl<T> From<(T, T)> for Point<T> {
+    fn from(p: (T, T)) -> Self {
+        Self::new(p.0, p.1)
+    }
+}
+
+impl<T> From<Point<T>> for (T, T) {
+    fn from(p: Point<T>) -> Self {
+        (p.x, p.y)
+    }
+}
+
+impl<T: Add<Output = T>> Add for Point<T> {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self::new(self.x + other.x, self.y + other.y)
+    }
+}
+
+impl<T: Sub<Output = T>> Sub for Point<T> {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        Self::new(self.x - other.x, self.y - other.y)
+    }
+}
+
+impl<T: AddAssign> AddAssign for Point<T> {
+    fn add_assign(&mut self, other: Self) {
+        self.x += other.x;
+        self.y += other.y;
+    }
+}
+
+impl<T: SubAssign> SubAssign for Point<T> {
+    fn sub_assign(&mut self, other: Self) {
+        self.x -= other.x;
+        self.y -= other.y;
+    }
+}
+
+impl<T: Mul<Output = T> + Copy> Mul<T> for Point<T> {
+    type Output = Self;
+
+    fn mul(self, scalar: T) -> Self {
+        Self::new(self.x * scalar, self.y * scalar)
+    }
+}
+
+impl<T: Div<Output = T> + Copy> Div<T> for Point<T> {
+    type Output = Self;
+
+    fn div(self, divisor: T) -> Self {
+        Self::new(self.x / divisor, self.y / divisor)
+    }
+}
+
+impl<T: Neg<Output = T>> Neg for Point<T> {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self::new(-self.x, -self.y)
+    }
+}
+
+macro_rules! impl_integer_point {
+    ($($t:ty),*) => {
+        $(
+            impl Point<$t> {
+                pub fn checked_add(self, other: Self) -> Option<Self> {
+                    Some(Self::new(self.x.checked_add(other.x)?, self.y.checked_add(other.y)?))
+                }
+
+                pub fn checked_sub(self, other: Self) -> Option<Self> {
+                    Some(Self::new(self.x.checked_sub(other.x)?, self.y.checked_sub(other.y)?))
+                }
+
+                pub fn saturating_add(self, other: Self) -> Self {
+                    Self::new(self.x.saturating_add(other.x), self.y.saturating_add(other.y))
+                }
+
+                pub fn saturating_sub(self, other: Self) -> Self {
+                    Self::new(self.x.saturating_sub(other.x), self.y.saturating_sub(other.y))
+                }
+
+                pub fn as_f32(self) -> Point<f32> {
+                    Point::new(self.x as f32, self.y as f32)
+                }
+            }
+        )*
+    };
+}
+
+impl_integer_point!(u16, i32, usize);
+
+impl Point<i32> {
+    /// Components are clamped into the `u16` range.
+    pub fn saturating_u16(self) -> Point<u16> {
+        Point::new(
+            self.x.clamp(0, u16::MAX as i32) as u16,
+            self.y.clamp(0, u16::MAX as i32) as u16,
+        )
+    }
+}
+
+impl Point<f32> {
+    /// Components are truncated and clamped into the `u16` range, NaN becomes 0.
+    pub fn saturating_u16(self) -> Point<u16> {
+        Point::new(self.x as u16, self.y as u16)
+    }
+
+    /// Components are truncated and clamped into the `i32` range, NaN becomes 0.
+    pub fn saturating_i32(self) -> Point<i32> {
+        Point::new(self.x as i32, self.y as i32)
+    }
+
+    pub fn round(self) -> Self {
+        Self::new(self.x.round(), self.y.round())
+    }
+
+    pub fn floor(self) -> Self {
+        Self::new(self.x.floor(), self.y.floor())
+    }
+
+    pub fn dot(self, other: Self) -> f32 {
+        self.x * other.x + self.y * other.y
+    }
+
+    /// Z component of the 3D cross product, 0.0 for parallel vectors.
+    pub fn cross(self, other: Self) -> f32 {
+        self.x * other.y - self.y * other.x
+    }
+
+    pub fn length_squared(self) -> f32 {
+        self.dot(self)
+    }
+
+    pub fn length(self) -> f32 {
+        self.length_squared().sqrt()
+    }
+
+    pub fn distance(self, other: Self) -> f32 {
+        (other - self).length()
+    }
+
+    /// Unit vector of the same direction, the zero vector stays zero.
+    pub fn normalize(self) -> Self {
+        let length = self.length();
+        if length == 0.0 { self } else { self / length }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn operators() {
+        let mut p = Point::new(3, -4);
+        assert_eq!(p + Point::new(1, 2), Point::new(4, -2));
+        assert_eq!(p - Point::new(1, 2), Point::new(2, -6));
+        assert_eq!(p * 2, Point::new(6, -8));
+        assert_eq!(p / 2, Point::new(1, -2));
+        assert_eq!(-p, Point::new(-3, 4));
+
+        p += Point::new(1, 1);
+        assert_eq!(p, Point::new(4, -3));
+        p -= Point::new(4, 4);
+        assert_eq!(p, Point::new(0, -7));
+
+        assert_eq!(Point::from((1, 2)), Point::new(1, 2));
+        assert_eq!(<(u16, u16)>::from(Point::new(1, 2)), (1, 2));
+    }
+
+    #[test]
+    fn casts() {
+        assert_eq!(Point::new(1u16, 2).cast::<i32>(), Point::new(1, 2));
+        assert_eq!(Point::new(1u16, 2).cast::<f32>(), Point::new(1.0, 2.0));
+        assert_eq!(
+            Point::new(1, 2).try_cast::<u16>(),
+            Some(Point::new(1u16, 2))
+        );
+        assert_eq!(Point::new(1, -2).try_cast::<u16>(), None);
+        assert_eq!(Point::new(70_000, 2).try_cast::<u16>(), None);
+        assert_eq!(Point::new(3u16, 4).as_f32(), Point::new(3.0, 4.0));
+    }
+
+    #[test]
+    fn min_and_max_are_component_wise() {
+        let a = Point::new(1, 5);
+        let b = Point::new(3, 2);
+        assert_eq!(a.min(b), Point::new(1, 2));
+        assert_eq!(a.max(b), Point::new(3, 5));
+    }
+
+    #[test]
+    fn saturating_and_checked_integer_arithmetic() {
+        let p = Point::new(1u16, u16::MAX - 1);
+        assert_eq!(
+            p.saturating_sub(Point::new(2, 0)),
+            Point::new(0, u16::MAX - 1)
+        );
+        assert_eq!(p.saturating_add(Point::new(0, 5)), Point::new(1, u16::MAX));
+        assert_eq!(p.checked_sub(Point::new(2, 0)), None);
+        assert_eq!(p.checked_add(Point::new(0, 5)), None);
+        assert_eq!(
+            p.checked_add(Point::new(1, 1)),
+            Some(Point::new(2, u16::MAX))
+        );
+
+        let p = Point::new(i32::MIN + 1, i32::MAX);
+        assert_eq!(
+            p.saturating_add(Point::new(-5, 5)),
+            Point::new(i32::MIN, i32::MAX)
+        );
+    }
+
+    #[test]
+    fn saturating_conversions() {
+        assert_eq!(
+            Point::new(-5, 70_000).saturating_u16(),
+            Point::new(0, u16::MAX)
+        );
+        assert_eq!(
+            Point::new(-1.5, 1e9).saturating_u16(),
+            Point::new(0, u16::MAX)
+        );
+        assert_eq!(Point::new(f32::NAN, 2.9).saturating_u16(), Point::new(0, 2));
+        assert_eq!(
+            Point::new(f32::NEG_INFINITY, -2.9).saturating_i32(),
+            Point::new(i32::MIN, -2)
+        );
+    }
+
+    #[test]
+    fn rounding_and_vector_math() {
+        assert_eq!(Point::new(1.5, -1.5).round(), Point::new(2.0, -2.0));
+        assert_eq!(Point::new(1.5, -1.5).floor(), Point::new(1.0, -2.0));
+
+        let v = Point::new(3.0, 4.0);
+        assert_eq!(v.length(), 5.0);
+        assert_eq!(v.dot(Point::new(1.0, 1.0)), 7.0);
+        assert_eq!(v.cross(v * 2.0), 0.0);
+        assert_eq!(v.distance(Point::new(0.0, 0.0)), 5.0);
+        assert_eq!(v.normalize(), Point::new(0.6, 0.8));
+        assert_eq!(Point::new(0.0, 0.0).normalize(), Point::new(0.0, 0.0));
+    }
+}
@@ -0,0 +1,172 @@
+use rand::RngCore;
+use rand::distr::uniform::{SampleRange, SampleUniform};
+
+/// Fast seedable PRNG (SplitMix64). The same seed always gives the same sequence, which makes
+/// runs reproducible.
+///
+/// Implements `rand::RngCore`, so it can be passed anywhere a `rand::Rng` is expected.
+#[derive(Debug, Clone)]
+pub struct Random {
+    seed: u64,
+    state: u64,
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        Self { seed, state: seed }
+    }
+
+    /// Seeded from the OS.
+    pub fn from_entropy() -> Self {
+        Self::new(rand::random())
+    }
+
+    /// The seed the sequence started from.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Starts the sequence of `seed` over.
+    pub fn reseed(&mut self, seed: u64) {
+        *self = Self::new(seed);
+    }
+
+    /// Uniform value in the range, eg. `0..10` or `-1.0..=1.0`. Panics on empty ranges.
+    pub fn range<T: SampleUniform, R: SampleRange<T>>(&mut self, range: R) -> T {
+        rand::Rng::random_range(self, range)
+    }
+
+    /// True with the given probability (0.0..=1.0).
+    pub fn chance(&mut self, probability: f64) -> bool {
+        self.unit_f64() < probability
+    }
+
+    /// `None` for empty slices.
+    pub fn choose<'a, T>(&mut self, items: &'a [T]) -> Option<&'a T> {
+        if items.is_empty() {
+            None
+        } else {
+            items.get(self.range(0..items.len()))
+        }
+    }
+
+    /// Picks an item with a probability proportional to its weight. `None` if there is nothing
+    /// with a positive weight.
+    pub fn choose_weighted<'a, T>(
+        &mut self,
+        items: &'a [T],
+        weight: impl Fn(&T) -> f64,
+    ) -> Option<&'a T> {
+        let total: f64 = items.iter().map(|item| weight(item).max(0.0)).sum();
+        if total <= 0.0 {
+            return None;
+        }
+
+        let mut target = self.unit_f64() * total;
+        let mut last_positive = None;
+        for item in items {
+            let item_weight = weight(item).max(0.0);
+            if item_weight <= 0.0 {
+                continue;
+            }
+
+            if target < item_weight {
+                return Some(item);
+            }
+            target -= item_weight;
+            last_positive = Some(item);
+        }
+
+        // Rounding can leave a tiny bit of the target over.
+        last_positive
+    }
+
+    /// Fisher-Yates shuffle.
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            items.swap(i, self.range(0..=i));
+        }
+    }
+
+    /// Uniform in 0.0..1.0.
+    fn unit_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+impl RngCore for Random {
+    fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(8) {
+            let bytes = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_same_sequence() {
+        let mut lhs = Random::new(42);
+        let mut rhs = Random::new(42);
+        let lhs_values: Vec<u32> = (0..10).map(|_| lhs.range(0..1000)).collect();
+        let rhs_values: Vec<u32> = (0..10).map(|_| rhs.range(0..1000)).collect();
+
+        assert_eq!(lhs_values, rhs_values);
+        assert_ne!(Random::new(43).next_u64(), Random::new(42).next_u64());
+    }
+
+    #[test]
+    fn reseed_starts_over() {
+        let mut rng = Random::new(7);
+        let first = rng.next_u64();
+        rng.next_u64();
+
+        rng.reseed(7);
+        assert_eq!(rng.next_u64(), first);
+        assert_eq!(rng.seed(), 7);
+    }
+
+    #[test]
+    fn choose_and_weighted_choice() {
+        let mut rng = Random::new(1);
+
+        assert_eq!(rng.choose::<u8>(&[]), None);
+        assert_eq!(rng.choose(&[5]), Some(&5));
+
+        let items = [("never", 0.0), ("always", 1.0)];
+        for _ in 0..20 {
+            assert_eq!(
+                rng.choose_weighted(&items, |item| item.1)
+                    .map(|item| item.0),
+                Some("always")
+            );
+        }
+        assert_eq!(rng.choose_weighted(&[("never", 0.0)], |item| item.1), None);
+    }
+
+    #[test]
+    fn shuffle_keeps_items() {
+        let mut rng = Random::new(3);
+        let mut items: Vec<u32> = (0..50).collect();
+        rng.shuffle(&mut items);
+
+        assert_ne!(items, (0..50).collect::<Vec<_>>());
+        items.sort();
+        assert_eq!(items, (0..50).collect::<Vec<_>>());
+    }
+}
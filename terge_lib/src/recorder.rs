@@ -0,0 +1,157 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::get_current_ms;
+
+/// Writes what goes to the terminal into an asciicast v2 file, playable with `asciinema play`
+/// or embedded with asciinema-player. See `Gfx::start_recording`.
+///
+/// Output is collected and written as one event per frame.
+pub struct Recorder {
+    out: Box<dyn Write>,
+    start_ms: u128,
+    pending: String,
+}
+
+impl Recorder {
+    pub fn create(path: impl AsRef<Path>, width: u16, height: u16) -> io::Result<Self> {
+        Self::new(Box::new(BufWriter::new(File::create(path)?)), width, height)
+    }
+
+    /// Writes the header with the terminal size at the start.
+    pub fn new(mut out: Box<dyn Write>, width: u16, height: u16) -> io::Result<Self> {
+        let start_ms = get_current_ms();
+        let env = match std::env::var("TERM") {
+            Ok(term) => format!(", \"env\": {{\"TERM\": {}}}", json_string(&term)),
+            Err(_) => String::new(),
+        };
+        writeln!(
+            out,
+            "{{\"version\": 2, \"width\": {}, \"height\": {}, \"timestamp\": {}{}}}",
+            width,
+            height,
+            start_ms / 1_000,
+            env
+        )?;
+
+        Ok(Self {
+            out,
+            start_ms,
+            pending: String::new(),
+        })
+    }
+
+    /// Collected until the next `flush`.
+    pub fn output(&mut self, text: &str) {
+        self.pending.push_str(text);
+    }
+
+    pub fn resize(&mut self, width: u16, height: u16) -> io::Result<()> {
+        self.flush()?;
+        self.event("r", &format!("{}x{}", width, height))
+    }
+
+    /// Writes the collected output as one event.
+    pub fn flush(&mut self) -> io::Result<()> {
+        if !self.pending.is_empty() {
+            let pending = std::mem::take(&mut self.pending);
+            self.event("o", &pending)?;
+        }
+        self.out.flush()
+    }
+
+    fn event(&mut self, code: &str, data: &str) -> io::Result<()> {
+        let elapsed_ms = get_current_ms().saturating_sub(self.start_ms);
+        writeln!(
+            self.out,
+            "[{}.{:03}, \"{}\", {}]",
+            elapsed_ms / 1_000,
+            elapsed_ms % 1_000,
+            code,
+            json_string(data)
+        )
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+fn json_string(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 2);
+    out.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 || c == '\x7F' => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::*;
+
+    #[derive(Clone, Default)]
+    struct Sink(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for Sink {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn lines(sink: &Sink) -> Vec<String> {
+        String::from_utf8(sink.0.borrow().clone())
+            .unwrap()
+            .lines()
+            .map(str::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn escapes_control_characters() {
+        assert_eq!(
+            json_string("a\"b\\c\n\x1B[0m"),
+            "\"a\\\"b\\\\c\\n\\u001b[0m\""
+        );
+        assert_eq!(json_string("█"), "\"█\"");
+    }
+
+    #[test]
+    fn header_output_and_resize_events() {
+        let sink = Sink::default();
+        let mut recorder = Recorder::new(Box::new(sink.clone()), 80, 24).unwrap();
+
+        recorder.output("\x1B[1;1H");
+        recorder.output("hi");
+        recorder.flush().unwrap();
+        // Nothing new, no event.
+        recorder.flush().unwrap();
+        recorder.resize(100, 30).unwrap();
+
+        let lines = lines(&sink);
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("{\"version\": 2, \"width\": 80, \"height\": 24, "));
+        assert!(lines[1].ends_with(", \"o\", \"\\u001b[1;1Hhi\"]"));
+        assert!(lines[2].ends_with(", \"r\", \"100x30\"]"));
+    }
+}
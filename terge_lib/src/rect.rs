@@ -1,5 +1,8 @@
 use crate::common::*;
 
+/// Area of cells from `start` to `end()`, both inclusive. A zero size rect is a single cell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Rect {
     // Start is always the upper-left corner (min-x:min-y).
     pub start: U16Point,
@@ -8,41 +11,274 @@ pub struct Rect {
 }
 
 impl Rect {
+    pub fn new(start: U16Point, size: U16Point) -> Self {
+        Self { start, size }
+    }
+
     pub fn new_from_unordered_points(lhs: U16Point, rhs: U16Point) -> Self {
-        let (min_x, min_y, max_x, max_y) = point_pair_minmax(lhs, rhs);
+        let (min, max) = point_pair_minmax(lhs, rhs);
         Self {
-            start: (min_x, min_y),
-            size: (max_x - min_x, max_y - min_y),
+            start: min,
+            size: max - min,
         }
     }
 
     pub fn is_point_on_header(&self, p: U16Point) -> bool {
-        p.1 == self.start.1 && p.0 >= self.start.0 && p.0 <= (self.start.0 + self.size.0)
+        p.y == self.start.y && p.x >= self.start.x && p.x <= self.end().x
     }
 
     pub fn is_point_on(&self, p: U16Point) -> bool {
-        p.0 >= self.start.0
-            && p.0 <= (self.start.0 + self.size.0)
-            && p.1 >= self.start.1
-            && p.1 <= (self.start.1 + self.size.1)
+        let end = self.end();
+        p.x >= self.start.x && p.x <= end.x && p.y >= self.start.y && p.y <= end.y
     }
 
+    /// Inside the border, rects narrower than 3 cells have no inside.
     pub fn is_point_inside(&self, p: U16Point) -> bool {
-        p.0 >= self.start.0 + 1
-            && p.0 <= (self.start.0 + self.size.0) - 1
-            && p.1 >= self.start.1 + 1
-            && p.1 <= (self.start.1 + self.size.1) - 1
+        let end = self.end();
+        p.x > self.start.x && p.x < end.x && p.y > self.start.y && p.y < end.y
     }
 
     pub fn midpoint(&self) -> U16Point {
-        self.start.add(self.size.div(2))
+        self.start.saturating_add(self.size / 2)
     }
 
+    /// Lower-right corner, clamped to the edge of the `u16` space.
     pub fn end(&self) -> U16Point {
-        self.start.add(self.size)
+        self.start.saturating_add(self.size)
+    }
+
+    /// Number of cells covered, `u64` as the whole `u16` space doesn't fit a `u32`.
+    pub fn area(&self) -> u64 {
+        self.width() as u64 * self.height() as u64
+    }
+
+    /// Number of cell columns covered.
+    pub fn width(&self) -> u32 {
+        (self.end().x - self.start.x) as u32 + 1
+    }
+
+    /// Number of cell rows covered.
+    pub fn height(&self) -> u32 {
+        (self.end().y - self.start.y) as u32 + 1
+    }
+
+    pub fn contains_rect(&self, other: &Rect) -> bool {
+        self.is_point_on(other.start) && self.is_point_on(other.end())
+    }
+
+    /// Sharing a border counts as overlap.
+    pub fn overlaps(&self, other: &Rect) -> bool {
+        self.intersection(other).is_some()
+    }
+
+    pub fn intersection(&self, other: &Rect) -> Option<Rect> {
+        let start = self.start.max(other.start);
+        let end = self.end().min(other.end());
+
+        if start.x > end.x || start.y > end.y {
+            return None;
+        }
+
+        Some(Rect::new(start, end - start))
+    }
+
+    /// Smallest rect containing both.
+    pub fn union(&self, other: &Rect) -> Rect {
+        let start = self.start.min(other.start);
+        let end = self.end().max(other.end());
+        Rect::new(start, end - start)
+    }
+
+    /// Shrinks every side by `amount`. `None` if nothing would be left.
+    pub fn inset(&self, amount: u16) -> Option<Rect> {
+        let start = self.start.checked_add(U16Point::new(amount, amount))?;
+        let end = self.end().checked_sub(U16Point::new(amount, amount))?;
+        let size = end.checked_sub(start)?;
+        Some(Rect::new(start, size))
+    }
+
+    /// Grows every side by `amount`, stopping at the edges of the `u16` space.
+    pub fn outset(&self, amount: u16) -> Rect {
+        let start = self.start.saturating_sub(U16Point::new(amount, amount));
+        let end = self.end().saturating_add(U16Point::new(amount, amount));
+        Rect::new(start, end - start)
+    }
+
+    /// Side by side parts, the left one `left_width` columns wide. `None` if either part would
+    /// be empty.
+    pub fn split_horizontal(&self, left_width: u16) -> Option<(Rect, Rect)> {
+        if left_width == 0 || left_width as u32 >= self.width() {
+            return None;
+        }
+
+        let end = self.end();
+        let left = Rect::new(
+            self.start,
+            U16Point::new(left_width - 1, end.y - self.start.y),
+        );
+        let right_start = U16Point::new(self.start.x + left_width, self.start.y);
+
+        Some((left, Rect::new(right_start, end - right_start)))
+    }
+
+    /// Stacked parts, the top one `top_height` rows tall. `None` if either part would be empty.
+    pub fn split_vertical(&self, top_height: u16) -> Option<(Rect, Rect)> {
+        if top_height == 0 || top_height as u32 >= self.height() {
+            return None;
+        }
+
+        let end = self.end();
+        let top = Rect::new(
+            self.start,
+            U16Point::new(end.x - self.start.x, top_height - 1),
+        );
+        let bottom_start = U16Point::new(self.start.x, self.start.y + top_height);
+
+        Some((top, Rect::new(bottom_start, end - bottom_start)))
+    }
+
+    /// Like `split_horizontal`, the left part gets `ratio` (0.0..=1.0) of the columns, rounded.
+    pub fn split_horizontal_ratio(&self, ratio: f32) -> Option<(Rect, Rect)> {
+        self.split_horizontal(ratio_of(self.width(), ratio))
+    }
+
+    /// Like `split_vertical`, the top part gets `ratio` (0.0..=1.0) of the rows, rounded.
+    pub fn split_vertical_ratio(&self, ratio: f32) -> Option<(Rect, Rect)> {
+        self.split_vertical(ratio_of(self.height(), ratio))
+    }
+}
+
+fn ratio_of(cells: u32, ratio: f32) -> u16 {
+    (cells as f32 * ratio.clamp(0.0, 1.0)).round() as u16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::rect_from_size;
+
+    #[test]
+    fn area_counts_cells() {
+        assert_eq!(rect_from_size(3, 4, 0, 0).area(), 1);
+        assert_eq!(rect_from_size(3, 4, 2, 1).area(), 6);
+        // Clamped to the edge like `end`.
+        assert_eq!(rect_from_size(u16::MAX - 1, 0, 5, 0).area(), 2);
+    }
+
+    #[test]
+    fn area_does_not_overflow() {
+        assert_eq!(
+            rect_from_size(0, 0, u16::MAX, u16::MAX).area(),
+            65536 * 65536
+        );
+    }
+
+    #[test]
+    fn end_saturates() {
+        assert_eq!(
+            rect_from_size(u16::MAX - 1, 10, 5, 5).end(),
+            U16Point::new(u16::MAX, 15)
+        );
+    }
+
+    #[test]
+    fn zero_size_rect_has_no_inside() {
+        let r = rect_from_size(0, 0, 0, 0);
+        assert!(r.is_point_on(U16Point::new(0, 0)));
+        assert!(!r.is_point_inside(U16Point::new(0, 0)));
+        assert!(!r.is_point_inside(U16Point::new(1, 1)));
+    }
+
+    #[test]
+    fn point_inside_excludes_border() {
+        let r = rect_from_size(2, 2, 4, 4);
+        assert!(r.is_point_inside(U16Point::new(3, 3)));
+        assert!(!r.is_point_inside(U16Point::new(2, 3)));
+        assert!(!r.is_point_inside(U16Point::new(6, 3)));
+    }
+
+    #[test]
+    fn intersection_and_overlap() {
+        let a = rect_from_size(0, 0, 10, 10);
+        let b = rect_from_size(5, 5, 10, 10);
+
+        assert_eq!(a.intersection(&b), Some(rect_from_size(5, 5, 5, 5)));
+        assert!(a.overlaps(&b));
+
+        // Shared border.
+        assert_eq!(
+            a.intersection(&rect_from_size(10, 0, 5, 5)),
+            Some(rect_from_size(10, 0, 0, 5))
+        );
+
+        assert_eq!(a.intersection(&rect_from_size(11, 0, 5, 5)), None);
+        assert!(!a.overlaps(&rect_from_size(0, 11, 5, 5)));
+    }
+
+    #[test]
+    fn union_spans_both() {
+        assert_eq!(
+            rect_from_size(1, 2, 3, 3).union(&rect_from_size(10, 0, 2, 2)),
+            rect_from_size(1, 0, 11, 5)
+        );
+    }
+
+    #[test]
+    fn contains_rect() {
+        let outer = rect_from_size(0, 0, 10, 10);
+        assert!(outer.contains_rect(&rect_from_size(2, 2, 3, 3)));
+        assert!(outer.contains_rect(&outer));
+        assert!(!outer.contains_rect(&rect_from_size(8, 8, 3, 3)));
+    }
+
+    #[test]
+    fn inset_and_outset() {
+        let r = rect_from_size(5, 5, 10, 6);
+
+        assert_eq!(r.inset(2), Some(rect_from_size(7, 7, 6, 2)));
+        assert_eq!(r.inset(3), Some(rect_from_size(8, 8, 4, 0)));
+        assert_eq!(r.inset(4), None);
+        assert_eq!(rect_from_size(u16::MAX, 0, 0, 0).inset(1), None);
+
+        assert_eq!(r.outset(2), rect_from_size(3, 3, 14, 10));
+        assert_eq!(r.outset(10), rect_from_size(0, 0, 25, 21));
+        assert_eq!(
+            rect_from_size(u16::MAX - 1, 0, 1, 1).outset(5),
+            rect_from_size(u16::MAX - 6, 0, 6, 6)
+        );
     }
 
-    pub fn area(&self) -> u16 {
-        self.size.0 * self.size.1
+    #[test]
+    fn split_by_size() {
+        let r = rect_from_size(0, 0, 9, 4);
+
+        let (left, right) = r.split_horizontal(3).unwrap();
+        assert_eq!(left, rect_from_size(0, 0, 2, 4));
+        assert_eq!(right, rect_from_size(3, 0, 6, 4));
+        assert_eq!(left.width() + right.width(), r.width());
+
+        let (top, bottom) = r.split_vertical(1).unwrap();
+        assert_eq!(top, rect_from_size(0, 0, 9, 0));
+        assert_eq!(bottom, rect_from_size(0, 1, 9, 3));
+
+        assert_eq!(r.split_horizontal(0), None);
+        assert_eq!(r.split_horizontal(10), None);
+        assert_eq!(r.split_vertical(5), None);
+    }
+
+    #[test]
+    fn split_by_ratio() {
+        let r = rect_from_size(0, 0, 9, 9);
+
+        let (left, right) = r.split_horizontal_ratio(0.5).unwrap();
+        assert_eq!(left, rect_from_size(0, 0, 4, 9));
+        assert_eq!(right, rect_from_size(5, 0, 4, 9));
+
+        let (top, bottom) = r.split_vertical_ratio(0.3).unwrap();
+        assert_eq!(top.height(), 3);
+        assert_eq!(bottom.height(), 7);
+
+        assert_eq!(r.split_horizontal_ratio(0.0), None);
+        assert_eq!(r.split_vertical_ratio(1.0), None);
     }
 }
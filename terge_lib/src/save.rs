@@ -0,0 +1,280 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+pub const SAVE_FILE_EXTENSION: &str = "json";
+
+type Migration = Box<dyn Fn(Value) -> io::Result<Value>>;
+
+/// Named save files of an app, each holding a state (anything `Serialize` and `Deserialize`)
+/// as JSON, next to the version of the format it was written with:
+///
+/// `{"version": 2, "state": ...}`
+///
+/// Files are replaced atomically, so a crash while saving leaves the previous save intact. When
+/// the state changes shape bump `version` and add a migration taking the JSON of the previous
+/// version to the next one, loading runs the ones needed to get an old save up to date.
+pub struct SaveSlots {
+    pub dir: PathBuf,
+    /// Version written into new saves, older ones are migrated when loaded.
+    pub version: u32,
+    /// Migration from the version to the next one.
+    migrations: Vec<(u32, Migration)>,
+}
+
+impl SaveSlots {
+    /// Slots in the `app_name` directory of the platform data directory (see `data_dir`).
+    pub fn new(app_name: &str, version: u32) -> io::Result<Self> {
+        let dir = data_dir().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                "No data directory on this platform",
+            )
+        })?;
+        Ok(Self::in_dir(dir.join(app_name), version))
+    }
+
+    pub fn in_dir(dir: impl Into<PathBuf>, version: u32) -> Self {
+        Self {
+            dir: dir.into(),
+            version,
+            migrations: vec![],
+        }
+    }
+
+    /// `migrate` turns the state of a `from` version save into the state of `from + 1`.
+    pub fn with_migration(
+        mut self,
+        from: u32,
+        migrate: impl Fn(Value) -> io::Result<Value> + 'static,
+    ) -> Self {
+        self.migrations.push((from, Box::new(migrate)));
+        self
+    }
+
+    /// Slot names are file names without the extension, made of letters, digits, `-` and `_`.
+    pub fn path(&self, slot: &str) -> io::Result<PathBuf> {
+        let valid = !slot.is_empty()
+            && slot
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !valid {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Invalid save slot name: {:?}", slot),
+            ));
+        }
+
+        Ok(self.dir.join(slot).with_extension(SAVE_FILE_EXTENSION))
+    }
+
+    pub fn exists(&self, slot: &str) -> bool {
+        self.path(slot).is_ok_and(|path| path.is_file())
+    }
+
+    /// Names of the saved slots, sorted.
+    pub fn slots(&self) -> io::Result<Vec<String>> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(err) => return Err(err),
+        };
+
+        let mut slots = vec![];
+        for entry in entries {
+            let path = entry?.path();
+            if path
+                .extension()
+                .is_some_and(|ext| ext == SAVE_FILE_EXTENSION)
+                && let Some(slot) = path.file_stem().and_then(|stem| stem.to_str())
+            {
+                slots.push(slot.to_string());
+            }
+        }
+        slots.sort();
+        Ok(slots)
+    }
+
+    pub fn save<T: Serialize>(&self, slot: &str, state: &T) -> io::Result<()> {
+        let file = serde_json::json!({
+            "version": self.version,
+            "state": serde_json::to_value(state)?,
+        });
+        write_atomic(&self.path(slot)?, &serde_json::to_vec_pretty(&file)?)
+    }
+
+    /// `None` when nothing was saved into the slot yet.
+    pub fn load<T: DeserializeOwned>(&self, slot: &str) -> io::Result<Option<T>> {
+        let content = match fs::read(self.path(slot)?) {
+            Ok(content) => content,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err),
+        };
+
+        let mut file: Value = serde_json::from_slice(&content)?;
+        let version = file
+            .get("version")
+            .and_then(Value::as_u64)
+            .and_then(|version| u32::try_from(version).ok())
+            .ok_or_else(|| invalid_data("Save file without a valid version".to_string()))?;
+        let state = file
+            .get_mut("state")
+            .map(Value::take)
+            .ok_or_else(|| invalid_data("Save file without state".to_string()))?;
+
+        let state = self.migrate(state, version)?;
+        Ok(Some(serde_json::from_value(state)?))
+    }
+
+    pub fn delete(&self, slot: &str) -> io::Result<()> {
+        match fs::remove_file(self.path(slot)?) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+            _ => Ok(()),
+        }
+    }
+
+    fn migrate(&self, mut state: Value, mut version: u32) -> io::Result<Value> {
+        if version > self.version {
+            return Err(invalid_data(format!(
+                "Save file version {} is newer than {}",
+                version, self.version
+            )));
+        }
+
+        while version < self.version {
+            let (_, migrate) = self
+                .migrations
+                .iter()
+                .find(|(from, _)| *from == version)
+                .ok_or_else(|| invalid_data(format!("No migration from version {}", version)))?;
+            state = migrate(state)?;
+            version += 1;
+        }
+
+        Ok(state)
+    }
+}
+
+/// Where apps keep their data: `$XDG_DATA_HOME` or `~/.local/share` on Linux,
+/// `~/Library/Application Support` on macOS and `%APPDATA%` on Windows.
+pub fn data_dir() -> Option<PathBuf> {
+    let env_dir = |name: &str| {
+        std::env::var_os(name)
+            .map(PathBuf::from)
+            .filter(|dir| dir.is_absolute())
+    };
+
+    if cfg!(windows) {
+        env_dir("APPDATA")
+    } else if cfg!(target_os = "macos") {
+        env_dir("HOME").map(|home| home.join("Library/Application Support"))
+    } else {
+        env_dir("XDG_DATA_HOME").or_else(|| env_dir("HOME").map(|home| home.join(".local/share")))
+    }
+}
+
+/// Writes into a temporary file next to `path` first and renames it over `path` once all is
+/// on disk, so readers see either the old or the new content. Creates the missing directories.
+pub fn write_atomic(path: &Path, content: &[u8]) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+    tmp_name.push(format!(".{}.tmp", std::process::id()));
+    let tmp_path = path.with_file_name(tmp_name);
+
+    let result = fs::File::create(&tmp_path).and_then(|mut file| {
+        file.write_all(content)?;
+        file.sync_all()
+    });
+    match result.and_then(|_| fs::rename(&tmp_path, path)) {
+        Ok(()) => Ok(()),
+        Err(err) => {
+            let _ = fs::remove_file(&tmp_path);
+            Err(err)
+        }
+    }
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+    use crate::common::U16Point;
+    use crate::rect::Rect;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct State {
+        name: String,
+        rect: Rect,
+    }
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("terge-save-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn save_and_load() {
+        let dir = test_dir("roundtrip");
+        let slots = SaveSlots::in_dir(&dir, 1);
+        let state = State {
+            name: "a".to_string(),
+            rect: Rect::new(U16Point::new(1, 2), U16Point::new(3, 4)),
+        };
+
+        assert_eq!(slots.load::<State>("slot-1").unwrap(), None);
+        slots.save("slot-1", &state).unwrap();
+        assert_eq!(slots.load::<State>("slot-1").unwrap(), Some(state));
+        assert_eq!(slots.slots().unwrap(), vec!["slot-1".to_string()]);
+        assert!(slots.save("../escape", &0).is_err());
+
+        slots.delete("slot-1").unwrap();
+        assert!(!slots.exists("slot-1"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn migrates_old_versions() {
+        let dir = test_dir("migrate");
+        SaveSlots::in_dir(&dir, 1)
+            .save("slot", &serde_json::json!({"title": "a"}))
+            .unwrap();
+
+        let slots = SaveSlots::in_dir(&dir, 3)
+            .with_migration(2, |mut state| {
+                state["rect"] = serde_json::to_value(Rect::default())?;
+                Ok(state)
+            })
+            .with_migration(1, |state| Ok(serde_json::json!({"name": state["title"]})));
+        assert_eq!(
+            slots.load::<State>("slot").unwrap(),
+            Some(State {
+                name: "a".to_string(),
+                rect: Rect::default(),
+            })
+        );
+
+        // Saves of a newer version can't be read.
+        slots.save("slot", &0).unwrap();
+        assert!(SaveSlots::in_dir(&dir, 2).load::<u32>("slot").is_err());
+
+        // Neither can versions out of the `u32` range, even if they would wrap to a known one.
+        let path = slots.path("slot").unwrap();
+        fs::write(&path, r#"{"version": 4294967299, "state": 0}"#).unwrap();
+        let err = slots.load::<u32>("slot").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
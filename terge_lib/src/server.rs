@@ -0,0 +1,837 @@
+use std::io::{self, BufWriter, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, mpsc};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crossterm::event::{
+    self, Event, KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+};
+use crossterm::{cursor, terminal};
+use log::{debug, warn};
+
+use crate::capabilities::Capabilities;
+use crate::event_group::EventGroup;
+use crate::gfx::Gfx;
+use crate::{App, get_current_ms, run_frame, sleep_until_next_frame};
+
+/// Size used until the client reports its window size.
+pub const SERVER_DEFAULT_WIDTH: u16 = 80;
+pub const SERVER_DEFAULT_HEIGHT: u16 = 24;
+/// How long a new session waits for the window size and terminal type before the app is reset.
+pub const SERVER_WINDOW_SIZE_WAIT_MS: u128 = 500;
+
+const TELNET_IAC: u8 = 255;
+const TELNET_DONT: u8 = 254;
+const TELNET_DO: u8 = 253;
+const TELNET_WONT: u8 = 252;
+const TELNET_WILL: u8 = 251;
+const TELNET_SB: u8 = 250;
+const TELNET_SE: u8 = 240;
+const TELNET_ECHO: u8 = 1;
+const TELNET_SUPPRESS_GO_AHEAD: u8 = 3;
+const TELNET_TERMINAL_TYPE: u8 = 24;
+const TELNET_NAWS: u8 = 31;
+const TELNET_TERMINAL_TYPE_IS: u8 = 0;
+const TELNET_TERMINAL_TYPE_SEND: u8 = 1;
+
+/// Character mode with the echo on our side (which never echoes), terminal type and window size
+/// reports.
+const TELNET_NEGOTIATION: [u8; 12] = [
+    TELNET_IAC,
+    TELNET_WILL,
+    TELNET_ECHO,
+    TELNET_IAC,
+    TELNET_WILL,
+    TELNET_SUPPRESS_GO_AHEAD,
+    TELNET_IAC,
+    TELNET_DO,
+    TELNET_TERMINAL_TYPE,
+    TELNET_IAC,
+    TELNET_DO,
+    TELNET_NAWS,
+];
+
+/// Asks a client which agreed to report its terminal type for it.
+const TELNET_TERMINAL_TYPE_REQUEST: [u8; 6] = [
+    TELNET_IAC,
+    TELNET_SB,
+    TELNET_TERMINAL_TYPE,
+    TELNET_TERMINAL_TYPE_SEND,
+    TELNET_IAC,
+    TELNET_SE,
+];
+
+type AppFactory = dyn Fn() -> Box<dyn App> + Send + Sync;
+
+/// Serves apps over TCP, eg. to `telnet host port`. Every connection gets its own app, made by
+/// the factory, with its own `Gfx` and input, running on its own thread.
+///
+/// Sessions start from the server's capabilities, colors and mouse support are then narrowed
+/// down to the terminal type the client reports.
+pub struct Server {
+    listener: TcpListener,
+    make_app: Arc<AppFactory>,
+    target_frame_length_ms: u128,
+    capabilities: Capabilities,
+}
+
+impl Server {
+    pub fn bind(
+        addr: impl ToSocketAddrs,
+        make_app: impl Fn() -> Box<dyn App> + Send + Sync + 'static,
+    ) -> io::Result<Self> {
+        Ok(Self {
+            listener: TcpListener::bind(addr)?,
+            make_app: Arc::new(make_app),
+            target_frame_length_ms: 16,
+            capabilities: Capabilities::default(),
+        })
+    }
+
+    /// Useful after binding to port 0.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    pub fn set_target_fps(&mut self, target_fps: u128) {
+        self.target_frame_length_ms = 1_000 / target_fps;
+    }
+
+    /// What sessions assume about the client's terminal, eg. no Unicode for ASCII only clients.
+    pub fn set_capabilities(&mut self, capabilities: Capabilities) {
+        self.capabilities = capabilities;
+    }
+
+    /// Accepts connections until the listener fails.
+    pub fn run(&self) -> io::Result<()> {
+        loop {
+            self.accept_one()?;
+        }
+    }
+
+    /// Serves the next connection only. The handle finishes when the session ends.
+    pub fn accept_one(&self) -> io::Result<JoinHandle<()>> {
+        let (stream, addr) = self.listener.accept()?;
+        debug!("Session started: {}", addr);
+
+        let make_app = self.make_app.clone();
+        let target_frame_length_ms = self.target_frame_length_ms;
+        let capabilities = self.capabilities;
+        Ok(thread::spawn(move || {
+            let result = run_session(
+                stream,
+                make_app.as_ref(),
+                target_frame_length_ms,
+                capabilities,
+            );
+            if let Err(err) = result {
+                warn!("Session {} failed: {}", addr, err);
+            }
+            debug!("Session ended: {}", addr);
+        }))
+    }
+}
+
+/// Socket side of a session's `Gfx`. A dropped connection only marks the session closed, so
+/// drawing never fails half way through a frame.
+struct SessionOutput {
+    stream: BufWriter<TcpStream>,
+    closed: Arc<AtomicBool>,
+}
+
+impl Write for SessionOutput {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if !self.closed.load(Ordering::Acquire) && self.stream.write_all(buf).is_err() {
+            self.closed.store(true, Ordering::Release);
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if !self.closed.load(Ordering::Acquire) && self.stream.flush().is_err() {
+            self.closed.store(true, Ordering::Release);
+        }
+        Ok(())
+    }
+}
+
+/// What the reader thread of a session passes on.
+enum SessionInput {
+    Event(Event),
+    /// `None` if the client won't tell.
+    TerminalType(Option<String>),
+}
+
+/// `base` with the colors and mouse support of the reported terminal type, the rest (eg.
+/// Unicode, which depends on the client's locale) can't be told from it.
+fn session_capabilities(base: Capabilities, terminal_type: &str) -> Capabilities {
+    let reported =
+        Capabilities::from_env(|name| (name == "TERM").then(|| terminal_type.to_string()));
+    Capabilities {
+        colors: base.colors.min(reported.colors),
+        mouse: base.mouse && reported.mouse,
+        ..base
+    }
+}
+
+fn run_session(
+    mut stream: TcpStream,
+    make_app: &AppFactory,
+    target_frame_length_ms: u128,
+    capabilities: Capabilities,
+) -> io::Result<()> {
+    stream.set_nodelay(true)?;
+    stream.write_all(&TELNET_NEGOTIATION)?;
+
+    let closed = Arc::new(AtomicBool::new(false));
+    let (ch_writer, ch_reader) = mpsc::channel::<SessionInput>();
+    let reader_thread = thread::spawn({
+        let mut stream = stream.try_clone()?;
+        let closed = closed.clone();
+
+        move || {
+            let mut decoder = TelnetDecoder::new();
+            let mut buf = [0; 1024];
+            loop {
+                match stream.read(&mut buf) {
+                    Ok(0) | Err(_) => break,
+                    Ok(n) => {
+                        let mut inputs: Vec<SessionInput> = decoder
+                            .feed(&buf[..n])
+                            .into_iter()
+                            .map(SessionInput::Event)
+                            .collect();
+                        inputs.extend(decoder.take_terminal_type().map(SessionInput::TerminalType));
+
+                        let replies = decoder.take_replies();
+                        if !replies.is_empty() && stream.write_all(&replies).is_err() {
+                            break;
+                        }
+                        for input in inputs {
+                            if ch_writer.send(input).is_err() {
+                                return;
+                            }
+                        }
+                    }
+                }
+            }
+            closed.store(true, Ordering::Release);
+        }
+    });
+
+    let mut gfx = Gfx::with_output(Box::new(SessionOutput {
+        stream: BufWriter::new(stream.try_clone()?),
+        closed: closed.clone(),
+    }));
+    gfx.width = SERVER_DEFAULT_WIDTH;
+    gfx.height = SERVER_DEFAULT_HEIGHT;
+    gfx.capabilities = capabilities;
+
+    // Most clients answer the NAWS and terminal type requests right away, the app should start
+    // with the real size and capabilities. A terminal type arriving later is ignored.
+    let mut early_events = vec![];
+    let mut terminal_type_known = false;
+    let wait_start = Instant::now();
+    while wait_start.elapsed().as_millis() < SERVER_WINDOW_SIZE_WAIT_MS {
+        match ch_reader.recv_timeout(Duration::from_millis(10)) {
+            Ok(SessionInput::Event(event)) => early_events.push(event),
+            Ok(SessionInput::TerminalType(terminal_type)) => {
+                debug!("Session terminal type: {:?}", terminal_type);
+                if let Some(terminal_type) = terminal_type {
+                    gfx.capabilities = session_capabilities(capabilities, &terminal_type);
+                }
+                terminal_type_known = true;
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+        }
+
+        let size_known = early_events
+            .iter()
+            .any(|event| matches!(event, Event::Resize(_, _)));
+        if size_known && terminal_type_known {
+            break;
+        }
+    }
+    for event in &early_events {
+        if let Event::Resize(width, height) = event {
+            gfx.width = *width;
+            gfx.height = *height;
+        }
+    }
+
+    gfx.queue(terminal::Clear(terminal::ClearType::All));
+    gfx.queue(cursor::Hide);
+    if gfx.capabilities.mouse {
+        gfx.queue(event::EnableMouseCapture);
+    }
+    gfx.queue(event::EnableBracketedPaste);
+
+    let mut app = make_app();
+    app.reset(&mut gfx);
+
+    let mut events = EventGroup::new();
+    events.current_ms = get_current_ms();
+    loop {
+        early_events.extend(ch_reader.try_iter().filter_map(|input| match input {
+            SessionInput::Event(event) => Some(event),
+            SessionInput::TerminalType(_) => None,
+        }));
+        events.start_frame(std::mem::take(&mut early_events));
+
+        if !run_frame(app.as_mut(), &mut gfx, &events) || closed.load(Ordering::Acquire) {
+            break;
+        }
+
+        sleep_until_next_frame(events.current_ms, target_frame_length_ms);
+    }
+
+    gfx.write_raw("\x1B[0m");
+    gfx.queue(event::DisableBracketedPaste);
+    if gfx.capabilities.mouse {
+        gfx.queue(event::DisableMouseCapture);
+    }
+    gfx.queue(cursor::SetCursorStyle::DefaultUserShape);
+    gfx.queue(cursor::Show);
+    gfx.queue(terminal::Clear(terminal::ClearType::All));
+    gfx.queue(cursor::MoveTo(0, 0));
+    gfx.flush_buffer();
+
+    // Unblocks the reader thread too.
+    let _ = stream.shutdown(Shutdown::Both);
+    drop(ch_reader);
+    let _ = reader_thread.join();
+
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TelnetState {
+    Data,
+    Iac,
+    /// After WILL, WONT, DO or DONT (kept), waiting for the option.
+    Option(u8),
+    Sub,
+    SubIac,
+}
+
+/// Splits the telnet stream into window size reports, the terminal type and terminal input, and
+/// turns the input into crossterm events.
+pub(crate) struct TelnetDecoder {
+    state: TelnetState,
+    sub: Vec<u8>,
+    input: Vec<u8>,
+    /// Enter arrives as CR LF or CR NUL, the second byte is dropped.
+    after_cr: bool,
+    /// To be sent to the client.
+    replies: Vec<u8>,
+    /// `Some(None)` once the client refused to report it.
+    terminal_type: Option<Option<String>>,
+}
+
+impl TelnetDecoder {
+    pub(crate) fn new() -> Self {
+        Self {
+            state: TelnetState::Data,
+            sub: vec![],
+            input: vec![],
+            after_cr: false,
+            replies: vec![],
+            terminal_type: None,
+        }
+    }
+
+    /// Answers to the client's option negotiation.
+    pub(crate) fn take_replies(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.replies)
+    }
+
+    /// The terminal type reported since the last call, `Some(None)` if the client won't tell.
+    pub(crate) fn take_terminal_type(&mut self) -> Option<Option<String>> {
+        self.terminal_type.take()
+    }
+
+    pub(crate) fn feed(&mut self, bytes: &[u8]) -> Vec<Event> {
+        let mut events = vec![];
+
+        for &byte in bytes {
+            self.state = match (self.state, byte) {
+                (TelnetState::Data, TELNET_IAC) => TelnetState::Iac,
+                (TelnetState::Data, _) => {
+                    self.input.push(byte);
+                    TelnetState::Data
+                }
+                // Escaped 255 data byte.
+                (TelnetState::Iac, TELNET_IAC) => {
+                    self.input.push(byte);
+                    TelnetState::Data
+                }
+                (TelnetState::Iac, TELNET_WILL | TELNET_WONT | TELNET_DO | TELNET_DONT) => {
+                    TelnetState::Option(byte)
+                }
+                (TelnetState::Option(TELNET_WILL), TELNET_TERMINAL_TYPE) => {
+                    self.replies.extend(TELNET_TERMINAL_TYPE_REQUEST);
+                    TelnetState::Data
+                }
+                (TelnetState::Option(TELNET_WONT), TELNET_TERMINAL_TYPE) => {
+                    self.terminal_type = Some(None);
+                    TelnetState::Data
+                }
+                (TelnetState::Iac, TELNET_SB) => {
+                    self.sub.clear();
+                    TelnetState::Sub
+                }
+                (TelnetState::Iac | TelnetState::Option(_), _) => TelnetState::Data,
+                (TelnetState::Sub, TELNET_IAC) => TelnetState::SubIac,
+                (TelnetState::Sub, _) => {
+                    self.sub.push(byte);
+                    TelnetState::Sub
+                }
+                (TelnetState::SubIac, TELNET_SE) => {
+                    if let [TELNET_NAWS, w0, w1, h0, h1] = self.sub[..] {
+                        // Keep the order of input and resize.
+                        self.decode_input(&mut events, false);
+                        events.push(Event::Resize(
+                            u16::from_be_bytes([w0, w1]),
+                            u16::from_be_bytes([h0, h1]),
+                        ));
+                    } else if let [TELNET_TERMINAL_TYPE, TELNET_TERMINAL_TYPE_IS, ref name @ ..] =
+                        self.sub[..]
+                    {
+                        let name = String::from_utf8_lossy(name).to_lowercase();
+                        self.terminal_type = Some(Some(name));
+                    }
+                    TelnetState::Data
+                }
+                (TelnetState::SubIac, _) => {
+                    self.sub.push(byte);
+                    TelnetState::Sub
+                }
+            };
+        }
+
+        self.decode_input(&mut events, true);
+        events
+    }
+
+    /// Decodes the complete sequences of the input. A lone escape at the end of the chunk is the
+    /// Esc key, other unfinished sequences wait for more bytes.
+    fn decode_input(&mut self, events: &mut Vec<Event>, end_of_chunk: bool) {
+        let mut i = 0;
+
+        while i < self.input.len() {
+            let rest = &self.input[i..];
+            let after_cr = std::mem::take(&mut self.after_cr);
+            if after_cr && matches!(rest[0], b'\n' | 0) {
+                i += 1;
+                continue;
+            }
+
+            let Some((event, len)) = decode_sequence(rest, end_of_chunk) else {
+                break;
+            };
+            self.after_cr = rest[0] == b'\r';
+            if let Some(event) = event {
+                events.push(event);
+            }
+            i += len;
+        }
+
+        self.input.drain(..i);
+    }
+}
+
+fn key(code: KeyCode, modifiers: KeyModifiers) -> Option<Event> {
+    Some(Event::Key(KeyEvent::new(code, modifiers)))
+}
+
+/// The event at the start of `bytes` and the bytes it took, `None` if more bytes are needed.
+fn decode_sequence(bytes: &[u8], end_of_chunk: bool) -> Option<(Option<Event>, usize)> {
+    match bytes[0] {
+        0x1B => match bytes.get(1) {
+            None if end_of_chunk => Some((key(KeyCode::Esc, KeyModifiers::NONE), 1)),
+            None => None,
+            Some(b'[') => decode_csi(bytes),
+            Some(b'O') => {
+                let code = match bytes.get(2)? {
+                    b'A' => KeyCode::Up,
+                    b'B' => KeyCode::Down,
+                    b'C' => KeyCode::Right,
+                    b'D' => KeyCode::Left,
+                    b'H' => KeyCode::Home,
+                    b'F' => KeyCode::End,
+                    b @ b'P'..=b'S' => KeyCode::F(b - b'P' + 1),
+                    _ => return Some((None, 3)),
+                };
+                Some((key(code, KeyModifiers::NONE), 3))
+            }
+            Some(_) => {
+                let (event, len) = decode_sequence(&bytes[1..], end_of_chunk)?;
+                let event = match event {
+                    Some(Event::Key(key_event)) => key(key_event.code, KeyModifiers::ALT),
+                    event => event,
+                };
+                Some((event, len + 1))
+            }
+        },
+        b'\r' | b'\n' => Some((key(KeyCode::Enter, KeyModifiers::NONE), 1)),
+        b'\t' => Some((key(KeyCode::Tab, KeyModifiers::NONE), 1)),
+        0x7F | 0x08 => Some((key(KeyCode::Backspace, KeyModifiers::NONE), 1)),
+        b @ 0x01..=0x1A => Some((
+            key(KeyCode::Char((b'a' + b - 1) as char), KeyModifiers::CONTROL),
+            1,
+        )),
+        0x00..=0x1F => Some((None, 1)),
+        b => {
+            let len = match b {
+                0xC0..=0xDF => 2,
+                0xE0..=0xEF => 3,
+                0xF0..=0xF7 => 4,
+                0x80.. => return Some((None, 1)),
+                _ => 1,
+            };
+            if bytes.len() < len {
+                return if end_of_chunk {
+                    Some((None, bytes.len()))
+                } else {
+                    None
+                };
+            }
+
+            match std::str::from_utf8(&bytes[..len]) {
+                Ok(s) => {
+                    let c = s.chars().next().unwrap();
+                    Some((key(KeyCode::Char(c), KeyModifiers::NONE), len))
+                }
+                Err(_) => Some((None, 1)),
+            }
+        }
+    }
+}
+
+const SERVER_PASTE_START: &[u8] = b"\x1B[200~";
+const SERVER_PASTE_END: &[u8] = b"\x1B[201~";
+
+/// `bytes` starts with ESC [.
+fn decode_csi(bytes: &[u8]) -> Option<(Option<Event>, usize)> {
+    if bytes.starts_with(SERVER_PASTE_START) {
+        let content = &bytes[SERVER_PASTE_START.len()..];
+        let end = content
+            .windows(SERVER_PASTE_END.len())
+            .position(|window| window == SERVER_PASTE_END)?;
+        let text = String::from_utf8_lossy(&content[..end]).into_owned();
+        return Some((
+            Some(Event::Paste(text)),
+            SERVER_PASTE_START.len() + end + SERVER_PASTE_END.len(),
+        ));
+    }
+
+    let final_i = 2 + bytes[2..].iter().position(|b| (0x40..=0x7E).contains(b))?;
+    let len = final_i + 1;
+    let params = std::str::from_utf8(&bytes[2..final_i]).unwrap_or("");
+    let final_byte = bytes[final_i];
+
+    if let Some(params) = params.strip_prefix('<') {
+        return Some((decode_sgr_mouse(params, final_byte), len));
+    }
+
+    let numbers: Vec<u16> = params
+        .split(';')
+        .map(|param| param.parse().unwrap_or(1))
+        .collect();
+    // `CSI 1;5A` is Ctrl+Up, the modifier is one more than the bit mask.
+    let modifiers = numbers
+        .get(1)
+        .map(|modifier| modifier_bits(modifier.saturating_sub(1)))
+        .unwrap_or(KeyModifiers::NONE);
+
+    let code = match final_byte {
+        b'A' => KeyCode::Up,
+        b'B' => KeyCode::Down,
+        b'C' => KeyCode::Right,
+        b'D' => KeyCode::Left,
+        b'H' => KeyCode::Home,
+        b'F' => KeyCode::End,
+        b'Z' => return Some((key(KeyCode::BackTab, KeyModifiers::SHIFT), len)),
+        b @ b'P'..=b'S' => KeyCode::F(b - b'P' + 1),
+        b'~' => match numbers[0] {
+            1 | 7 => KeyCode::Home,
+            2 => KeyCode::Insert,
+            3 => KeyCode::Delete,
+            4 | 8 => KeyCode::End,
+            5 => KeyCode::PageUp,
+            6 => KeyCode::PageDown,
+            n @ 11..=15 => KeyCode::F((n - 10) as u8),
+            n @ 17..=21 => KeyCode::F((n - 11) as u8),
+            n @ 23..=24 => KeyCode::F((n - 12) as u8),
+            _ => return Some((None, len)),
+        },
+        _ => return Some((None, len)),
+    };
+
+    Some((key(code, modifiers), len))
+}
+
+fn modifier_bits(bits: u16) -> KeyModifiers {
+    let mut modifiers = KeyModifiers::NONE;
+    if bits & 1 != 0 {
+        modifiers |= KeyModifiers::SHIFT;
+    }
+    if bits & 2 != 0 {
+        modifiers |= KeyModifiers::ALT;
+    }
+    if bits & 4 != 0 {
+        modifiers |= KeyModifiers::CONTROL;
+    }
+    modifiers
+}
+
+/// `CSI < button ; column ; row M` (press) or `m` (release), 1-based coordinates.
+fn decode_sgr_mouse(params: &str, final_byte: u8) -> Option<Event> {
+    let numbers: Vec<u16> = params
+        .split(';')
+        .map(|param| param.parse().ok())
+        .collect::<Option<_>>()?;
+    let [code, column, row] = numbers[..] else {
+        return None;
+    };
+
+    let button = match code & 0b11 {
+        0 => Some(MouseButton::Left),
+        1 => Some(MouseButton::Middle),
+        2 => Some(MouseButton::Right),
+        _ => None,
+    };
+    let kind = if code & 64 != 0 {
+        match code & 0b11 {
+            0 => MouseEventKind::ScrollUp,
+            1 => MouseEventKind::ScrollDown,
+            2 => MouseEventKind::ScrollLeft,
+            _ => MouseEventKind::ScrollRight,
+        }
+    } else if code & 32 != 0 {
+        button.map_or(MouseEventKind::Moved, MouseEventKind::Drag)
+    } else if final_byte == b'm' {
+        MouseEventKind::Up(button.unwrap_or(MouseButton::Left))
+    } else {
+        MouseEventKind::Down(button?)
+    };
+
+    Some(Event::Mouse(MouseEvent {
+        kind,
+        column: column.saturating_sub(1),
+        row: row.saturating_sub(1),
+        modifiers: modifier_bits((code >> 2) & 0b111),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capabilities::ColorSupport;
+
+    fn char_key(c: char) -> Event {
+        Event::Key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE))
+    }
+
+    #[test]
+    fn keys_and_escape_sequences() {
+        let mut decoder = TelnetDecoder::new();
+
+        assert_eq!(
+            decoder.feed("aé\r\0\x1B[A\x1B[1;5C\x1B[3~\x7F\x03".as_bytes()),
+            vec![
+                char_key('a'),
+                char_key('é'),
+                Event::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)),
+                Event::Key(KeyEvent::new(KeyCode::Up, KeyModifiers::NONE)),
+                Event::Key(KeyEvent::new(KeyCode::Right, KeyModifiers::CONTROL)),
+                Event::Key(KeyEvent::new(KeyCode::Delete, KeyModifiers::NONE)),
+                Event::Key(KeyEvent::new(KeyCode::Backspace, KeyModifiers::NONE)),
+                Event::Key(KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL)),
+            ]
+        );
+        assert_eq!(
+            decoder.feed(b"\x1B"),
+            vec![Event::Key(KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE))]
+        );
+    }
+
+    #[test]
+    fn split_sequences_wait_for_the_rest() {
+        let mut decoder = TelnetDecoder::new();
+
+        assert_eq!(decoder.feed(b"\x1B[20"), vec![]);
+        assert_eq!(decoder.feed(b"0~hi\x1B[2"), vec![]);
+        assert_eq!(decoder.feed(b"01~"), vec![Event::Paste("hi".to_string())]);
+    }
+
+    #[test]
+    fn telnet_window_size_and_options() {
+        let mut decoder = TelnetDecoder::new();
+
+        assert_eq!(
+            decoder.feed(&[
+                b'x',
+                TELNET_IAC,
+                TELNET_WILL,
+                TELNET_NAWS,
+                TELNET_IAC,
+                TELNET_SB,
+                TELNET_NAWS,
+                0,
+                120,
+                0,
+                40,
+                TELNET_IAC,
+                TELNET_SE,
+                b'y',
+            ]),
+            vec![char_key('x'), Event::Resize(120, 40), char_key('y')]
+        );
+    }
+
+    #[test]
+    fn telnet_terminal_type() {
+        let mut decoder = TelnetDecoder::new();
+
+        decoder.feed(&[TELNET_IAC, TELNET_WILL, TELNET_TERMINAL_TYPE]);
+        assert_eq!(decoder.take_replies(), TELNET_TERMINAL_TYPE_REQUEST);
+        assert_eq!(decoder.take_terminal_type(), None);
+
+        let mut report = vec![
+            TELNET_IAC,
+            TELNET_SB,
+            TELNET_TERMINAL_TYPE,
+            TELNET_TERMINAL_TYPE_IS,
+        ];
+        report.extend(b"XTERM-256COLOR");
+        report.extend([TELNET_IAC, TELNET_SE]);
+        assert_eq!(decoder.feed(&report), vec![]);
+        assert_eq!(
+            decoder.take_terminal_type(),
+            Some(Some("xterm-256color".to_string()))
+        );
+
+        decoder.feed(&[TELNET_IAC, TELNET_WONT, TELNET_TERMINAL_TYPE]);
+        assert_eq!(decoder.take_terminal_type(), Some(None));
+        assert!(decoder.take_replies().is_empty());
+    }
+
+    #[test]
+    fn capabilities_of_the_terminal_type() {
+        let base = Capabilities {
+            unicode: false,
+            ..Capabilities::default()
+        };
+
+        let xterm = session_capabilities(base, "xterm-256color");
+        assert_eq!(xterm.colors, ColorSupport::Indexed256);
+        assert!(xterm.mouse);
+        assert!(!xterm.unicode);
+
+        let vt100 = session_capabilities(Capabilities::default(), "vt100");
+        assert_eq!(vt100.colors, ColorSupport::Monochrome);
+        assert!(!vt100.mouse);
+        assert!(vt100.unicode);
+
+        let limited = Capabilities {
+            colors: ColorSupport::Ansi8,
+            ..Capabilities::default()
+        };
+        assert_eq!(
+            session_capabilities(limited, "xterm-256color").colors,
+            ColorSupport::Ansi8
+        );
+    }
+
+    #[test]
+    fn sgr_mouse() {
+        let mut decoder = TelnetDecoder::new();
+        let mouse = |kind, modifiers| {
+            Event::Mouse(MouseEvent {
+                kind,
+                column: 9,
+                row: 4,
+                modifiers,
+            })
+        };
+
+        assert_eq!(
+            decoder.feed(b"\x1B[<0;10;5M\x1B[<32;10;5M\x1B[<0;10;5m\x1B[<65;10;5M\x1B[<20;10;5M"),
+            vec![
+                mouse(MouseEventKind::Down(MouseButton::Left), KeyModifiers::NONE),
+                mouse(MouseEventKind::Drag(MouseButton::Left), KeyModifiers::NONE),
+                mouse(MouseEventKind::Up(MouseButton::Left), KeyModifiers::NONE),
+                mouse(MouseEventKind::ScrollDown, KeyModifiers::NONE),
+                mouse(
+                    MouseEventKind::Down(MouseButton::Left),
+                    KeyModifiers::SHIFT | KeyModifiers::CONTROL
+                ),
+            ]
+        );
+    }
+
+    struct SizeApp;
+
+    impl App for SizeApp {
+        fn reset(&mut self, _gfx: &mut Gfx) {}
+
+        fn update(&mut self, events: &EventGroup, _gfx: &mut Gfx) -> bool {
+            events.first_pressed_char() != Some('q')
+        }
+
+        fn draw(&self, gfx: &mut Gfx) {
+            gfx.draw_text_uncoloured(&format!("{}x{}", gfx.width, gfx.height), 0, 0);
+        }
+    }
+
+    #[test]
+    fn session_over_loopback() {
+        let server = Server::bind("127.0.0.1:0", || Box::new(SizeApp)).unwrap();
+        let mut client = TcpStream::connect(server.local_addr().unwrap()).unwrap();
+        let session = server.accept_one().unwrap();
+
+        client
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let mut input = vec![TELNET_IAC, TELNET_WILL, TELNET_TERMINAL_TYPE];
+        input.extend([
+            TELNET_IAC,
+            TELNET_SB,
+            TELNET_NAWS,
+            0,
+            40,
+            0,
+            10,
+            TELNET_IAC,
+            TELNET_SE,
+        ]);
+        input.extend([
+            TELNET_IAC,
+            TELNET_SB,
+            TELNET_TERMINAL_TYPE,
+            TELNET_TERMINAL_TYPE_IS,
+        ]);
+        input.extend(b"vt100");
+        input.extend([TELNET_IAC, TELNET_SE, b'q']);
+        client.write_all(&input).unwrap();
+
+        let mut output = vec![];
+        client.read_to_end(&mut output).unwrap();
+        session.join().unwrap();
+
+        assert!(output.starts_with(&TELNET_NEGOTIATION));
+        assert!(
+            output
+                .windows(TELNET_TERMINAL_TYPE_REQUEST.len())
+                .any(|bytes| bytes == TELNET_TERMINAL_TYPE_REQUEST)
+        );
+        let output = String::from_utf8_lossy(&output);
+        assert!(output.contains("40x10"));
+        // No mouse on a VT100.
+        assert!(!output.contains("\x1B[?1000h"));
+    }
+}
@@ -0,0 +1,179 @@
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+
+use crate::common::*;
+use crate::rect::Rect;
+
+pub const SPATIAL_INDEX_DEFAULT_CELL_SIZE: u16 = 16;
+
+/// Uniform grid of keys bucketed by their bounds, for finding the objects around a point or
+/// area without scanning all of them.
+///
+/// Queries only check bounds, callers still do the exact hit-test on the returned keys.
+#[derive(Debug, Clone)]
+pub struct SpatialIndex<K> {
+    cell_size: u16,
+    cells: HashMap<U16Point, Vec<K>>,
+    bounds: HashMap<K, Rect>,
+}
+
+impl<K: Copy + Eq + Hash> Default for SpatialIndex<K> {
+    fn default() -> Self {
+        Self::new(SPATIAL_INDEX_DEFAULT_CELL_SIZE)
+    }
+}
+
+impl<K: Copy + Eq + Hash> SpatialIndex<K> {
+    pub fn new(cell_size: u16) -> Self {
+        Self {
+            cell_size: cell_size.max(1),
+            cells: HashMap::new(),
+            bounds: HashMap::new(),
+        }
+    }
+
+    /// Inserting a key again replaces its bounds.
+    pub fn insert(&mut self, key: K, bounds: Rect) {
+        if let Some(old_bounds) = self.bounds.get(&key) {
+            if *old_bounds == bounds {
+                return;
+            }
+            self.remove(key);
+        }
+
+        for cell in self.cells_of(&bounds) {
+            self.cells.entry(cell).or_default().push(key);
+        }
+        self.bounds.insert(key, bounds);
+    }
+
+    /// Moves a key already in the index. Returns false (and does nothing) for unknown keys.
+    pub fn update(&mut self, key: K, bounds: Rect) -> bool {
+        if !self.bounds.contains_key(&key) {
+            return false;
+        }
+
+        self.insert(key, bounds);
+        true
+    }
+
+    /// Returns the bounds the key had.
+    pub fn remove(&mut self, key: K) -> Option<Rect> {
+        let bounds = self.bounds.remove(&key)?;
+
+        for cell in self.cells_of(&bounds) {
+            if let Some(keys) = self.cells.get_mut(&cell) {
+                keys.retain(|other| *other != key);
+                if keys.is_empty() {
+                    self.cells.remove(&cell);
+                }
+            }
+        }
+
+        Some(bounds)
+    }
+
+    pub fn bounds(&self, key: K) -> Option<Rect> {
+        self.bounds.get(&key).copied()
+    }
+
+    /// Keys whose bounds contain the point.
+    pub fn query_point(&self, p: U16Point) -> Vec<K> {
+        self.cells
+            .get(&(p / self.cell_size))
+            .map(|keys| {
+                keys.iter()
+                    .filter(|key| self.bounds[key].is_point_on(p))
+                    .copied()
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Keys whose bounds overlap the rect, each once.
+    pub fn query_rect(&self, rect: &Rect) -> Vec<K> {
+        let mut seen = HashSet::new();
+        let mut out = vec![];
+
+        for cell in self.cells_of(rect) {
+            for key in self.cells.get(&cell).into_iter().flatten() {
+                if seen.insert(*key) && self.bounds[key].overlaps(rect) {
+                    out.push(*key);
+                }
+            }
+        }
+
+        out
+    }
+
+    pub fn len(&self) -> usize {
+        self.bounds.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bounds.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.cells.clear();
+        self.bounds.clear();
+    }
+
+    fn cells_of(&self, rect: &Rect) -> impl Iterator<Item = U16Point> + use<K> {
+        let min = rect.start / self.cell_size;
+        let max = rect.end() / self.cell_size;
+
+        (min.y..=max.y).flat_map(move |y| (min.x..=max.x).map(move |x| U16Point::new(x, y)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::rect_from_size;
+
+    fn sorted(mut keys: Vec<u32>) -> Vec<u32> {
+        keys.sort();
+        keys
+    }
+
+    #[test]
+    fn point_query_checks_bounds() {
+        let mut index = SpatialIndex::new(8);
+        index.insert(1, rect_from_size(0, 0, 3, 3));
+        index.insert(2, rect_from_size(2, 2, 20, 20));
+
+        assert_eq!(sorted(index.query_point(U16Point::new(2, 2))), vec![1, 2]);
+        assert_eq!(index.query_point(U16Point::new(5, 5)), vec![2]);
+        assert_eq!(index.query_point(U16Point::new(22, 22)), vec![2]);
+        assert!(index.query_point(U16Point::new(23, 22)).is_empty());
+    }
+
+    #[test]
+    fn rect_query_returns_keys_once() {
+        let mut index = SpatialIndex::new(4);
+        index.insert(1, rect_from_size(0, 0, 30, 30));
+        index.insert(2, rect_from_size(40, 40, 2, 2));
+
+        assert_eq!(index.query_rect(&rect_from_size(0, 0, 20, 20)), vec![1]);
+        assert_eq!(
+            sorted(index.query_rect(&rect_from_size(25, 25, 20, 20))),
+            vec![1, 2]
+        );
+    }
+
+    #[test]
+    fn update_and_remove() {
+        let mut index = SpatialIndex::new(4);
+        index.insert(1, rect_from_size(0, 0, 2, 2));
+
+        assert!(index.update(1, rect_from_size(50, 50, 2, 2)));
+        assert!(!index.update(2, rect_from_size(0, 0, 2, 2)));
+        assert!(index.query_point(U16Point::new(1, 1)).is_empty());
+        assert_eq!(index.query_point(U16Point::new(51, 51)), vec![1]);
+
+        assert_eq!(index.remove(1), Some(rect_from_size(50, 50, 2, 2)));
+        assert!(index.is_empty());
+        assert!(index.query_point(U16Point::new(51, 51)).is_empty());
+    }
+}
@@ -0,0 +1,182 @@
+use std::collections::HashMap;
+
+use crate::common::*;
+
+/// Character that is not drawn, letting whatever is under the sprite show through.
+pub const SPRITE_TRANSPARENT_CHAR: char = ' ';
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SpriteCell {
+    pub ch: char,
+    pub color: u8,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Sprite {
+    pub rows: Vec<Vec<SpriteCell>>,
+}
+
+impl Sprite {
+    /// Every character gets the same color.
+    pub fn from_text(text: &str, color: u8) -> Self {
+        Self {
+            rows: text
+                .lines()
+                .map(|line| line.chars().map(|ch| SpriteCell { ch, color }).collect())
+                .collect(),
+        }
+    }
+
+    /// Colors are taken from SGR escape sequences (`ESC[<code>m`) embedded in the text. Only the
+    /// last numeric parameter of a sequence is kept, `0` resets to the default color.
+    pub fn from_ansi(text: &str) -> Self {
+        let mut rows = vec![];
+        let mut color = DEFAULT_COLOR_CODE;
+
+        for line in text.lines() {
+            let mut row = vec![];
+            let mut chars = line.chars().peekable();
+
+            while let Some(ch) = chars.next() {
+                if ch != '\x1B' {
+                    row.push(SpriteCell { ch, color });
+                    continue;
+                }
+
+                if chars.peek() != Some(&'[') {
+                    continue;
+                }
+                chars.next();
+
+                let mut sequence = String::new();
+                for ch in chars.by_ref() {
+                    if ch.is_ascii_alphabetic() {
+                        if ch == 'm' {
+                            color = sequence
+                                .split(';')
+                                .filter_map(|code| code.parse::<u8>().ok())
+                                .next_back()
+                                .unwrap_or(DEFAULT_COLOR_CODE);
+                        }
+                        break;
+                    }
+                    sequence.push(ch);
+                }
+            }
+
+            rows.push(row);
+        }
+
+        Self { rows }
+    }
+
+    pub fn width(&self) -> u16 {
+        self.rows.iter().map(|row| row.len()).max().unwrap_or(0) as u16
+    }
+
+    pub fn height(&self) -> u16 {
+        self.rows.len() as u16
+    }
+
+    pub fn size(&self) -> U16Point {
+        U16Point::new(self.width(), self.height())
+    }
+
+    /// Recolors every cell which has an entry in the color map.
+    pub fn apply_color_map(&mut self, color_map: &ColorMap) {
+        for cell in self.rows.iter_mut().flatten() {
+            if let Some(color) = color_map.color_of(cell.ch) {
+                cell.color = color;
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Animation {
+    pub frames: Vec<Sprite>,
+    /// Number of time units (frames, ticks, ms - up to the caller) a single frame is shown.
+    pub frame_duration: u64,
+}
+
+impl Animation {
+    pub fn frame(&self, time: u64) -> &Sprite {
+        let index = (time / self.frame_duration.max(1)) % self.frames.len() as u64;
+        &self.frames[index as usize]
+    }
+
+    /// Length of a full cycle in the same time unit as `frame_duration`.
+    pub fn duration(&self) -> u64 {
+        self.frame_duration.max(1) * self.frames.len() as u64
+    }
+}
+
+/// Ordered glyph to color code pairs, optionally named by a key.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ColorMap {
+    pub entries: Vec<(char, u8)>,
+    /// Index into `entries` by key.
+    pub keys: HashMap<String, usize>,
+}
+
+impl ColorMap {
+    /// Glyph and color of the entry with the key.
+    pub fn get(&self, key: &str) -> Option<(char, u8)> {
+        self.keys
+            .get(key)
+            .and_then(|i| self.entries.get(*i))
+            .copied()
+    }
+
+    pub fn color_of(&self, ch: char) -> Option<u8> {
+        self.entries
+            .iter()
+            .find(|(entry_ch, _)| *entry_ch == ch)
+            .map(|(_, color)| *color)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use super::*;
+    use crate::gfx::Gfx;
+
+    #[test]
+    fn colors_from_ansi_escapes() {
+        let sprite = Sprite::from_ansi("a\x1B[1;31mb\x1B[2Kc\n\x1B[0md\x1B(e");
+
+        let colors = |row: &[SpriteCell]| row.iter().map(|c| (c.ch, c.color)).collect::<Vec<_>>();
+        // Non SGR sequences are dropped without changing the color.
+        assert_eq!(
+            colors(&sprite.rows[0]),
+            vec![('a', 0), ('b', 31), ('c', 31)]
+        );
+        // A lone escape is dropped, the color carries over lines until reset.
+        assert_eq!(colors(&sprite.rows[1]), vec![('d', 0), ('(', 0), ('e', 0)]);
+        assert_eq!(sprite.size(), U16Point::new(3, 2));
+    }
+
+    #[test]
+    fn spaces_are_transparent() {
+        let mut gfx = Gfx::with_output(Box::new(io::sink()));
+        gfx.resize(3, 2);
+        gfx.draw_text("xxx", 0, 0, 0);
+        gfx.draw_text("xxx", 0, 1, 0);
+
+        let mut sprite = Sprite::from_text("a b\n c", 31);
+        sprite.apply_color_map(&ColorMap {
+            entries: vec![('c', 32)],
+            ..ColorMap::default()
+        });
+        assert_eq!(sprite.rows[1][1].color, 32);
+
+        gfx.draw_sprite(&sprite, 0, 0);
+        assert_eq!(gfx.capture().to_text(), "axb\nxcx");
+    }
+}
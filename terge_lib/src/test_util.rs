@@ -0,0 +1,43 @@
+use crossterm::event::{
+    Event, KeyCode, KeyEvent, KeyEventKind, KeyEventState, KeyModifiers, MouseButton, MouseEvent,
+    MouseEventKind,
+};
+
+use crate::common::U16Point;
+use crate::rect::Rect;
+
+/// Rect at `x`:`y` with `size_x`:`size_y` as its `size`, which is inclusive: a rect of size
+/// 2:1 covers 3 by 2 cells.
+pub(crate) fn rect_from_size(x: u16, y: u16, size_x: u16, size_y: u16) -> Rect {
+    Rect::new(U16Point::new(x, y), U16Point::new(size_x, size_y))
+}
+
+pub(crate) fn key(code: KeyCode) -> Event {
+    key_with_modifiers(code, KeyModifiers::NONE)
+}
+
+pub(crate) fn key_with_modifiers(code: KeyCode, modifiers: KeyModifiers) -> Event {
+    Event::Key(KeyEvent {
+        code,
+        modifiers,
+        kind: KeyEventKind::Press,
+        state: KeyEventState::NONE,
+    })
+}
+
+pub(crate) fn mouse(kind: MouseEventKind, column: u16, row: u16) -> Event {
+    Event::Mouse(MouseEvent {
+        kind,
+        column,
+        row,
+        modifiers: KeyModifiers::NONE,
+    })
+}
+
+/// Left button press and release.
+pub(crate) fn click(column: u16, row: u16) -> Vec<Event> {
+    vec![
+        mouse(MouseEventKind::Down(MouseButton::Left), column, row),
+        mouse(MouseEventKind::Up(MouseButton::Left), column, row),
+    ]
+}
@@ -0,0 +1,599 @@
+use crate::common::*;
+use crate::gfx::Gfx;
+use crate::rect::Rect;
+
+/// Marks text cut off by `TextLayout`.
+pub const TEXT_ELLIPSIS_CHAR: char = '…';
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WrapMode {
+    /// Breaks between words, words wider than the rect are broken anywhere. Runs of spaces
+    /// collapse into one.
+    #[default]
+    Word,
+    /// Breaks anywhere, keeping every character.
+    Char,
+    /// No wrapping, lines are cut at the right edge.
+    Clip,
+}
+
+/// Text in one style, from `parse_markup`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextSpan {
+    pub text: String,
+    /// SGR codes applied on top of the base color, eg. `[1, 31]` for bold red.
+    pub codes: Vec<u8>,
+}
+
+/// Laid out piece of text, ready to be drawn.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextRun {
+    pub pos: U16Point,
+    pub text: String,
+    pub codes: Vec<u8>,
+}
+
+/// Places text into a rect: wrapping, alignment, cutting off what doesn't fit and optional inline
+/// markup (see `parse_markup`).
+#[derive(Debug, Clone)]
+pub struct TextLayout {
+    pub rect: Rect,
+    pub wrap: WrapMode,
+    pub horizontal_align: TextHorizontalAlign,
+    pub vertical_align: TextVercticalAlign,
+    /// Ends text cut off at the right or bottom edge with `TEXT_ELLIPSIS_CHAR`.
+    pub ellipsis: bool,
+    pub markup: bool,
+    pub color: u8,
+}
+
+impl TextLayout {
+    pub fn new(rect: Rect) -> Self {
+        Self {
+            rect,
+            wrap: WrapMode::Word,
+            horizontal_align: TextHorizontalAlign::Left,
+            vertical_align: TextVercticalAlign::Top,
+            ellipsis: true,
+            markup: false,
+            color: DEFAULT_COLOR_CODE,
+        }
+    }
+
+    pub fn with_wrap(mut self, wrap: WrapMode) -> Self {
+        self.wrap = wrap;
+        self
+    }
+
+    pub fn with_align(
+        mut self,
+        horizontal_align: TextHorizontalAlign,
+        vertical_align: TextVercticalAlign,
+    ) -> Self {
+        self.horizontal_align = horizontal_align;
+        self.vertical_align = vertical_align;
+        self
+    }
+
+    pub fn with_ellipsis(mut self, ellipsis: bool) -> Self {
+        self.ellipsis = ellipsis;
+        self
+    }
+
+    pub fn with_markup(mut self) -> Self {
+        self.markup = true;
+        self
+    }
+
+    pub fn with_color(mut self, color: u8) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn layout(&self, text: &str) -> Vec<TextRun> {
+        let width = self.rect.width() as usize;
+        let height = self.rect.height() as usize;
+
+        let (cells, styles) = if self.markup {
+            styled_cells(&parse_markup(text))
+        } else {
+            styled_cells(&[TextSpan {
+                text: text.to_string(),
+                codes: vec![],
+            }])
+        };
+
+        let wrapped = wrap_cells(&cells, width, self.wrap);
+        let cut_off = wrapped.len() > height;
+        let mut lines: Vec<Vec<Cell>> = wrapped
+            .iter()
+            .take(height)
+            .map(|line| {
+                let justify =
+                    self.horizontal_align == TextHorizontalAlign::Justify && !line.ends_paragraph;
+                line.render(if justify { Some(width) } else { None })
+            })
+            .collect();
+
+        for line in &mut lines {
+            if line.len() > width {
+                line.truncate(width);
+                if self.ellipsis {
+                    mark_cut_off(line, width);
+                }
+            }
+        }
+        if cut_off
+            && self.ellipsis
+            && let Some(last) = lines.last_mut()
+        {
+            mark_cut_off(last, width);
+        }
+
+        let free_rows = height - lines.len();
+        let top = match self.vertical_align {
+            TextVercticalAlign::Top => 0,
+            TextVercticalAlign::Center => free_rows / 2,
+            TextVercticalAlign::Bottom => free_rows,
+        };
+
+        let mut out = vec![];
+        for (row, line) in lines.iter().enumerate() {
+            let free_columns = width - line.len();
+            let left = match self.horizontal_align {
+                TextHorizontalAlign::Left | TextHorizontalAlign::Justify => 0,
+                TextHorizontalAlign::Center => free_columns / 2,
+                TextHorizontalAlign::Right => free_columns,
+            };
+
+            for (column, run) in runs(line) {
+                let mut codes = vec![self.color];
+                codes.extend(&styles[run.1]);
+                out.push(TextRun {
+                    pos: self.rect.start
+                        + U16Point::new((left + column) as u16, (top + row) as u16),
+                    text: run.0,
+                    codes,
+                });
+            }
+        }
+
+        out
+    }
+
+    pub fn draw(&self, gfx: &Gfx, text: &str) {
+        for run in self.layout(text) {
+            gfx.draw_styled_text(&run.text, run.pos.x, run.pos.y, &run.codes);
+        }
+    }
+}
+
+/// Plain text wrapped to `width` columns, one string per row.
+pub fn wrap_text(text: &str, width: usize, mode: WrapMode) -> Vec<String> {
+    let (cells, _) = styled_cells(&[TextSpan {
+        text: text.to_string(),
+        codes: vec![],
+    }]);
+
+    wrap_cells(&cells, width.max(1), mode)
+        .iter()
+        .map(|line| line.render(None).iter().map(|cell| cell.0).collect())
+        .collect()
+}
+
+/// Splits text with inline markup into spans.
+///
+/// `[red]`, `[bold]`, `[on_blue]` (background) or a raw SGR code like `[91]` apply until the
+/// next `[/]`. Tags nest and can combine names: `[bold yellow]`. `[[` is a literal `[`, brackets
+/// that aren't a known tag are kept as they are.
+pub fn parse_markup(text: &str) -> Vec<TextSpan> {
+    let mut out = vec![];
+    let mut stack: Vec<Vec<u8>> = vec![];
+    let mut current = String::new();
+    let mut rest = text;
+
+    let flush = |current: &mut String, stack: &Vec<Vec<u8>>, out: &mut Vec<TextSpan>| {
+        if !current.is_empty() {
+            out.push(TextSpan {
+                text: std::mem::take(current),
+                codes: stack.concat(),
+            });
+        }
+    };
+
+    while let Some(i) = rest.find('[') {
+        current.push_str(&rest[..i]);
+        rest = &rest[i..];
+
+        if let Some(after) = rest.strip_prefix("[[") {
+            current.push('[');
+            rest = after;
+            continue;
+        }
+
+        let Some(end) = rest.find(']') else {
+            break;
+        };
+        let tag = &rest[1..end];
+
+        if tag == "/" {
+            flush(&mut current, &stack, &mut out);
+            stack.pop();
+        } else if let Some(codes) = tag_codes(tag) {
+            flush(&mut current, &stack, &mut out);
+            stack.push(codes);
+        } else {
+            current.push_str(&rest[..=end]);
+        }
+        rest = &rest[end + 1..];
+    }
+
+    current.push_str(rest);
+    flush(&mut current, &stack, &mut out);
+    out
+}
+
+fn tag_codes(tag: &str) -> Option<Vec<u8>> {
+    let codes: Option<Vec<u8>> = tag.split_whitespace().map(tag_code).collect();
+    codes.filter(|codes| !codes.is_empty())
+}
+
+fn tag_code(name: &str) -> Option<u8> {
+    if let Ok(code) = name.parse() {
+        return Some(code);
+    }
+    if let Some(color) = name.strip_prefix("on_") {
+        return color_code(color).map(|code| code + 10);
+    }
+
+    match name {
+        "bold" => Some(1),
+        "dim" => Some(2),
+        "italic" => Some(3),
+        "underline" => Some(4),
+        "blink" => Some(5),
+        "reverse" => Some(7),
+        "strike" => Some(9),
+        _ => color_code(name),
+    }
+}
+
+fn color_code(name: &str) -> Option<u8> {
+    let (name, bright) = match name.strip_prefix("bright_") {
+        Some(name) => (name, true),
+        None => (name, false),
+    };
+    let code = match name {
+        "black" => 30,
+        "red" => 31,
+        "green" => 32,
+        "yellow" => 33,
+        "blue" => 34,
+        "magenta" => 35,
+        "cyan" => 36,
+        "white" => 37,
+        "gray" | "grey" if !bright => return Some(90),
+        _ => return None,
+    };
+
+    Some(if bright { code + 60 } else { code })
+}
+
+/// Character and the index of its style.
+type Cell = (char, usize);
+
+#[derive(Debug)]
+struct Word {
+    /// Style of the space in front of the word.
+    gap_style: usize,
+    cells: Vec<Cell>,
+}
+
+#[derive(Debug)]
+struct WrappedLine {
+    words: Vec<Word>,
+    ends_paragraph: bool,
+}
+
+impl WrappedLine {
+    /// Words joined by single spaces, or spread out to `justify_width` columns.
+    fn render(&self, justify_width: Option<usize>) -> Vec<Cell> {
+        let natural: usize = self
+            .words
+            .iter()
+            .map(|word| word.cells.len())
+            .sum::<usize>()
+            + self.words.len();
+        let gaps = self.words.len().saturating_sub(1);
+        let extra = match justify_width {
+            Some(width) if gaps > 0 => width.saturating_sub(natural - 1),
+            _ => 0,
+        };
+
+        let mut out = vec![];
+        for (i, word) in self.words.iter().enumerate() {
+            if i > 0 {
+                let spaces = 1 + extra / gaps + ((i - 1) < extra % gaps) as usize;
+                out.extend(std::iter::repeat_n((' ', word.gap_style), spaces));
+            }
+            out.extend(&word.cells);
+        }
+        out
+    }
+}
+
+/// Flattens spans into cells, the returned styles are indexed by the cells.
+fn styled_cells(spans: &[TextSpan]) -> (Vec<Cell>, Vec<Vec<u8>>) {
+    let mut styles: Vec<Vec<u8>> = vec![vec![]];
+    let mut cells = vec![];
+
+    for span in spans {
+        let style = match styles.iter().position(|codes| *codes == span.codes) {
+            Some(style) => style,
+            None => {
+                styles.push(span.codes.clone());
+                styles.len() - 1
+            }
+        };
+        cells.extend(span.text.chars().map(|ch| (ch, style)));
+    }
+
+    (cells, styles)
+}
+
+fn wrap_cells(cells: &[Cell], width: usize, mode: WrapMode) -> Vec<WrappedLine> {
+    let mut out = vec![];
+
+    for paragraph in cells.split(|cell| cell.0 == '\n') {
+        let first = out.len();
+        let whole = |cells: &[Cell]| WrappedLine {
+            words: vec![Word {
+                gap_style: 0,
+                cells: cells.to_vec(),
+            }],
+            ends_paragraph: false,
+        };
+
+        match mode {
+            WrapMode::Clip => out.push(whole(paragraph)),
+            WrapMode::Char if paragraph.is_empty() => out.push(whole(paragraph)),
+            WrapMode::Char => out.extend(paragraph.chunks(width).map(whole)),
+            WrapMode::Word => wrap_words(paragraph, width, &mut out),
+        }
+
+        if out.len() == first {
+            out.push(whole(&[]));
+        }
+        if let Some(last) = out.last_mut() {
+            last.ends_paragraph = true;
+        }
+    }
+
+    out
+}
+
+fn wrap_words(paragraph: &[Cell], width: usize, out: &mut Vec<WrappedLine>) {
+    let mut words: Vec<Word> = vec![];
+    let mut gap_style = 0;
+    let mut cells = vec![];
+
+    for cell in paragraph {
+        if cell.0.is_whitespace() {
+            if !cells.is_empty() {
+                words.push(Word {
+                    gap_style,
+                    cells: std::mem::take(&mut cells),
+                });
+            }
+            gap_style = cell.1;
+        } else {
+            cells.push(*cell);
+        }
+    }
+    if !cells.is_empty() {
+        words.push(Word { gap_style, cells });
+    }
+
+    let mut line: Vec<Word> = vec![];
+    let mut line_width = 0;
+    let mut flush = |line: &mut Vec<Word>, line_width: &mut usize| {
+        if !line.is_empty() {
+            out.push(WrappedLine {
+                words: std::mem::take(line),
+                ends_paragraph: false,
+            });
+        }
+        *line_width = 0;
+    };
+
+    for word in words {
+        let word_width = word.cells.len();
+
+        if word_width > width {
+            flush(&mut line, &mut line_width);
+            let mut chunks = word.cells.chunks(width).peekable();
+            while let Some(chunk) = chunks.next() {
+                line.push(Word {
+                    gap_style: word.gap_style,
+                    cells: chunk.to_vec(),
+                });
+                line_width = chunk.len();
+                if chunks.peek().is_some() {
+                    flush(&mut line, &mut line_width);
+                }
+            }
+        } else if line.is_empty() {
+            line_width = word_width;
+            line.push(word);
+        } else if line_width + 1 + word_width <= width {
+            line_width += 1 + word_width;
+            line.push(word);
+        } else {
+            flush(&mut line, &mut line_width);
+            line_width = word_width;
+            line.push(word);
+        }
+    }
+    flush(&mut line, &mut line_width);
+}
+
+/// Puts the ellipsis at the end of the line, replacing the last character if it is full.
+fn mark_cut_off(line: &mut Vec<Cell>, width: usize) {
+    let style = line.last().map_or(0, |cell| cell.1);
+    if line.len() >= width {
+        line.truncate(width.saturating_sub(1));
+    }
+    line.push((TEXT_ELLIPSIS_CHAR, style));
+}
+
+/// Groups neighbouring cells of the same style, with their column.
+fn runs(line: &[Cell]) -> Vec<(usize, (String, usize))> {
+    let mut out: Vec<(usize, (String, usize))> = vec![];
+
+    for (column, (ch, style)) in line.iter().enumerate() {
+        match out.last_mut() {
+            Some((_, (text, run_style))) if run_style == style => text.push(*ch),
+            _ => out.push((column, (ch.to_string(), *style))),
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::rect_from_size;
+
+    fn rows(runs: &[TextRun]) -> Vec<(U16Point, String)> {
+        runs.iter().map(|run| (run.pos, run.text.clone())).collect()
+    }
+
+    #[test]
+    fn markup_spans() {
+        assert_eq!(
+            parse_markup("a [bold red]b [on_blue]c[/] d[/] [[x] [nope] [91]e"),
+            vec![
+                TextSpan {
+                    text: "a ".to_string(),
+                    codes: vec![],
+                },
+                TextSpan {
+                    text: "b ".to_string(),
+                    codes: vec![1, 31],
+                },
+                TextSpan {
+                    text: "c".to_string(),
+                    codes: vec![1, 31, 44],
+                },
+                TextSpan {
+                    text: " d".to_string(),
+                    codes: vec![1, 31],
+                },
+                TextSpan {
+                    text: " [x] [nope] ".to_string(),
+                    codes: vec![],
+                },
+                TextSpan {
+                    text: "e".to_string(),
+                    codes: vec![91],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn word_and_char_wrap() {
+        assert_eq!(
+            wrap_text("the quick  brown fox\n\njumps", 10, WrapMode::Word),
+            vec!["the quick", "brown fox", "", "jumps"]
+        );
+        assert_eq!(
+            wrap_text("abcdefghij xy", 4, WrapMode::Word),
+            vec!["abcd", "efgh", "ij", "xy"]
+        );
+        assert_eq!(
+            wrap_text("ab cdef", 3, WrapMode::Char),
+            vec!["ab ", "cde", "f"]
+        );
+    }
+
+    #[test]
+    fn horizontal_alignments() {
+        let layout = |align| {
+            TextLayout::new(rect_from_size(0, 0, 9, 1))
+                .with_align(align, TextVercticalAlign::Top)
+                .layout("aa bb cc dd")
+        };
+
+        assert_eq!(
+            rows(&layout(TextHorizontalAlign::Right)),
+            vec![
+                (U16Point::new(2, 0), "aa bb cc".to_string()),
+                (U16Point::new(8, 1), "dd".to_string()),
+            ]
+        );
+        assert_eq!(
+            rows(&layout(TextHorizontalAlign::Center)),
+            vec![
+                (U16Point::new(1, 0), "aa bb cc".to_string()),
+                (U16Point::new(4, 1), "dd".to_string()),
+            ]
+        );
+        // The last line of a paragraph is not justified.
+        assert_eq!(
+            rows(&layout(TextHorizontalAlign::Justify)),
+            vec![
+                (U16Point::new(0, 0), "aa  bb  cc".to_string()),
+                (U16Point::new(0, 1), "dd".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn vertical_alignment() {
+        let runs = TextLayout::new(rect_from_size(3, 3, 9, 4))
+            .with_align(TextHorizontalAlign::Left, TextVercticalAlign::Bottom)
+            .layout("one\ntwo");
+
+        assert_eq!(
+            rows(&runs),
+            vec![
+                (U16Point::new(3, 6), "one".to_string()),
+                (U16Point::new(3, 7), "two".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn cut_off_text_gets_an_ellipsis() {
+        let runs = TextLayout::new(rect_from_size(0, 0, 4, 0)).layout("one two three");
+        assert_eq!(rows(&runs), vec![(U16Point::new(0, 0), "one…".to_string())]);
+
+        let runs = TextLayout::new(rect_from_size(0, 0, 2, 0))
+            .with_wrap(WrapMode::Clip)
+            .layout("abcdef");
+        assert_eq!(rows(&runs), vec![(U16Point::new(0, 0), "ab…".to_string())]);
+
+        let runs = TextLayout::new(rect_from_size(0, 0, 2, 0))
+            .with_wrap(WrapMode::Clip)
+            .with_ellipsis(false)
+            .layout("abcdef");
+        assert_eq!(rows(&runs), vec![(U16Point::new(0, 0), "abc".to_string())]);
+    }
+
+    #[test]
+    fn markup_runs_keep_base_color() {
+        let runs = TextLayout::new(rect_from_size(0, 0, 20, 0))
+            .with_markup()
+            .with_color(97)
+            .layout("hi [red]there[/]");
+
+        assert_eq!(
+            runs.iter()
+                .map(|run| (run.text.as_str(), run.codes.clone()))
+                .collect::<Vec<_>>(),
+            vec![("hi ", vec![97]), ("there", vec![97, 31])]
+        );
+    }
+}
@@ -0,0 +1,259 @@
+use std::{collections::HashMap, io};
+
+use crate::camera::Camera;
+use crate::common::*;
+use crate::gfx::Gfx;
+
+/// Tiles are identified by the character used for them in the map file.
+pub type TileId = char;
+
+/// Tile that is never drawn and never solid.
+pub const EMPTY_TILE: TileId = ' ';
+/// Map file line defining a tile: `@tile <id> <glyph> <color> [solid]`.
+pub const TILE_DEFINITION_DIRECTIVE: &str = "@tile";
+/// Starts a comment line in a map file. A directive like `@tile`, so rows of any tiles (eg. `#`
+/// walls) are never mistaken for comments.
+pub const TILE_MAP_COMMENT_DIRECTIVE: &str = "@#";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tile {
+    pub glyph: char,
+    pub color: u8,
+    pub solid: bool,
+}
+
+/// Grid of tile ids, each rendered through its `Tile` definition.
+///
+/// Ids without a definition are drawn as themselves with the default color.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TileMap {
+    pub width: u16,
+    pub height: u16,
+    cells: Vec<TileId>,
+    pub tiles: HashMap<TileId, Tile>,
+}
+
+impl TileMap {
+    pub fn new(width: u16, height: u16) -> Self {
+        Self {
+            width,
+            height,
+            cells: vec![EMPTY_TILE; width as usize * height as usize],
+            tiles: HashMap::new(),
+        }
+    }
+
+    /// Parses a map file. `@tile` lines define tiles, `@#` lines are comments and every other
+    /// line is a row of the map. Short rows are padded with `EMPTY_TILE`.
+    pub fn from_text(text: &str) -> io::Result<Self> {
+        let mut tiles = HashMap::new();
+        let mut rows: Vec<Vec<TileId>> = vec![];
+
+        for line in text.lines() {
+            if line.starts_with(TILE_MAP_COMMENT_DIRECTIVE) {
+                continue;
+            }
+
+            if let Some(definition) = line.strip_prefix(TILE_DEFINITION_DIRECTIVE) {
+                let (id, tile) = parse_tile_definition(definition).ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("Invalid tile definition: {}", line),
+                    )
+                })?;
+                tiles.insert(id, tile);
+                continue;
+            }
+
+            rows.push(line.chars().collect());
+        }
+
+        let width = rows.iter().map(|row| row.len()).max().unwrap_or(0) as u16;
+        let mut map = Self::new(width, rows.len() as u16);
+        map.tiles = tiles;
+
+        for (y, row) in rows.iter().enumerate() {
+            for (x, id) in row.iter().enumerate() {
+                map.set(U16Point::new(x as u16, y as u16), *id);
+            }
+        }
+
+        Ok(map)
+    }
+
+    pub fn define_tile(&mut self, id: TileId, tile: Tile) {
+        self.tiles.insert(id, tile);
+    }
+
+    pub fn size(&self) -> U16Point {
+        U16Point::new(self.width, self.height)
+    }
+
+    pub fn is_in_bounds(&self, p: I32Point) -> bool {
+        p.x >= 0 && p.y >= 0 && p.x < self.width as i32 && p.y < self.height as i32
+    }
+
+    /// Tile id at the position, `None` outside of the map.
+    pub fn get(&self, p: U16Point) -> Option<TileId> {
+        if p.x < self.width && p.y < self.height {
+            Some(self.cells[self.index(p)])
+        } else {
+            None
+        }
+    }
+
+    /// Out of bounds positions are ignored.
+    pub fn set(&mut self, p: U16Point, id: TileId) {
+        if p.x < self.width && p.y < self.height {
+            let index = self.index(p);
+            self.cells[index] = id;
+        }
+    }
+
+    pub fn tile(&self, p: U16Point) -> Option<Tile> {
+        self.get(p).and_then(|id| self.tiles.get(&id).copied())
+    }
+
+    /// Positions outside of the map count as solid, so nothing walks off the edge.
+    pub fn is_solid(&self, p: I32Point) -> bool {
+        if !self.is_in_bounds(p) {
+            return true;
+        }
+
+        self.tile(p.saturating_u16())
+            .map(|tile| tile.solid)
+            .unwrap_or(false)
+    }
+
+    /// Draws the tiles seen by the camera, everything else is skipped.
+    pub fn draw(&self, gfx: &Gfx, camera: &Camera) {
+        let (min, max) = camera.visible_cells();
+
+        for y in min.y.max(0)..=max.y.min(self.height as i32 - 1) {
+            for x in min.x.max(0)..=max.x.min(self.width as i32 - 1) {
+                let p = U16Point::new(x as u16, y as u16);
+                let id = self.cells[self.index(p)];
+                if id == EMPTY_TILE {
+                    continue;
+                }
+
+                let (glyph, color) = self
+                    .tiles
+                    .get(&id)
+                    .map(|tile| (tile.glyph, tile.color))
+                    .unwrap_or((id, DEFAULT_COLOR_CODE));
+
+                if let Some(screen_p) = camera.world_to_screen(p.as_f32()) {
+                    gfx.draw_text(
+                        glyph.encode_utf8(&mut [0; 4]),
+                        screen_p.x,
+                        screen_p.y,
+                        color,
+                    );
+                }
+            }
+        }
+    }
+
+    fn index(&self, p: U16Point) -> usize {
+        p.y as usize * self.width as usize + p.x as usize
+    }
+}
+
+fn parse_tile_definition(definition: &str) -> Option<(TileId, Tile)> {
+    let mut parts = definition.split_whitespace();
+
+    let id = single_char(parts.next()?)?;
+    let glyph = single_char(parts.next()?)?;
+    let color = parts.next()?.parse().ok()?;
+    let solid = match parts.next() {
+        Some("solid") => true,
+        Some(_) => return None,
+        None => false,
+    };
+
+    Some((
+        id,
+        Tile {
+            glyph,
+            color,
+            solid,
+        },
+    ))
+}
+
+fn single_char(s: &str) -> Option<char> {
+    let mut chars = s.chars();
+    let ch = chars.next()?;
+    chars.next().is_none().then_some(ch)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loads_the_example_dungeon() {
+        let map = TileMap::from_text(include_str!("../examples/assets/dungeon.txt")).unwrap();
+
+        assert_eq!(map.size(), U16Point::new(120, 48));
+        assert_eq!(map.tiles.len(), 4);
+        assert_eq!(map.get(U16Point::new(0, 0)), Some('#'));
+        assert!(map.is_solid(I32Point::new(0, 0)));
+        assert!(!map.is_solid(I32Point::new(52, 4)));
+    }
+
+    #[test]
+    fn parses_definitions_and_ragged_rows() {
+        let map = TileMap::from_text(
+            "@# header comment\n\
+             @tile # X 90 solid\n\
+             @tile . _ 37\n\
+             #.\n\
+             @# comment between rows\n\
+             # .?\n\
+             .",
+        )
+        .unwrap();
+
+        assert_eq!(map.size(), U16Point::new(4, 3));
+        assert_eq!(
+            map.tile(U16Point::new(0, 0)),
+            Some(Tile {
+                glyph: 'X',
+                color: 90,
+                solid: true,
+            })
+        );
+        assert_eq!(map.get(U16Point::new(1, 1)), Some(' '));
+        // Unknown ids are kept, they are just not solid.
+        assert_eq!(map.get(U16Point::new(3, 1)), Some('?'));
+        assert_eq!(map.tile(U16Point::new(3, 1)), None);
+        assert!(!map.is_solid(I32Point::new(3, 1)));
+        // Short rows are padded.
+        assert_eq!(map.get(U16Point::new(3, 2)), Some(EMPTY_TILE));
+        assert_eq!(map.get(U16Point::new(4, 2)), None);
+
+        assert!(TileMap::from_text("@tile # X red").is_err());
+        assert!(TileMap::from_text("@tile # X 90 hard").is_err());
+    }
+
+    #[test]
+    fn first_row_may_start_with_a_wall() {
+        let map = TileMap::from_text("#\n# .\n###").unwrap();
+
+        assert_eq!(map.size(), U16Point::new(3, 3));
+        assert_eq!(map.get(U16Point::new(0, 0)), Some('#'));
+        assert_eq!(map.get(U16Point::new(2, 1)), Some('.'));
+    }
+
+    #[test]
+    fn outside_of_the_map_is_solid() {
+        let map = TileMap::from_text("..\n..").unwrap();
+
+        assert!(!map.is_solid(I32Point::new(1, 1)));
+        assert!(map.is_solid(I32Point::new(-1, 0)));
+        assert!(map.is_solid(I32Point::new(0, 2)));
+        assert!(map.is_solid(I32Point::new(2, 0)));
+    }
+}
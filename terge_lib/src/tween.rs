@@ -0,0 +1,457 @@
+use std::f32::consts::PI;
+use std::fmt;
+
+use crate::common::*;
+use crate::event_group::EventGroup;
+
+/// Number of repeats meaning the tween never finishes.
+pub const TWEEN_REPEAT_FOREVER: u32 = u32::MAX;
+
+/// Values that can be interpolated. `t` is usually in 0.0..=1.0 but elastic and bounce easing
+/// can overshoot.
+pub trait Lerp {
+    fn lerp(&self, to: &Self, t: f32) -> Self;
+}
+
+impl Lerp for f32 {
+    fn lerp(&self, to: &Self, t: f32) -> Self {
+        self + (to - self) * t
+    }
+}
+
+impl Lerp for F32Point {
+    fn lerp(&self, to: &Self, t: f32) -> Self {
+        F32Point::new(self.x.lerp(&to.x, t), self.y.lerp(&to.y, t))
+    }
+}
+
+impl Lerp for I32Point {
+    fn lerp(&self, to: &Self, t: f32) -> Self {
+        self.as_f32().lerp(&to.as_f32(), t).round().saturating_i32()
+    }
+}
+
+impl Lerp for U16Point {
+    fn lerp(&self, to: &Self, t: f32) -> Self {
+        self.as_f32().lerp(&to.as_f32(), t).round().saturating_u16()
+    }
+}
+
+impl Lerp for RgbColor {
+    fn lerp(&self, to: &Self, t: f32) -> Self {
+        let channel = |lhs: u8, rhs: u8| (lhs as f32).lerp(&(rhs as f32), t).round() as u8;
+        RgbColor(
+            channel(self.0, to.0),
+            channel(self.1, to.1),
+            channel(self.2, to.2),
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Easing {
+    Linear,
+    QuadIn,
+    QuadOut,
+    QuadInOut,
+    CubicIn,
+    CubicOut,
+    CubicInOut,
+    ElasticIn,
+    ElasticOut,
+    ElasticInOut,
+    BounceIn,
+    BounceOut,
+    BounceInOut,
+}
+
+impl Easing {
+    /// Maps linear progress (0.0..=1.0) to eased progress.
+    pub fn apply(&self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+
+        match self {
+            Easing::Linear => t,
+            Easing::QuadIn => t * t,
+            Easing::QuadOut => 1.0 - (1.0 - t).powi(2),
+            Easing::QuadInOut => {
+                if t < 0.5 {
+                    2.0 * t * t
+                } else {
+                    1.0 - (-2.0 * t + 2.0).powi(2) / 2.0
+                }
+            }
+            Easing::CubicIn => t.powi(3),
+            Easing::CubicOut => 1.0 - (1.0 - t).powi(3),
+            Easing::CubicInOut => {
+                if t < 0.5 {
+                    4.0 * t.powi(3)
+                } else {
+                    1.0 - (-2.0 * t + 2.0).powi(3) / 2.0
+                }
+            }
+            Easing::ElasticIn => {
+                if t == 0.0 || t == 1.0 {
+                    t
+                } else {
+                    -(2f32.powf(10.0 * t - 10.0)) * ((10.0 * t - 10.75) * (2.0 * PI / 3.0)).sin()
+                }
+            }
+            Easing::ElasticOut => {
+                if t == 0.0 || t == 1.0 {
+                    t
+                } else {
+                    2f32.powf(-10.0 * t) * ((10.0 * t - 0.75) * (2.0 * PI / 3.0)).sin() + 1.0
+                }
+            }
+            Easing::ElasticInOut => {
+                let c = 2.0 * PI / 4.5;
+                if t == 0.0 || t == 1.0 {
+                    t
+                } else if t < 0.5 {
+                    -(2f32.powf(20.0 * t - 10.0) * ((20.0 * t - 11.125) * c).sin()) / 2.0
+                } else {
+                    2f32.powf(-20.0 * t + 10.0) * ((20.0 * t - 11.125) * c).sin() / 2.0 + 1.0
+                }
+            }
+            Easing::BounceIn => 1.0 - bounce_out(1.0 - t),
+            Easing::BounceOut => bounce_out(t),
+            Easing::BounceInOut => {
+                if t < 0.5 {
+                    (1.0 - bounce_out(1.0 - 2.0 * t)) / 2.0
+                } else {
+                    (1.0 + bounce_out(2.0 * t - 1.0)) / 2.0
+                }
+            }
+        }
+    }
+}
+
+fn bounce_out(t: f32) -> f32 {
+    const N: f32 = 7.5625;
+    const D: f32 = 2.75;
+
+    if t < 1.0 / D {
+        N * t * t
+    } else if t < 2.0 / D {
+        let t = t - 1.5 / D;
+        N * t * t + 0.75
+    } else if t < 2.5 / D {
+        let t = t - 2.25 / D;
+        N * t * t + 0.9375
+    } else {
+        let t = t - 2.625 / D;
+        N * t * t + 0.984375
+    }
+}
+
+/// Interpolates between two values over time.
+///
+/// Time only moves when the tween is advanced, usually once per update with the engine's frame
+/// delta (see `update`).
+pub struct Tween<T> {
+    pub from: T,
+    pub to: T,
+    pub duration_ms: u128,
+    pub easing: Easing,
+    pub delay_ms: u128,
+    /// Extra plays after the first one, `TWEEN_REPEAT_FOREVER` never stops.
+    pub repeat: u32,
+    /// Every other play runs backwards.
+    pub yoyo: bool,
+    elapsed_ms: u128,
+    on_complete: Option<Box<dyn FnMut()>>,
+    completed: bool,
+}
+
+impl<T: fmt::Debug> fmt::Debug for Tween<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Tween")
+            .field("from", &self.from)
+            .field("to", &self.to)
+            .field("duration_ms", &self.duration_ms)
+            .field("easing", &self.easing)
+            .field("delay_ms", &self.delay_ms)
+            .field("repeat", &self.repeat)
+            .field("yoyo", &self.yoyo)
+            .field("elapsed_ms", &self.elapsed_ms)
+            .finish()
+    }
+}
+
+impl<T: Lerp + Clone> Tween<T> {
+    pub fn new(from: T, to: T, duration_ms: u128, easing: Easing) -> Self {
+        Self {
+            from,
+            to,
+            duration_ms,
+            easing,
+            delay_ms: 0,
+            repeat: 0,
+            yoyo: false,
+            elapsed_ms: 0,
+            on_complete: None,
+            completed: false,
+        }
+    }
+
+    pub fn with_delay(mut self, delay_ms: u128) -> Self {
+        self.delay_ms = delay_ms;
+        self
+    }
+
+    pub fn with_repeat(mut self, repeat: u32) -> Self {
+        self.repeat = repeat;
+        self
+    }
+
+    pub fn with_yoyo(mut self, yoyo: bool) -> Self {
+        self.yoyo = yoyo;
+        self
+    }
+
+    /// Called once, in the advance call that finishes the tween.
+    pub fn on_complete(mut self, callback: impl FnMut() + 'static) -> Self {
+        self.on_complete = Some(Box::new(callback));
+        self
+    }
+
+    /// Advances by the time elapsed since the previous frame.
+    pub fn update(&mut self, events: &EventGroup) -> T {
+        self.advance(events.delta_ms);
+        self.value()
+    }
+
+    /// Returns the time left over after the tween finished, 0 while it is running.
+    pub fn advance(&mut self, delta_ms: u128) -> u128 {
+        self.elapsed_ms = self.elapsed_ms.saturating_add(delta_ms);
+
+        if self.completed || !self.is_finished() {
+            return 0;
+        }
+
+        self.completed = true;
+        if let Some(on_complete) = self.on_complete.as_mut() {
+            on_complete();
+        }
+
+        self.elapsed_ms - self.total_duration_ms().unwrap_or(self.elapsed_ms)
+    }
+
+    pub fn value(&self) -> T {
+        if self.elapsed_ms < self.delay_ms {
+            return self.from.clone();
+        }
+
+        if self.duration_ms == 0 || self.is_finished() {
+            return self.end_value();
+        }
+
+        let active_ms = self.elapsed_ms - self.delay_ms;
+        let play = active_ms / self.duration_ms;
+        let mut t = (active_ms % self.duration_ms) as f32 / self.duration_ms as f32;
+        if self.yoyo && play % 2 == 1 {
+            t = 1.0 - t;
+        }
+
+        self.from.lerp(&self.to, self.easing.apply(t))
+    }
+
+    /// `None` for tweens repeating forever.
+    pub fn total_duration_ms(&self) -> Option<u128> {
+        if self.repeat == TWEEN_REPEAT_FOREVER {
+            None
+        } else {
+            Some(self.delay_ms + self.duration_ms * (self.repeat as u128 + 1))
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.total_duration_ms()
+            .map(|total| self.elapsed_ms >= total)
+            .unwrap_or(false)
+    }
+
+    /// Starts over, the completion callback can fire again.
+    pub fn restart(&mut self) {
+        self.elapsed_ms = 0;
+        self.completed = false;
+    }
+
+    fn end_value(&self) -> T {
+        if self.yoyo && self.repeat % 2 == 1 {
+            self.from.clone()
+        } else {
+            self.to.clone()
+        }
+    }
+}
+
+/// Tweens played one after the other.
+#[derive(Debug)]
+pub struct TweenSequence<T> {
+    pub tweens: Vec<Tween<T>>,
+    current: usize,
+}
+
+impl<T: Lerp + Clone> TweenSequence<T> {
+    pub fn new(tweens: Vec<Tween<T>>) -> Self {
+        Self { tweens, current: 0 }
+    }
+
+    pub fn then(mut self, tween: Tween<T>) -> Self {
+        self.tweens.push(tween);
+        self
+    }
+
+    pub fn update(&mut self, events: &EventGroup) -> Option<T> {
+        self.advance(events.delta_ms);
+        self.value()
+    }
+
+    pub fn advance(&mut self, delta_ms: u128) {
+        let mut delta_ms = delta_ms;
+
+        while let Some(tween) = self.tweens.get_mut(self.current) {
+            delta_ms = tween.advance(delta_ms);
+            if !tween.is_finished() || self.current + 1 == self.tweens.len() {
+                break;
+            }
+            self.current += 1;
+        }
+    }
+
+    /// `None` if the sequence is empty.
+    pub fn value(&self) -> Option<T> {
+        self.tweens.get(self.current).map(|tween| tween.value())
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.tweens.iter().all(|tween| tween.is_finished())
+    }
+
+    pub fn restart(&mut self) {
+        self.current = 0;
+        for tween in &mut self.tweens {
+            tween.restart();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    use super::*;
+
+    const EASINGS: [Easing; 13] = [
+        Easing::Linear,
+        Easing::QuadIn,
+        Easing::QuadOut,
+        Easing::QuadInOut,
+        Easing::CubicIn,
+        Easing::CubicOut,
+        Easing::CubicInOut,
+        Easing::ElasticIn,
+        Easing::ElasticOut,
+        Easing::ElasticInOut,
+        Easing::BounceIn,
+        Easing::BounceOut,
+        Easing::BounceInOut,
+    ];
+
+    #[test]
+    fn easings_start_and_end_at_the_endpoints() {
+        for easing in EASINGS {
+            assert!(easing.apply(0.0).abs() < 1e-5, "{:?} at 0", easing);
+            assert!((easing.apply(1.0) - 1.0).abs() < 1e-5, "{:?} at 1", easing);
+
+            let tween = Tween::new(10.0, 20.0, 100, easing);
+            assert!((tween.value() - 10.0).abs() < 1e-4, "{:?} start", easing);
+        }
+        assert_eq!(Easing::QuadIn.apply(0.5), 0.25);
+    }
+
+    #[test]
+    fn completes_once_after_the_delay() {
+        let completed = Rc::new(Cell::new(0));
+        let counter = completed.clone();
+        let mut tween = Tween::new(0.0, 10.0, 100, Easing::Linear)
+            .with_delay(50)
+            .on_complete(move || counter.set(counter.get() + 1));
+
+        assert_eq!(tween.advance(50), 0);
+        assert_eq!(tween.value(), 0.0);
+        assert_eq!(tween.advance(50), 0);
+        assert_eq!(tween.value(), 5.0);
+        assert_eq!(tween.advance(70), 20);
+        assert!(tween.is_finished());
+        assert_eq!(tween.value(), 10.0);
+
+        tween.advance(100);
+        assert_eq!(completed.get(), 1);
+
+        tween.restart();
+        assert_eq!(tween.value(), 0.0);
+        tween.advance(150);
+        assert_eq!(completed.get(), 2);
+    }
+
+    #[test]
+    fn repeats_and_loops() {
+        let mut tween = Tween::new(0.0, 10.0, 100, Easing::Linear).with_repeat(2);
+        assert_eq!(tween.total_duration_ms(), Some(300));
+
+        tween.advance(125);
+        assert_eq!(tween.value(), 2.5);
+        tween.advance(100);
+        assert_eq!(tween.value(), 2.5);
+        assert!(!tween.is_finished());
+        tween.advance(75);
+        assert!(tween.is_finished());
+        assert_eq!(tween.value(), 10.0);
+
+        let mut tween =
+            Tween::new(0.0, 10.0, 100, Easing::Linear).with_repeat(TWEEN_REPEAT_FOREVER);
+        tween.advance(1_000_050);
+        assert_eq!(tween.total_duration_ms(), None);
+        assert!(!tween.is_finished());
+        assert_eq!(tween.value(), 5.0);
+    }
+
+    #[test]
+    fn ping_pong_runs_every_other_play_backwards() {
+        let mut tween = Tween::new(0.0, 10.0, 100, Easing::Linear)
+            .with_repeat(1)
+            .with_yoyo(true);
+
+        tween.advance(25);
+        assert_eq!(tween.value(), 2.5);
+        tween.advance(100);
+        assert_eq!(tween.value(), 7.5);
+        tween.advance(75);
+        assert!(tween.is_finished());
+        // An odd number of repeats ends where it started.
+        assert_eq!(tween.value(), 0.0);
+
+        let mut tween = Tween::new(0.0, 10.0, 100, Easing::Linear)
+            .with_repeat(2)
+            .with_yoyo(true);
+        tween.advance(300);
+        assert_eq!(tween.value(), 10.0);
+    }
+
+    #[test]
+    fn sequence_carries_over_leftover_time() {
+        let mut sequence = TweenSequence::new(vec![Tween::new(0.0, 10.0, 100, Easing::Linear)])
+            .then(Tween::new(10.0, 0.0, 100, Easing::Linear));
+
+        sequence.advance(150);
+        assert_eq!(sequence.value(), Some(5.0));
+        sequence.advance(100);
+        assert!(sequence.is_finished());
+        assert_eq!(sequence.value(), Some(0.0));
+        assert_eq!(TweenSequence::<f32>::new(vec![]).value(), None);
+    }
+}
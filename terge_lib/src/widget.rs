@@ -0,0 +1,1441 @@
+use std::collections::HashMap;
+
+use crossterm::event::{
+    Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+};
+
+use crate::common::*;
+use crate::event_group::EventGroup;
+use crate::gfx::Gfx;
+use crate::rect::Rect;
+use crate::text::{WrapMode, wrap_text};
+
+/// Reverse video, used to highlight whatever has the keyboard focus.
+pub const WIDGET_FOCUS_COLOR_CODE: u8 = 7;
+
+const SCROLL_BAR_THUMB_CHAR: &str = "█";
+const SCROLL_BAR_TRACK_CHAR: &str = "│";
+const TEXT_INPUT_PADDING_CHAR: char = '_';
+const LIST_BOX_SELECTED_MARKER: &str = "> ";
+const LIST_BOX_UNSELECTED_MARKER: &str = "  ";
+
+pub type WidgetId = usize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Style {
+    pub color: u8,
+    /// Used instead of `color` while the widget has the keyboard focus.
+    pub focus_color: u8,
+}
+
+impl Style {
+    pub fn new(color: u8) -> Self {
+        Self {
+            color,
+            focus_color: WIDGET_FOCUS_COLOR_CODE,
+        }
+    }
+
+    fn color(&self, focused: bool) -> u8 {
+        if focused {
+            self.focus_color
+        } else {
+            self.color
+        }
+    }
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Self::new(DEFAULT_COLOR_CODE)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Align {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
+
+/// Piece of a label's text. Without a color it takes the label's.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span {
+    pub text: String,
+    pub color: Option<u8>,
+}
+
+impl Span {
+    pub fn plain(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            color: None,
+        }
+    }
+
+    pub fn colored(text: impl Into<String>, color: u8) -> Self {
+        Self {
+            text: text.into(),
+            color: Some(color),
+        }
+    }
+}
+
+/// Single line of text on the first row of its rect, cut off at the right edge.
+#[derive(Debug, Clone)]
+pub struct Label {
+    pub rect: Rect,
+    pub spans: Vec<Span>,
+    pub style: Style,
+    pub align: Align,
+    /// Paints the rest of the row with `style.color`, eg. for bars with a background color.
+    pub fill: bool,
+}
+
+impl Label {
+    pub fn new(rect: Rect, text: impl Into<String>) -> Self {
+        Self::from_spans(rect, vec![Span::plain(text)])
+    }
+
+    pub fn from_spans(rect: Rect, spans: Vec<Span>) -> Self {
+        Self {
+            rect,
+            spans,
+            style: Style::default(),
+            align: Align::Left,
+            fill: false,
+        }
+    }
+
+    pub fn with_style(mut self, style: Style) -> Self {
+        self.style = style;
+        self
+    }
+
+    pub fn with_align(mut self, align: Align) -> Self {
+        self.align = align;
+        self
+    }
+
+    pub fn with_fill(mut self) -> Self {
+        self.fill = true;
+        self
+    }
+
+    pub fn set_text(&mut self, text: impl Into<String>) {
+        self.spans = vec![Span::plain(text)];
+    }
+
+    pub fn text(&self) -> String {
+        self.spans.iter().map(|span| span.text.as_str()).collect()
+    }
+
+    fn draw(&self, gfx: &Gfx) {
+        let width = self.rect.width() as usize;
+        let text_width: usize = self.spans.iter().map(|span| char_count(&span.text)).sum();
+        let offset = match self.align {
+            Align::Left => 0,
+            Align::Center => width.saturating_sub(text_width) / 2,
+            Align::Right => width.saturating_sub(text_width),
+        };
+
+        if self.fill {
+            gfx.draw_text(
+                &" ".repeat(width),
+                self.rect.start.x,
+                self.rect.start.y,
+                self.style.color,
+            );
+        }
+
+        let mut x = offset;
+        for span in &self.spans {
+            if x >= width {
+                break;
+            }
+
+            let text = clip(&span.text, width - x);
+            gfx.draw_text(
+                &text,
+                self.rect.start.x + x as u16,
+                self.rect.start.y,
+                span.color.unwrap_or(self.style.color),
+            );
+            x += char_count(&text);
+        }
+    }
+}
+
+/// `[ label ]`, activated with Enter, Space or a click.
+#[derive(Debug, Clone)]
+pub struct Button {
+    pub rect: Rect,
+    pub label: String,
+    pub style: Style,
+}
+
+impl Button {
+    /// Sized to fit the label.
+    pub fn new(pos: U16Point, label: impl Into<String>) -> Self {
+        let label = label.into();
+        Self {
+            rect: Rect::new(pos, U16Point::new(text_width(&label).saturating_add(3), 0)),
+            label,
+            style: Style::default(),
+        }
+    }
+
+    pub fn with_style(mut self, style: Style) -> Self {
+        self.style = style;
+        self
+    }
+
+    fn on_key(&mut self, id: WidgetId, key: &KeyEvent, out: &mut Vec<UiEvent>) {
+        if is_activation_key(key) {
+            self.on_click(id, out);
+        }
+    }
+
+    fn on_click(&mut self, id: WidgetId, out: &mut Vec<UiEvent>) {
+        out.push(UiEvent::Clicked(id));
+    }
+
+    fn draw(&self, gfx: &Gfx, focused: bool) {
+        gfx.draw_text(
+            &clip(&format!("[ {} ]", self.label), self.rect.width() as usize),
+            self.rect.start.x,
+            self.rect.start.y,
+            self.style.color(focused),
+        );
+    }
+}
+
+/// `[x] label`, toggled with Enter, Space or a click.
+#[derive(Debug, Clone)]
+pub struct Checkbox {
+    pub rect: Rect,
+    pub label: String,
+    pub checked: bool,
+    pub style: Style,
+}
+
+impl Checkbox {
+    /// Sized to fit the label.
+    pub fn new(pos: U16Point, label: impl Into<String>, checked: bool) -> Self {
+        let label = label.into();
+        Self {
+            rect: Rect::new(pos, U16Point::new(text_width(&label).saturating_add(3), 0)),
+            label,
+            checked,
+            style: Style::default(),
+        }
+    }
+
+    pub fn with_style(mut self, style: Style) -> Self {
+        self.style = style;
+        self
+    }
+
+    fn on_key(&mut self, id: WidgetId, key: &KeyEvent, out: &mut Vec<UiEvent>) {
+        if is_activation_key(key) {
+            self.on_click(id, out);
+        }
+    }
+
+    fn on_click(&mut self, id: WidgetId, out: &mut Vec<UiEvent>) {
+        self.checked = !self.checked;
+        out.push(UiEvent::Toggled(id, self.checked));
+    }
+
+    fn draw(&self, gfx: &Gfx, focused: bool) {
+        let mark = if self.checked { 'x' } else { ' ' };
+        gfx.draw_text(
+            &clip(
+                &format!("[{}] {}", mark, self.label),
+                self.rect.width() as usize,
+            ),
+            self.rect.start.x,
+            self.rect.start.y,
+            self.style.color(focused),
+        );
+    }
+}
+
+/// Scrollable list with at most one selected item, one item per row.
+#[derive(Debug, Clone)]
+pub struct ListBox {
+    pub rect: Rect,
+    pub style: Style,
+    items: Vec<String>,
+    selected: Option<usize>,
+    scroll: usize,
+}
+
+impl ListBox {
+    pub fn new(rect: Rect, items: Vec<String>) -> Self {
+        Self {
+            rect,
+            style: Style::default(),
+            items,
+            selected: None,
+            scroll: 0,
+        }
+    }
+
+    pub fn with_style(mut self, style: Style) -> Self {
+        self.style = style;
+        self
+    }
+
+    pub fn with_selected(mut self, selected: Option<usize>) -> Self {
+        self.set_selected(selected);
+        self
+    }
+
+    pub fn items(&self) -> &[String] {
+        &self.items
+    }
+
+    /// Keeps the selection if it is still in range.
+    pub fn set_items(&mut self, items: Vec<String>) {
+        self.items = items;
+        self.set_selected(self.selected);
+    }
+
+    pub fn selected(&self) -> Option<usize> {
+        self.selected
+    }
+
+    pub fn selected_item(&self) -> Option<&str> {
+        self.selected.map(|i| self.items[i].as_str())
+    }
+
+    /// Out of range indices select the last item. Scrolls the selection into view.
+    pub fn set_selected(&mut self, selected: Option<usize>) {
+        self.selected = selected
+            .filter(|_| !self.items.is_empty())
+            .map(|i| i.min(self.items.len() - 1));
+
+        if let Some(selected) = self.selected {
+            if selected < self.scroll {
+                self.scroll = selected;
+            } else if selected >= self.scroll + self.rows() {
+                self.scroll = selected + 1 - self.rows();
+            }
+        }
+        self.scroll = self.scroll.min(self.max_scroll());
+    }
+
+    /// Index of the first visible item.
+    pub fn scroll(&self) -> usize {
+        self.scroll
+    }
+
+    pub fn scroll_by(&mut self, delta: isize) {
+        self.scroll = self
+            .scroll
+            .saturating_add_signed(delta)
+            .min(self.max_scroll());
+    }
+
+    fn rows(&self) -> usize {
+        self.rect.height() as usize
+    }
+
+    fn max_scroll(&self) -> usize {
+        self.items.len().saturating_sub(self.rows())
+    }
+
+    fn select_and_notify(&mut self, id: WidgetId, index: usize, out: &mut Vec<UiEvent>) {
+        let previous = self.selected;
+        self.set_selected(Some(index));
+
+        if let Some(selected) = self.selected
+            && previous != self.selected
+        {
+            out.push(UiEvent::Selected(id, selected));
+        }
+    }
+
+    fn on_key(&mut self, id: WidgetId, key: &KeyEvent, out: &mut Vec<UiEvent>) {
+        let rows = self.rows();
+        let target = match key.code {
+            KeyCode::Up => self.selected.map_or(0, |i| i.saturating_sub(1)),
+            KeyCode::Down => self.selected.map_or(0, |i| i + 1),
+            KeyCode::PageUp => self.selected.map_or(0, |i| i.saturating_sub(rows)),
+            KeyCode::PageDown => self.selected.map_or(0, |i| i + rows),
+            KeyCode::Home => 0,
+            KeyCode::End => usize::MAX,
+            KeyCode::Enter => {
+                if self.selected.is_some() {
+                    out.push(UiEvent::Submitted(id));
+                }
+                return;
+            }
+            _ => return,
+        };
+
+        self.select_and_notify(id, target, out);
+    }
+
+    fn on_press(&mut self, id: WidgetId, p: U16Point, out: &mut Vec<UiEvent>) {
+        let index = self.scroll + (p.y - self.rect.start.y) as usize;
+        if index < self.items.len() {
+            self.select_and_notify(id, index, out);
+        }
+    }
+
+    fn draw(&self, gfx: &Gfx, focused: bool) {
+        let rows = self.rows();
+        let has_scroll_bar = self.items.len() > rows;
+        let width = (self.rect.width() as usize).saturating_sub(has_scroll_bar as usize);
+
+        for row in 0..rows {
+            let index = self.scroll + row;
+            let is_selected = self.selected == Some(index);
+            let text = match self.items.get(index) {
+                Some(item) if is_selected => format!("{}{}", LIST_BOX_SELECTED_MARKER, item),
+                Some(item) => format!("{}{}", LIST_BOX_UNSELECTED_MARKER, item),
+                None => String::new(),
+            };
+
+            gfx.draw_text(
+                &pad(&text, width),
+                self.rect.start.x,
+                self.rect.start.y + row as u16,
+                self.style.color(focused && is_selected),
+            );
+        }
+
+        if has_scroll_bar {
+            draw_scroll_bar(
+                gfx,
+                &self.rect,
+                self.scroll,
+                self.items.len(),
+                self.style.color,
+            );
+        }
+    }
+}
+
+/// Single line text field on the first row of its rect, scrolls sideways when the text is wider.
+#[derive(Debug, Clone)]
+pub struct TextInput {
+    pub rect: Rect,
+    pub style: Style,
+    /// Characters, not bytes.
+    pub max_len: Option<usize>,
+    text: String,
+    cursor: usize,
+    scroll: usize,
+}
+
+impl TextInput {
+    pub fn new(rect: Rect) -> Self {
+        Self {
+            rect,
+            style: Style::default(),
+            max_len: None,
+            text: String::new(),
+            cursor: 0,
+            scroll: 0,
+        }
+    }
+
+    pub fn with_style(mut self, style: Style) -> Self {
+        self.style = style;
+        self
+    }
+
+    pub fn with_max_len(mut self, max_len: usize) -> Self {
+        self.max_len = Some(max_len);
+        self
+    }
+
+    pub fn with_text(mut self, text: impl Into<String>) -> Self {
+        self.set_text(text);
+        self
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Moves the cursor to the end.
+    pub fn set_text(&mut self, text: impl Into<String>) {
+        self.text = text.into();
+        self.cursor = char_count(&self.text);
+        self.keep_cursor_visible();
+    }
+
+    /// Position in characters.
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    fn byte_index(&self, char_index: usize) -> usize {
+        self.text
+            .char_indices()
+            .nth(char_index)
+            .map_or(self.text.len(), |(i, _)| i)
+    }
+
+    fn keep_cursor_visible(&mut self) {
+        let width = self.rect.width() as usize;
+        if self.cursor < self.scroll {
+            self.scroll = self.cursor;
+        } else if self.cursor >= self.scroll + width {
+            self.scroll = self.cursor + 1 - width;
+        }
+    }
+
+    fn on_key(&mut self, id: WidgetId, key: &KeyEvent, out: &mut Vec<UiEvent>) {
+        let len = char_count(&self.text);
+
+        match key.code {
+            KeyCode::Char(c)
+                if !key
+                    .modifiers
+                    .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+            {
+                if self.max_len.is_some_and(|max_len| len >= max_len) {
+                    return;
+                }
+                let i = self.byte_index(self.cursor);
+                self.text.insert(i, c);
+                self.cursor += 1;
+                out.push(UiEvent::Changed(id));
+            }
+            KeyCode::Backspace if self.cursor > 0 => {
+                self.cursor -= 1;
+                let i = self.byte_index(self.cursor);
+                self.text.remove(i);
+                out.push(UiEvent::Changed(id));
+            }
+            KeyCode::Delete if self.cursor < len => {
+                let i = self.byte_index(self.cursor);
+                self.text.remove(i);
+                out.push(UiEvent::Changed(id));
+            }
+            KeyCode::Left => self.cursor = self.cursor.saturating_sub(1),
+            KeyCode::Right => self.cursor = (self.cursor + 1).min(len),
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::End => self.cursor = len,
+            KeyCode::Enter => out.push(UiEvent::Submitted(id)),
+            _ => {}
+        }
+
+        self.keep_cursor_visible();
+    }
+
+    fn on_press(&mut self, p: U16Point) {
+        self.cursor =
+            (self.scroll + (p.x - self.rect.start.x) as usize).min(char_count(&self.text));
+        self.keep_cursor_visible();
+    }
+
+    fn draw(&self, gfx: &Gfx, focused: bool) {
+        let width = self.rect.width() as usize;
+        let visible: String = self
+            .text
+            .chars()
+            .skip(self.scroll)
+            .chain(std::iter::repeat(TEXT_INPUT_PADDING_CHAR))
+            .take(width)
+            .collect();
+
+        gfx.draw_text(
+            &visible,
+            self.rect.start.x,
+            self.rect.start.y,
+            self.style.color,
+        );
+
+        if focused {
+            let under_cursor = self.text.chars().nth(self.cursor).unwrap_or(' ');
+            gfx.draw_text(
+                under_cursor.encode_utf8(&mut [0; 4]),
+                self.rect.start.x + (self.cursor - self.scroll) as u16,
+                self.rect.start.y,
+                self.style.focus_color,
+            );
+        }
+    }
+}
+
+/// Read-only text, word wrapped to the width of the rect. The last column is kept for the scroll bar.
+#[derive(Debug, Clone)]
+pub struct TextView {
+    pub rect: Rect,
+    pub style: Style,
+    lines: Vec<String>,
+    scroll: usize,
+}
+
+impl TextView {
+    pub fn new(rect: Rect, text: &str) -> Self {
+        Self {
+            rect,
+            style: Style::default(),
+            lines: text.lines().map(String::from).collect(),
+            scroll: 0,
+        }
+    }
+
+    pub fn with_style(mut self, style: Style) -> Self {
+        self.style = style;
+        self
+    }
+
+    pub fn lines(&self) -> &[String] {
+        &self.lines
+    }
+
+    pub fn set_text(&mut self, text: &str) {
+        self.lines = text.lines().map(String::from).collect();
+        self.scroll = self.scroll.min(self.max_scroll());
+    }
+
+    pub fn push_line(&mut self, line: impl Into<String>) {
+        self.lines.push(line.into());
+    }
+
+    /// Index of the first visible (wrapped) row.
+    pub fn scroll(&self) -> usize {
+        self.scroll
+    }
+
+    pub fn scroll_by(&mut self, delta: isize) {
+        self.scroll = self
+            .scroll
+            .saturating_add_signed(delta)
+            .min(self.max_scroll());
+    }
+
+    pub fn scroll_to_end(&mut self) {
+        self.scroll = self.max_scroll();
+    }
+
+    fn rows(&self) -> usize {
+        self.rect.height() as usize
+    }
+
+    fn max_scroll(&self) -> usize {
+        self.wrapped_lines().len().saturating_sub(self.rows())
+    }
+
+    fn wrapped_lines(&self) -> Vec<String> {
+        let width = (self.rect.width() as usize).saturating_sub(1);
+        wrap_text(&self.lines.join("\n"), width, WrapMode::Word)
+    }
+
+    fn on_key(&mut self, key: &KeyEvent) {
+        let rows = self.rows() as isize;
+        match key.code {
+            KeyCode::Up => self.scroll_by(-1),
+            KeyCode::Down => self.scroll_by(1),
+            KeyCode::PageUp => self.scroll_by(-rows),
+            KeyCode::PageDown => self.scroll_by(rows),
+            KeyCode::Home => self.scroll = 0,
+            KeyCode::End => self.scroll_to_end(),
+            _ => {}
+        }
+    }
+
+    fn draw(&self, gfx: &Gfx, focused: bool) {
+        let wrapped = self.wrapped_lines();
+        let width = (self.rect.width() as usize).saturating_sub(1);
+
+        for row in 0..self.rows() {
+            let line = wrapped
+                .get(self.scroll + row)
+                .map_or("", |line| line.as_str());
+            gfx.draw_text(
+                &pad(line, width),
+                self.rect.start.x,
+                self.rect.start.y + row as u16,
+                self.style.color,
+            );
+        }
+
+        if wrapped.len() > self.rows() {
+            draw_scroll_bar(
+                gfx,
+                &self.rect,
+                self.scroll,
+                wrapped.len(),
+                self.style.color(focused),
+            );
+        }
+    }
+}
+
+/// Modal box with a message and a row of buttons, centered on the screen. While open it takes
+/// all input; Left/Right/Tab pick a button and Enter/Space or a click closes it.
+#[derive(Debug, Clone)]
+pub struct Dialog {
+    pub title: String,
+    pub lines: Vec<String>,
+    pub buttons: Vec<String>,
+    pub style: Style,
+    selected: usize,
+}
+
+impl Dialog {
+    /// Without buttons an "Ok" one is added.
+    pub fn new(title: impl Into<String>, text: &str, buttons: &[&str]) -> Self {
+        let buttons = if buttons.is_empty() {
+            vec!["Ok".to_string()]
+        } else {
+            buttons.iter().map(|button| button.to_string()).collect()
+        };
+
+        Self {
+            title: title.into(),
+            lines: text.lines().map(String::from).collect(),
+            buttons,
+            style: Style::default(),
+            selected: 0,
+        }
+    }
+
+    pub fn with_style(mut self, style: Style) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// Index of the highlighted button.
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    /// Where the dialog is drawn on a screen of the given size.
+    pub fn rect(&self, screen: &Rect) -> Rect {
+        let content_width = self
+            .lines
+            .iter()
+            .map(|line| text_width(line))
+            .max()
+            .unwrap_or(0)
+            .max(text_width(&self.title).saturating_add(2))
+            .max(self.buttons_width());
+        let size = U16Point::new(
+            content_width.saturating_add(3),
+            (self.lines.len() as u16).saturating_add(3),
+        );
+
+        Rect::new(screen.midpoint().saturating_sub(size / 2), size)
+    }
+
+    fn buttons_width(&self) -> u16 {
+        let gaps = 2 * (self.buttons.len() as u16).saturating_sub(1);
+        self.buttons
+            .iter()
+            .map(|button| text_width(button).saturating_add(4))
+            .fold(gaps, u16::saturating_add)
+    }
+
+    fn button_rects(&self, rect: &Rect) -> Vec<Rect> {
+        let y = rect.end().y.saturating_sub(1);
+        let mut x = rect.start.x + (rect.width() as u16).saturating_sub(self.buttons_width()) / 2;
+        let mut out = vec![];
+
+        for button in &self.buttons {
+            let width = text_width(button).saturating_add(4);
+            out.push(Rect::new(U16Point::new(x, y), U16Point::new(width - 1, 0)));
+            x = x.saturating_add(width + 2);
+        }
+
+        out
+    }
+
+    fn on_key(&mut self, key: &KeyEvent) -> Option<usize> {
+        let count = self.buttons.len().max(1);
+        match key.code {
+            KeyCode::Left | KeyCode::BackTab => self.selected = (self.selected + count - 1) % count,
+            KeyCode::Right | KeyCode::Tab => self.selected = (self.selected + 1) % count,
+            _ if is_activation_key(key) => return Some(self.selected),
+            _ => {}
+        }
+        None
+    }
+
+    fn on_press(&mut self, p: U16Point, screen: &Rect) -> Option<usize> {
+        let index = self
+            .button_rects(&self.rect(screen))
+            .iter()
+            .position(|rect| rect.is_point_on(p))?;
+        self.selected = index;
+        Some(index)
+    }
+
+    fn draw(&self, gfx: &Gfx) {
+        let rect = self.rect(&gfx.screen_rect());
+
+        let blank = " ".repeat(rect.width() as usize);
+        for y in rect.start.y..=rect.end().y {
+            gfx.draw_text(&blank, rect.start.x, y, self.style.color);
+        }
+        gfx.draw_rect(&rect, self.style.color);
+
+        if !self.title.is_empty() {
+            gfx.draw_text(
+                &format!(" {} ", self.title),
+                rect.start.x + 2,
+                rect.start.y,
+                self.style.color,
+            );
+        }
+
+        for (i, line) in self.lines.iter().enumerate() {
+            gfx.draw_text(
+                line,
+                rect.start.x + 2,
+                rect.start.y + 1 + i as u16,
+                self.style.color,
+            );
+        }
+
+        for (i, (button, button_rect)) in self
+            .buttons
+            .iter()
+            .zip(self.button_rects(&rect))
+            .enumerate()
+        {
+            gfx.draw_text(
+                &format!("[ {} ]", button),
+                button_rect.start.x,
+                button_rect.start.y,
+                self.style.color(i == self.selected),
+            );
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Widget {
+    Label(Label),
+    Button(Button),
+    Checkbox(Checkbox),
+    ListBox(ListBox),
+    TextInput(TextInput),
+    TextView(TextView),
+}
+
+impl Widget {
+    pub fn rect(&self) -> Rect {
+        match self {
+            Widget::Label(widget) => widget.rect,
+            Widget::Button(widget) => widget.rect,
+            Widget::Checkbox(widget) => widget.rect,
+            Widget::ListBox(widget) => widget.rect,
+            Widget::TextInput(widget) => widget.rect,
+            Widget::TextView(widget) => widget.rect,
+        }
+    }
+
+    /// Labels are skipped by focus traversal.
+    pub fn is_focusable(&self) -> bool {
+        !matches!(self, Widget::Label(_))
+    }
+
+    fn on_key(&mut self, id: WidgetId, key: &KeyEvent, out: &mut Vec<UiEvent>) {
+        match self {
+            Widget::Label(_) => {}
+            Widget::Button(widget) => widget.on_key(id, key, out),
+            Widget::Checkbox(widget) => widget.on_key(id, key, out),
+            Widget::ListBox(widget) => widget.on_key(id, key, out),
+            Widget::TextInput(widget) => widget.on_key(id, key, out),
+            Widget::TextView(widget) => widget.on_key(key),
+        }
+    }
+
+    /// Left button went down on the widget.
+    fn on_press(&mut self, id: WidgetId, p: U16Point, out: &mut Vec<UiEvent>) {
+        match self {
+            Widget::ListBox(widget) => widget.on_press(id, p, out),
+            Widget::TextInput(widget) => widget.on_press(p),
+            _ => {}
+        }
+    }
+
+    /// Left button went down and up on the widget.
+    fn on_click(&mut self, id: WidgetId, out: &mut Vec<UiEvent>) {
+        match self {
+            Widget::Button(widget) => widget.on_click(id, out),
+            Widget::Checkbox(widget) => widget.on_click(id, out),
+            _ => {}
+        }
+    }
+
+    fn on_scroll(&mut self, delta: isize) {
+        match self {
+            Widget::ListBox(widget) => widget.scroll_by(delta),
+            Widget::TextView(widget) => widget.scroll_by(delta),
+            _ => {}
+        }
+    }
+
+    fn draw(&self, gfx: &Gfx, focused: bool) {
+        match self {
+            Widget::Label(widget) => widget.draw(gfx),
+            Widget::Button(widget) => widget.draw(gfx, focused),
+            Widget::Checkbox(widget) => widget.draw(gfx, focused),
+            Widget::ListBox(widget) => widget.draw(gfx, focused),
+            Widget::TextInput(widget) => widget.draw(gfx, focused),
+            Widget::TextView(widget) => widget.draw(gfx, focused),
+        }
+    }
+}
+
+/// Widget types that can be looked up by id, see `Ui::get`.
+pub trait WidgetKind: Into<Widget> {
+    fn from_widget(widget: &Widget) -> Option<&Self>;
+    fn from_widget_mut(widget: &mut Widget) -> Option<&mut Self>;
+}
+
+macro_rules! impl_widget_kind {
+    ($($kind:ident),*) => {
+        $(
+            impl From<$kind> for Widget {
+                fn from(widget: $kind) -> Self {
+                    Widget::$kind(widget)
+                }
+            }
+
+            impl WidgetKind for $kind {
+                fn from_widget(widget: &Widget) -> Option<&Self> {
+                    match widget {
+                        Widget::$kind(widget) => Some(widget),
+                        _ => None,
+                    }
+                }
+
+                fn from_widget_mut(widget: &mut Widget) -> Option<&mut Self> {
+                    match widget {
+                        Widget::$kind(widget) => Some(widget),
+                        _ => None,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_widget_kind!(Label, Button, Checkbox, ListBox, TextInput, TextView);
+
+/// What happened to the widgets during `Ui::update`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UiEvent {
+    Clicked(WidgetId),
+    Toggled(WidgetId, bool),
+    /// Index of the newly selected list item.
+    Selected(WidgetId, usize),
+    /// Text input edited.
+    Changed(WidgetId),
+    /// Enter in a text input or on a list item.
+    Submitted(WidgetId),
+    /// Index of the button that closed the dialog.
+    DialogClosed(WidgetId, usize),
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    widget: Widget,
+    visible: bool,
+}
+
+/// Retained set of widgets. Apps add widgets once, feed every frame's events through `update`,
+/// react to the returned `UiEvent`s and call `draw` after their own drawing.
+///
+/// Tab and Shift+Tab move the keyboard focus between the visible widgets in the order they were
+/// added, clicks focus the widget under the mouse.
+#[derive(Debug, Default)]
+pub struct Ui {
+    widgets: HashMap<WidgetId, Entry>,
+    order: Vec<WidgetId>,
+    dialogs: Vec<(WidgetId, Dialog)>,
+    focused: Option<WidgetId>,
+    pressed: Option<WidgetId>,
+    next_id: WidgetId,
+}
+
+impl Ui {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn next_id(&mut self) -> WidgetId {
+        self.next_id += 1;
+        self.next_id
+    }
+
+    /// Widgets added later are drawn over earlier ones and come later in the focus order.
+    pub fn add(&mut self, widget: impl Into<Widget>) -> WidgetId {
+        let id = self.next_id();
+        self.widgets.insert(
+            id,
+            Entry {
+                widget: widget.into(),
+                visible: true,
+            },
+        );
+        self.order.push(id);
+        id
+    }
+
+    pub fn remove(&mut self, id: WidgetId) -> Option<Widget> {
+        let entry = self.widgets.remove(&id)?;
+        self.order.retain(|other| *other != id);
+        if self.focused == Some(id) {
+            self.focused = None;
+        }
+        if self.pressed == Some(id) {
+            self.pressed = None;
+        }
+        Some(entry.widget)
+    }
+
+    /// `None` if the id is unknown or belongs to another kind of widget.
+    pub fn get<T: WidgetKind>(&self, id: WidgetId) -> Option<&T> {
+        self.widgets
+            .get(&id)
+            .and_then(|entry| T::from_widget(&entry.widget))
+    }
+
+    pub fn get_mut<T: WidgetKind>(&mut self, id: WidgetId) -> Option<&mut T> {
+        self.widgets
+            .get_mut(&id)
+            .and_then(|entry| T::from_widget_mut(&mut entry.widget))
+    }
+
+    /// Hidden widgets are not drawn and get no input.
+    pub fn set_visible(&mut self, id: WidgetId, visible: bool) {
+        if let Some(entry) = self.widgets.get_mut(&id) {
+            entry.visible = visible;
+        }
+        if !visible && self.focused == Some(id) {
+            self.focused = None;
+        }
+    }
+
+    pub fn is_visible(&self, id: WidgetId) -> bool {
+        self.widgets.get(&id).is_some_and(|entry| entry.visible)
+    }
+
+    pub fn focused(&self) -> Option<WidgetId> {
+        self.focused
+    }
+
+    /// Returns false (and does nothing) if the widget can't take the focus.
+    pub fn focus(&mut self, id: WidgetId) -> bool {
+        if !self.is_focusable(id) {
+            return false;
+        }
+
+        self.focused = Some(id);
+        true
+    }
+
+    pub fn blur(&mut self) {
+        self.focused = None;
+    }
+
+    pub fn focus_next(&mut self) {
+        self.move_focus(1);
+    }
+
+    pub fn focus_prev(&mut self) {
+        self.move_focus(-1);
+    }
+
+    /// True while typing goes to a widget, so apps should skip their own key shortcuts.
+    pub fn wants_keyboard(&self) -> bool {
+        self.is_dialog_open()
+            || self.focused.is_some_and(|id| {
+                matches!(
+                    self.widgets.get(&id).map(|entry| &entry.widget),
+                    Some(Widget::TextInput(_))
+                )
+            })
+    }
+
+    /// Opens the dialog over everything else, including other dialogs.
+    pub fn open_dialog(&mut self, dialog: Dialog) -> WidgetId {
+        let id = self.next_id();
+        self.dialogs.push((id, dialog));
+        id
+    }
+
+    /// Closes without a `DialogClosed` event.
+    pub fn close_dialog(&mut self, id: WidgetId) -> bool {
+        let len = self.dialogs.len();
+        self.dialogs.retain(|(other, _)| *other != id);
+        self.dialogs.len() != len
+    }
+
+    pub fn is_dialog_open(&self) -> bool {
+        !self.dialogs.is_empty()
+    }
+
+    pub fn update(&mut self, events: &EventGroup, gfx: &Gfx) -> Vec<UiEvent> {
+        let mut out = vec![];
+
+        for event in &events.events {
+            match event {
+                Event::Key(key) if key.kind != KeyEventKind::Release => self.on_key(key, &mut out),
+                Event::Mouse(mouse) => self.on_mouse(mouse, &gfx.screen_rect(), &mut out),
+                _ => {}
+            }
+        }
+
+        out
+    }
+
+    pub fn draw(&self, gfx: &Gfx) {
+        for id in &self.order {
+            let entry = &self.widgets[id];
+            if entry.visible {
+                entry
+                    .widget
+                    .draw(gfx, self.focused == Some(*id) && !self.is_dialog_open());
+            }
+        }
+
+        for (_id, dialog) in &self.dialogs {
+            dialog.draw(gfx);
+        }
+    }
+
+    fn is_focusable(&self, id: WidgetId) -> bool {
+        self.widgets
+            .get(&id)
+            .is_some_and(|entry| entry.visible && entry.widget.is_focusable())
+    }
+
+    fn move_focus(&mut self, step: isize) {
+        let focusable: Vec<WidgetId> = self
+            .order
+            .iter()
+            .copied()
+            .filter(|id| self.is_focusable(*id))
+            .collect();
+        if focusable.is_empty() {
+            self.focused = None;
+            return;
+        }
+
+        let len = focusable.len() as isize;
+        let next = match self
+            .focused
+            .and_then(|id| focusable.iter().position(|other| *other == id))
+        {
+            Some(i) => (i as isize + step).rem_euclid(len),
+            None if step > 0 => 0,
+            None => len - 1,
+        };
+        self.focused = Some(focusable[next as usize]);
+    }
+
+    fn widget_at(&self, p: U16Point) -> Option<WidgetId> {
+        self.order.iter().rev().copied().find(|id| {
+            let entry = &self.widgets[id];
+            entry.visible && entry.widget.rect().is_point_on(p)
+        })
+    }
+
+    fn on_key(&mut self, key: &KeyEvent, out: &mut Vec<UiEvent>) {
+        if let Some((id, dialog)) = self.dialogs.last_mut() {
+            if let Some(button) = dialog.on_key(key) {
+                out.push(UiEvent::DialogClosed(*id, button));
+                self.dialogs.pop();
+            }
+            return;
+        }
+
+        match key.code {
+            KeyCode::Tab => self.focus_next(),
+            KeyCode::BackTab => self.focus_prev(),
+            _ => {
+                if let Some(id) = self.focused
+                    && let Some(entry) = self.widgets.get_mut(&id)
+                {
+                    entry.widget.on_key(id, key, out);
+                }
+            }
+        }
+    }
+
+    fn on_mouse(&mut self, mouse: &MouseEvent, screen: &Rect, out: &mut Vec<UiEvent>) {
+        let p = U16Point::new(mouse.column, mouse.row);
+
+        if let Some((id, dialog)) = self.dialogs.last_mut() {
+            if mouse.kind == MouseEventKind::Down(MouseButton::Left)
+                && let Some(button) = dialog.on_press(p, screen)
+            {
+                out.push(UiEvent::DialogClosed(*id, button));
+                self.dialogs.pop();
+            }
+            return;
+        }
+
+        let hit = self.widget_at(p);
+
+        match mouse.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                self.pressed = hit;
+                self.focused = hit.filter(|id| self.is_focusable(*id));
+
+                if let Some(id) = hit {
+                    self.widgets
+                        .get_mut(&id)
+                        .unwrap()
+                        .widget
+                        .on_press(id, p, out);
+                }
+            }
+            MouseEventKind::Up(MouseButton::Left) => {
+                if let Some(id) = self.pressed.take()
+                    && hit == Some(id)
+                {
+                    self.widgets.get_mut(&id).unwrap().widget.on_click(id, out);
+                }
+            }
+            MouseEventKind::ScrollUp | MouseEventKind::ScrollDown => {
+                let delta = if mouse.kind == MouseEventKind::ScrollUp {
+                    -1
+                } else {
+                    1
+                };
+                if let Some(id) = hit {
+                    self.widgets.get_mut(&id).unwrap().widget.on_scroll(delta);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn is_activation_key(key: &KeyEvent) -> bool {
+    matches!(key.code, KeyCode::Enter | KeyCode::Char(' '))
+}
+
+fn char_count(text: &str) -> usize {
+    text.chars().count()
+}
+
+fn text_width(text: &str) -> u16 {
+    char_count(text).min(u16::MAX as usize) as u16
+}
+
+fn clip(text: &str, width: usize) -> String {
+    text.chars().take(width).collect()
+}
+
+/// Clips or pads with spaces to exactly `width` characters.
+fn pad(text: &str, width: usize) -> String {
+    format!("{:<width$}", clip(text, width), width = width)
+}
+
+/// On the last column of the rect.
+fn draw_scroll_bar(gfx: &Gfx, rect: &Rect, scroll: usize, total: usize, color: u8) {
+    let rows = rect.height() as usize;
+    let thumb = if total <= rows {
+        0
+    } else {
+        scroll * (rows - 1) / (total - rows)
+    };
+
+    for row in 0..rows {
+        let ch = if row == thumb {
+            SCROLL_BAR_THUMB_CHAR
+        } else {
+            SCROLL_BAR_TRACK_CHAR
+        };
+        gfx.draw_text(ch, rect.end().x, rect.start.y + row as u16, color);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{click, key, mouse, rect_from_size};
+
+    fn gfx() -> Gfx {
+        let mut gfx = Gfx::new();
+        gfx.width = 80;
+        gfx.height = 24;
+        gfx
+    }
+
+    fn update(ui: &mut Ui, events: Vec<Event>) -> Vec<UiEvent> {
+        ui.update(
+            &EventGroup {
+                events,
+                ..Default::default()
+            },
+            &gfx(),
+        )
+    }
+
+    #[test]
+    fn tab_skips_labels_and_hidden_widgets() {
+        let mut ui = Ui::new();
+        let first = ui.add(Button::new(U16Point::new(0, 0), "First"));
+        ui.add(Label::new(rect_from_size(0, 1, 10, 0), "Label"));
+        let hidden = ui.add(Button::new(U16Point::new(0, 2), "Hidden"));
+        let last = ui.add(Checkbox::new(U16Point::new(0, 3), "Last", false));
+        ui.set_visible(hidden, false);
+
+        update(&mut ui, vec![key(KeyCode::Tab)]);
+        assert_eq!(ui.focused(), Some(first));
+        update(&mut ui, vec![key(KeyCode::Tab)]);
+        assert_eq!(ui.focused(), Some(last));
+        update(&mut ui, vec![key(KeyCode::Tab)]);
+        assert_eq!(ui.focused(), Some(first));
+        update(&mut ui, vec![key(KeyCode::BackTab)]);
+        assert_eq!(ui.focused(), Some(last));
+    }
+
+    #[test]
+    fn buttons_and_checkboxes_react_to_keys_and_clicks() {
+        let mut ui = Ui::new();
+        let button = ui.add(Button::new(U16Point::new(0, 0), "Ok"));
+        let checkbox = ui.add(Checkbox::new(U16Point::new(0, 2), "Sound", false));
+
+        assert_eq!(update(&mut ui, click(3, 0)), vec![UiEvent::Clicked(button)]);
+        assert_eq!(ui.focused(), Some(button));
+        assert_eq!(
+            update(&mut ui, vec![key(KeyCode::Enter)]),
+            vec![UiEvent::Clicked(button)]
+        );
+
+        assert_eq!(
+            update(&mut ui, click(1, 2)),
+            vec![UiEvent::Toggled(checkbox, true)]
+        );
+        assert_eq!(
+            update(&mut ui, vec![key(KeyCode::Char(' '))]),
+            vec![UiEvent::Toggled(checkbox, false)]
+        );
+        assert!(!ui.get::<Checkbox>(checkbox).unwrap().checked);
+
+        // Releasing somewhere else is not a click.
+        assert!(
+            update(
+                &mut ui,
+                vec![
+                    mouse(MouseEventKind::Down(MouseButton::Left), 3, 0),
+                    mouse(MouseEventKind::Up(MouseButton::Left), 40, 10),
+                ]
+            )
+            .is_empty()
+        );
+        assert!(ui.get::<Button>(checkbox).is_none());
+    }
+
+    #[test]
+    fn text_input_editing() {
+        let mut ui = Ui::new();
+        let input = ui.add(TextInput::new(rect_from_size(0, 0, 3, 0)).with_max_len(6));
+        ui.focus(input);
+        assert!(ui.wants_keyboard());
+
+        let typed: Vec<Event> = "héllo!?".chars().map(|c| key(KeyCode::Char(c))).collect();
+        assert_eq!(update(&mut ui, typed).len(), 6);
+        assert_eq!(ui.get::<TextInput>(input).unwrap().text(), "héllo!");
+
+        update(
+            &mut ui,
+            vec![
+                key(KeyCode::Home),
+                key(KeyCode::Right),
+                key(KeyCode::Delete),
+                key(KeyCode::End),
+                key(KeyCode::Backspace),
+            ],
+        );
+        let text_input = ui.get::<TextInput>(input).unwrap();
+        assert_eq!(text_input.text(), "hllo");
+        assert_eq!(text_input.cursor(), 4);
+        assert_eq!(text_input.scroll, 2);
+
+        assert_eq!(
+            update(&mut ui, vec![key(KeyCode::Enter)]),
+            vec![UiEvent::Submitted(input)]
+        );
+    }
+
+    #[test]
+    fn list_box_selection_scrolls() {
+        let mut ui = Ui::new();
+        let items = (0..10).map(|i| format!("Item {}", i)).collect();
+        let list = ui.add(ListBox::new(rect_from_size(0, 0, 10, 2), items));
+        ui.focus(list);
+
+        assert_eq!(
+            update(&mut ui, vec![key(KeyCode::Down)]),
+            vec![UiEvent::Selected(list, 0)]
+        );
+        update(&mut ui, vec![key(KeyCode::PageDown), key(KeyCode::Down)]);
+        let list_box = ui.get::<ListBox>(list).unwrap();
+        assert_eq!(list_box.selected(), Some(4));
+        assert_eq!(list_box.scroll(), 2);
+
+        assert_eq!(
+            update(&mut ui, click(2, 1)),
+            vec![UiEvent::Selected(list, 3)]
+        );
+        assert_eq!(
+            update(&mut ui, vec![key(KeyCode::End), key(KeyCode::End)]),
+            vec![UiEvent::Selected(list, 9)]
+        );
+        assert_eq!(
+            ui.get::<ListBox>(list).unwrap().selected_item(),
+            Some("Item 9")
+        );
+    }
+
+    #[test]
+    fn text_view_wraps_and_scrolls() {
+        let mut view = TextView::new(rect_from_size(0, 0, 4, 1), "abcdefgh\n\nx y");
+
+        assert_eq!(view.wrapped_lines(), vec!["abcd", "efgh", "", "x y"]);
+        view.scroll_by(10);
+        assert_eq!(view.scroll(), 2);
+        view.scroll_by(-1);
+        assert_eq!(view.scroll(), 1);
+    }
+
+    #[test]
+    fn dialog_is_modal() {
+        let mut ui = Ui::new();
+        let button = ui.add(Button::new(U16Point::new(0, 0), "Behind"));
+        let dialog = ui.open_dialog(Dialog::new("Quit?", "Progress is lost.", &["Yes", "No"]));
+
+        assert!(update(&mut ui, click(1, 0)).is_empty());
+        assert_eq!(ui.focused(), None);
+
+        assert_eq!(
+            update(&mut ui, vec![key(KeyCode::Right), key(KeyCode::Enter)]),
+            vec![UiEvent::DialogClosed(dialog, 1)]
+        );
+        assert!(!ui.is_dialog_open());
+        assert_eq!(update(&mut ui, click(1, 0)), vec![UiEvent::Clicked(button)]);
+    }
+
+    #[test]
+    fn dialog_buttons_can_be_clicked() {
+        let mut ui = Ui::new();
+        let dialog = Dialog::new("", "Saved.", &[]);
+        let button_rect = dialog.button_rects(&dialog.rect(&gfx().screen_rect()))[0];
+        let id = ui.open_dialog(dialog);
+
+        assert_eq!(
+            update(&mut ui, click(button_rect.start.x, button_rect.start.y)),
+            vec![UiEvent::DialogClosed(id, 0)]
+        );
+    }
+}